@@ -0,0 +1,88 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    net::TcpListener,
+};
+
+use rust_mcp_sdk::schema::Tool;
+
+/// The outcome of one of the checks made by the `doctor` subcommand.
+pub(crate) struct Check {
+    name: String,
+    result: Result<(), String>,
+}
+
+impl Check {
+    pub(crate) fn new(name: impl Into<String>, result: Result<(), String>) -> Self {
+        Self {
+            name: name.into(),
+            result,
+        }
+    }
+}
+
+/// Checks that the input schema of each tool is a valid JSON Schema and that tool names
+/// are unique.
+pub(crate) fn tool_checks(tools: &[Tool]) -> Vec<Check> {
+    let mut checks: Vec<_> = tools
+        .iter()
+        .map(|tool| {
+            let result = serde_json::to_value(&tool.input_schema)
+                .map_err(|err| err.to_string())
+                .and_then(|schema| {
+                    jsonschema::validator_for(&schema)
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                });
+
+            Check::new(
+                format!("tool `{}` has a valid input schema", tool.name),
+                result,
+            )
+        })
+        .collect();
+
+    let mut names = HashSet::new();
+    let duplicates: Vec<_> = tools
+        .iter()
+        .filter(|tool| !names.insert(tool.name.as_str()))
+        .map(|tool| format!("`{}`", tool.name))
+        .collect();
+
+    checks.push(Check::new(
+        "tool names are unique",
+        if duplicates.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("duplicated names: {}", duplicates.join(", ")))
+        },
+    ));
+
+    checks
+}
+
+pub(crate) fn port_check(host: &str, port: u16) -> Check {
+    Check::new(
+        format!("port {} can be bound on `{}`", port, host),
+        TcpListener::bind((host, port))
+            .map(|_| ())
+            .map_err(|err| err.to_string()),
+    )
+}
+
+/// Writes a line for each check and returns the number of failed checks.
+pub(crate) fn write_report(checks: &[Check], output: &mut impl Write) -> io::Result<usize> {
+    let mut failures = 0;
+
+    for check in checks {
+        match &check.result {
+            Ok(()) => writeln!(output, "[pass] {}", check.name)?,
+            Err(message) => {
+                failures += 1;
+                writeln!(output, "[fail] {}: {}", check.name, message)?
+            }
+        }
+    }
+
+    Ok(failures)
+}
@@ -14,22 +14,70 @@
 //! - **Rich help output**: Automatically generates help text with tool descriptions and usage instructions
 //! - **Timeout configuration**: Built-in support for request timeouts using [`humantime`](https://docs.rs/humantime/latest/humantime/) formats
 //! - **Zero configuration**: Works out of the box with any [`ToolBox`] implementation
+//! - **Argument validation**: The `validate` subcommand checks tool arguments against the tool's input schema
+//! - **Self-diagnosis**: The `doctor` subcommand checks that the server is able to start
+//! - **Capability inspection**: The `initialize-info` subcommand prints what the server advertises to clients
+//! - **Tool catalog**: [`describe_tools`] renders the list of tools shown in the help output
+//! - **Custom arguments**: Additional [`clap::Arg`]s can be added with [`run_with_args`]
+//! - **Custom command**: The command built by [`build_cli`] can be customized and run with [`run_with_command`]
+//! - **Exit codes**: [`run_with_exit_code`] returns the exit code instead of exiting the process
+//! - **Testable parsing**: [`resolve_config`] parses the arguments without starting the server
 
-use std::{env, ffi::OsString};
+mod doctor;
+mod validate;
 
-use clap::{Arg, Command};
+use std::{
+    env,
+    ffi::OsString,
+    fmt, fs,
+    io::{self, IsTerminal, Write},
+    net::IpAddr,
+    path::PathBuf,
+};
+
+pub use clap;
+use clap::{Arg, ArgAction, ArgMatches, ColorChoice, Command, ValueEnum};
 pub use mcp_utils::server_prelude::ServerBuilder;
-use mcp_utils::server_prelude::ToolBox;
+use mcp_utils::server_prelude::{HttpMode, IpNet, ToolBox};
 use rust_mcp_sdk::{
     error::McpSdkError,
     schema::{CallToolRequestParams, schema_utils::CallToolError},
 };
 
-const DEFAULT_PORT: u16 = 8080;
-
 const ARG_TIMEOUT: &str = "timeout";
 const ARG_HOST: &str = "host";
 const ARG_PORT: &str = "port";
+const ARG_HTTP: &str = "http";
+const ARG_STDIO: &str = "stdio";
+const ARG_COLOR: &str = "color";
+const ARG_TOOL: &str = "tool";
+const ARG_ARGS: &str = "args";
+const ARG_ARGS_FILE: &str = "args-file";
+const ARG_WORKER_THREADS: &str = "worker-threads";
+const ARG_SINGLE_THREADED: &str = "single-threaded";
+const ARG_TOOL_PREFIX: &str = "tool-prefix";
+const ARG_ALLOW_EXTERNAL: &str = "allow-external";
+const ARG_COMPRESSION: &str = "compression";
+const ARG_HTTP_MODE: &str = "http-mode";
+const ARG_SSE_KEEPALIVE: &str = "sse-keepalive";
+const ARG_CORS_ORIGIN: &str = "cors-origin";
+const ARG_ALLOW_IP: &str = "allow-ip";
+const ARG_MAX_CALL_DURATION: &str = "max-call-duration";
+const ARG_LIST_TOOL_NAMES: &str = "list-tool-names";
+const ARG_WARMUP: &str = "warmup";
+const ARG_WARMUP_STRICT: &str = "warmup-strict";
+const ARG_NO_TOOLS: &str = "no-tools";
+const ARG_LOCALE: &str = "locale";
+const ARG_TCP_BACKLOG: &str = "tcp-backlog";
+const ARG_TCP_NODELAY: &str = "tcp-nodelay";
+
+const CMD_VALIDATE: &str = "validate";
+const CMD_DOCTOR: &str = "doctor";
+const CMD_INITIALIZE_INFO: &str = "initialize-info";
+const CMD_MANIFEST: &str = "manifest";
+
+const HTTP_MODE_SSE: &str = "sse";
+const HTTP_MODE_STREAMABLE_HTTP: &str = "streamable-http";
 
 /// Runs an MCP server with automatically generated command-line interface.
 ///
@@ -50,8 +98,11 @@ const ARG_PORT: &str = "port";
 ///
 /// # Server Behavior
 ///
-/// - When called **without** `--host` or `--port` the server starts in stdio mode
-/// - When called **with** `--host` and/or `--port` the server starts an HTTP server with Server-Sent Events
+/// - When called with `--stdio` the server starts in stdio mode
+/// - When called with `--http` the server starts an HTTP server with Server-Sent Events, using the
+///   builder's default host and port unless `--host` or `--port` are provided
+/// - Otherwise, the server starts in stdio mode, unless `--host` and/or `--port` are provided,
+///   in which case it starts an HTTP server
 ///
 /// # Examples
 ///
@@ -84,216 +135,2076 @@ pub fn run<T>(builder: ServerBuilder) -> Result<(), String>
 where
     T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
 {
-    match inner_run::<T, _>(builder, env::args_os()) {
-        Ok(Ok(())) => Ok(()),
-        Ok(Err(start_error)) => {
-            eprintln!(
-                "{}",
-                start_error
-                    .rpc_error_message()
-                    .unwrap_or(&start_error.to_string())
-            );
-            Err(start_error.to_string())
-        }
+    run_with_args::<T>(builder, Vec::new(), |_| {})
+}
+
+/// Runs an MCP server like [`run`], with additional command-line arguments.
+///
+/// The `extra_args` are added to the generated command and `on_matches` is called with the
+/// parsed arguments before the server starts, so that the values of these custom arguments can
+/// be read. Arguments that reuse the name or a flag of a built-in argument (like `timeout`,
+/// `host` or `port`) are rejected with an error.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mcp_cli_builder::{clap::Arg, run_with_args, ServerBuilder};
+/// use mcp_utils::{tool_prelude::*, server_prelude::*};
+///
+/// # #[mcp_tool(name = "example", description = "An example tool")]
+/// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// # pub struct ExampleTool { pub message: String }
+/// # impl TextTool for ExampleTool {
+/// #     type Output = String;
+/// #     fn call(&self) -> Self::Output { self.message.clone() }
+/// # }
+/// setup_tools!(pub MyTools, [
+///     text(ExampleTool),
+/// ]);
+///
+/// fn main() -> Result<(), String> {
+///     let builder = ServerBuilder::new().with_name(env!("CARGO_PKG_NAME"));
+///     let mut region = None;
+///
+///     run_with_args::<MyTools>(
+///         builder,
+///         vec![Arg::new("region").long("region").help("Region to query")],
+///         |matches| region = matches.get_one::<String>("region").cloned(),
+///     )
+/// }
+/// ```
+pub fn run_with_args<T>(
+    builder: ServerBuilder,
+    extra_args: Vec<Arg>,
+    on_matches: impl FnOnce(&ArgMatches),
+) -> Result<(), String>
+where
+    T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+{
+    match inner_run::<T, _>(
+        builder,
+        extra_args,
+        on_matches,
+        env::args_os(),
+        &mut io::stdout(),
+    ) {
+        Ok(result) => result.map_err(|error| {
+            report_run_error(&error);
+            error.to_string()
+        }),
+        Err(clap_err) => clap_err.exit(),
+    }
+}
+
+/// Runs an MCP server like [`run`], and returns the exit code of the process instead of
+/// exiting when the arguments can't be parsed.
+///
+/// The exit code is `0` when the server stops successfully, the code chosen by `clap` when
+/// the arguments are invalid or when the help or the version is printed (`0` for the help
+/// and version output, `2` for usage errors), and `1` when the server fails. Errors are
+/// printed to the standard error before returning, so the caller only has to clean up and
+/// exit with the code.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mcp_cli_builder::{run_with_exit_code, ServerBuilder};
+/// use mcp_utils::{tool_prelude::*, server_prelude::*};
+///
+/// # #[mcp_tool(name = "example", description = "An example tool")]
+/// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// # pub struct ExampleTool { pub message: String }
+/// # impl TextTool for ExampleTool {
+/// #     type Output = String;
+/// #     fn call(&self) -> Self::Output { self.message.clone() }
+/// # }
+/// setup_tools!(pub MyTools, [
+///     text(ExampleTool),
+/// ]);
+///
+/// fn main() {
+///     let builder = ServerBuilder::new().with_name(env!("CARGO_PKG_NAME"));
+///
+///     let exit_code = run_with_exit_code::<MyTools>(builder);
+///
+///     // clean up resources here
+///
+///     std::process::exit(exit_code);
+/// }
+/// ```
+pub fn run_with_exit_code<T>(builder: ServerBuilder) -> i32
+where
+    T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+{
+    exit_code(inner_run::<T, _>(
+        builder,
+        Vec::new(),
+        |_| {},
+        env::args_os(),
+        &mut io::stdout(),
+    ))
+}
+
+/// Builds the command-line interface used by [`run`], to customize it before running the
+/// server with [`run_with_command`].
+///
+/// The styles of the help output follow the `--color` option found in the arguments of the
+/// process.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mcp_cli_builder::{build_cli, run_with_command, ServerBuilder};
+/// use mcp_utils::{tool_prelude::*, server_prelude::*};
+///
+/// # #[mcp_tool(name = "example", description = "An example tool")]
+/// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// # pub struct ExampleTool { pub message: String }
+/// # impl TextTool for ExampleTool {
+/// #     type Output = String;
+/// #     fn call(&self) -> Self::Output { self.message.clone() }
+/// # }
+/// setup_tools!(pub MyTools, [
+///     text(ExampleTool),
+/// ]);
+///
+/// fn main() -> Result<(), String> {
+///     let builder = ServerBuilder::new().with_name(env!("CARGO_PKG_NAME"));
+///     let command = build_cli::<MyTools>(&builder)
+///         .bin_name("my-server")
+///         .author("Jane Doe");
+///
+///     run_with_command::<MyTools>(command, builder)
+/// }
+/// ```
+pub fn build_cli<T>(builder: &ServerBuilder) -> Command
+where
+    T: ToolBox,
+{
+    let args: Vec<OsString> = env::args_os().collect();
+
+    build_command::<T>(builder, find_color_choice(&args))
+}
+
+/// Runs an MCP server like [`run`], with a command built by [`build_cli`] and customized by
+/// the caller.
+///
+/// The arguments built in the command are still interpreted by the server, so they should
+/// not be removed.
+pub fn run_with_command<T>(command: Command, builder: ServerBuilder) -> Result<(), String>
+where
+    T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+{
+    match run_command::<T, _>(builder, command, |_| {}, env::args_os(), &mut io::stdout()) {
+        Ok(result) => result.map_err(|error| {
+            report_run_error(&error);
+            error.to_string()
+        }),
         Err(clap_err) => clap_err.exit(),
     }
 }
 
+fn exit_code(result: Result<Result<(), RunError>, clap::Error>) -> i32 {
+    match result {
+        Ok(Ok(())) => 0,
+        Ok(Err(error)) => {
+            report_run_error(&error);
+            1
+        }
+        Err(clap_err) => {
+            // printing only fails when the standard output or error is closed
+            let _ = clap_err.print();
+            clap_err.exit_code()
+        }
+    }
+}
+
+fn report_run_error(error: &RunError) {
+    match error {
+        RunError::Server(start_error) => eprintln!(
+            "{}",
+            start_error
+                .rpc_error_message()
+                .unwrap_or(&start_error.to_string())
+        ),
+        RunError::Command(message) => eprintln!("{}", message),
+    }
+}
+
+#[derive(Debug)]
+enum RunError {
+    Server(McpSdkError),
+    Command(String),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Server(error) => write!(f, "{}", error),
+            Self::Command(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// The server configuration resolved from the command-line arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedConfig {
+    pub mode: ServerMode,
+    pub timeout: std::time::Duration,
+    pub max_call_duration: Option<std::time::Duration>,
+    pub tool_prefix: Option<String>,
+    pub compression: bool,
+    pub http_mode: HttpMode,
+    pub sse_keepalive: Option<std::time::Duration>,
+    pub cors_origins: Vec<String>,
+    pub allowed_ips: Vec<IpNet>,
+    pub tcp_backlog: Option<u32>,
+    pub tcp_nodelay: bool,
+    pub warmup: Option<String>,
+    pub warmup_strict: bool,
+    pub no_tools: bool,
+    pub locale: Option<String>,
+    pub allow_external: bool,
+    pub runtime_flavor: RuntimeFlavor,
+}
+
+impl ResolvedConfig {
+    fn from_matches(builder: &ServerBuilder, matches: &ArgMatches) -> Self {
+        Self {
+            mode: server_mode(builder, matches),
+            timeout: matches
+                .get_one::<humantime::Duration>(ARG_TIMEOUT)
+                .cloned()
+                .map(Into::into)
+                .unwrap_or_else(|| std::time::Duration::from_secs(60)),
+            max_call_duration: matches
+                .get_one::<humantime::Duration>(ARG_MAX_CALL_DURATION)
+                .cloned()
+                .map(Into::into),
+            tool_prefix: matches.get_one::<String>(ARG_TOOL_PREFIX).cloned(),
+            compression: matches.get_flag(ARG_COMPRESSION),
+            http_mode: http_mode(builder, matches),
+            sse_keepalive: matches
+                .get_one::<humantime::Duration>(ARG_SSE_KEEPALIVE)
+                .cloned()
+                .map(Into::into),
+            cors_origins: matches
+                .get_many::<String>(ARG_CORS_ORIGIN)
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
+            allowed_ips: matches
+                .get_many::<IpNet>(ARG_ALLOW_IP)
+                .unwrap_or_default()
+                .copied()
+                .collect(),
+            tcp_backlog: matches.get_one::<u32>(ARG_TCP_BACKLOG).copied(),
+            tcp_nodelay: matches.get_flag(ARG_TCP_NODELAY),
+            warmup: matches.get_one::<String>(ARG_WARMUP).cloned(),
+            warmup_strict: matches.get_flag(ARG_WARMUP_STRICT),
+            no_tools: matches.get_flag(ARG_NO_TOOLS),
+            locale: matches.get_one::<String>(ARG_LOCALE).cloned(),
+            allow_external: matches.get_flag(ARG_ALLOW_EXTERNAL),
+            runtime_flavor: runtime_flavor(matches),
+        }
+    }
+}
+
+/// Parses the command-line arguments into a [`ResolvedConfig`], without starting a runtime
+/// or a server.
+///
+/// The first argument is the name of the program, like with [`std::env::args_os()`]. Requests
+/// for the help or the version output are returned as errors, like with
+/// [`Command::try_get_matches_from`].
+///
+/// # Examples
+///
+/// ```rust
+/// use mcp_cli_builder::{resolve_config, ServerBuilder, ServerMode};
+/// use mcp_utils::{tool_prelude::*, server_prelude::*};
+///
+/// # #[mcp_tool(name = "example", description = "An example tool")]
+/// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// # pub struct ExampleTool { pub message: String }
+/// # impl TextTool for ExampleTool {
+/// #     type Output = String;
+/// #     fn call(&self) -> Self::Output { self.message.clone() }
+/// # }
+/// setup_tools!(pub MyTools, [
+///     text(ExampleTool),
+/// ]);
+///
+/// fn main() {
+///     let builder = ServerBuilder::new().with_name("example-server");
+///
+///     let config = resolve_config::<MyTools, _>(&builder, ["example-server", "--port", "3000"])
+///         .unwrap();
+///
+///     assert_eq!(
+///         config.mode,
+///         ServerMode::Http {
+///             host: "127.0.0.1".to_owned(),
+///             port: 3000,
+///         }
+///     );
+/// }
+/// ```
+pub fn resolve_config<T, IntoArg>(
+    builder: &ServerBuilder,
+    args: impl IntoIterator<Item = IntoArg>,
+) -> Result<ResolvedConfig, clap::Error>
+where
+    T: ToolBox,
+    IntoArg: Into<OsString> + Clone,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let color = find_color_choice(&args);
+
+    let matches = build_command::<T>(builder, color).try_get_matches_from(args)?;
+
+    Ok(ResolvedConfig::from_matches(builder, &matches))
+}
+
 fn inner_run<T, IntoArg>(
-    mut builder: ServerBuilder,
+    builder: ServerBuilder,
+    extra_args: Vec<Arg>,
+    on_matches: impl FnOnce(&ArgMatches),
     args: impl IntoIterator<Item = IntoArg>,
-) -> Result<Result<(), McpSdkError>, clap::Error>
+    output: &mut impl Write,
+) -> Result<Result<(), RunError>, clap::Error>
 where
     T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
     IntoArg: Into<OsString> + Clone,
 {
-    let bold = clap::builder::styling::Style::new().bold();
-    let underlined = clap::builder::styling::Style::new().underline();
-    let dimmed = clap::builder::styling::Style::new().dimmed();
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let color = find_color_choice(&args);
 
-    let tools = T::get_tools();
-    let mut tool_names: Vec<_> = tools
-        .iter()
-        .enumerate()
-        .map(|(i, tool)| {
-            if let Some(description) = tool.description.as_ref() {
-                format!(
-                    "{}. {underlined}{}{underlined:#}\n    {}",
-                    i + 1,
-                    tool.title.as_ref().unwrap_or(&tool.name).as_str(),
-                    description
-                )
-            } else {
-                format!(
-                    "{}. {underlined}{}{underlined:#}: {dimmed}no description available{dimmed:#}",
-                    i + 1,
-                    tool.title.as_ref().unwrap_or(&tool.name).as_str(),
-                )
-            }
-        })
-        .collect();
-    tool_names.sort();
+    let command = match add_extra_args(build_command::<T>(&builder, color), extra_args) {
+        Ok(command) => command,
+        Err(message) => return Ok(Err(RunError::Command(message))),
+    };
 
-    let matches = Command::new(builder.name().to_owned())
-        .about(format!(
-            r#"{underlined}{}{underlined:#}
+    run_command::<T, _>(builder, command, on_matches, args, output)
+}
 
-Start the MCP server in stdio mode by running the command:
-  {bold}{}{bold:#}
+fn run_command<T, IntoArg>(
+    mut builder: ServerBuilder,
+    command: Command,
+    on_matches: impl FnOnce(&ArgMatches),
+    args: impl IntoIterator<Item = IntoArg>,
+    output: &mut impl Write,
+) -> Result<Result<(), RunError>, clap::Error>
+where
+    T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+    IntoArg: Into<OsString> + Clone,
+{
+    let matches = command.try_get_matches_from(args)?;
 
-To use SSE (Server-Sent Events), pass the --host and/or the --port options
-  {bold}{} --port 8080{bold:#}
-"#,
-            builder.title(),
-            builder.name(),
-            builder.name(),
-        ))
-        .version(builder.version().to_owned())
-        .after_long_help(format!(
-            "MCP server: {}\n\n{bold}Instructions:{bold:#}\n{}\n\n{bold}Tools:{bold:#}\n{}",
-            builder.title(),
-            builder.instructions(),
-            tool_names.join("\n")
-        ))
-        .arg(
-            Arg::new(ARG_TIMEOUT)
-                .help("Timeout for requests made  (in humantime format, see <https://docs.rs/humantime/latest/humantime/>)")
-                .default_value("60s")
-                .long("timeout")
-                .value_parser(clap::value_parser!(humantime::Duration)),
-        )
-        .arg(
-            Arg::new(ARG_HOST)
-                .help("Host to bind the server to")
-                .long("host")
-                .value_parser(clap::value_parser!(String)),
-        )
-        .arg(
-            Arg::new(ARG_PORT)
-                .help("Port to bind the server to")
-                .long("port")
-                .short('p')
-                .value_parser(clap::value_parser!(u16)),
-        )
-        .try_get_matches_from(args)?;
+    on_matches(&matches);
 
-    let timeout = matches
-        .get_one::<humantime::Duration>(ARG_TIMEOUT)
-        .cloned()
-        .map(Into::into)
-        .unwrap_or_else(|| std::time::Duration::from_secs(60));
+    if let Some((CMD_VALIDATE, sub_matches)) = matches.subcommand() {
+        return Ok(run_validate::<T>(sub_matches, output));
+    }
 
-    builder.set_timeout(timeout);
+    let config = ResolvedConfig::from_matches(&builder, &matches);
 
-    let host = matches.get_one::<String>(ARG_HOST).cloned();
-    let port = matches.get_one::<u16>(ARG_PORT).cloned();
+    builder.set_timeout(config.timeout);
 
-    tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap()
-        .block_on(async {
-            Ok(match (host, port) {
-                (None, None) => builder.start_stdio::<T>().await,
-                (host, port) => {
-                    builder
-                        .start_server::<T>(
-                            host.as_deref().unwrap_or("127.0.0.1"),
-                            port.unwrap_or(DEFAULT_PORT),
-                        )
-                        .await
-                }
-            })
-        })
-}
+    if let Some(max_call_duration) = config.max_call_duration {
+        builder.set_max_call_duration(max_call_duration);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mcp_utils::server_prelude::setup_tools;
-    use mcp_utils::tool_prelude::*;
+    if let Some(tool_prefix) = &config.tool_prefix {
+        builder.set_tool_prefix(tool_prefix);
+    }
 
-    #[mcp_tool(
-        name = "test_tool",
-        description = "A test tool for demonstration",
-        title = "Test Tool"
-    )]
-    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
-    pub struct TestTool {
-        /// A message to process
-        pub message: String,
+    if config.compression {
+        builder.set_compression(true);
     }
 
-    impl StructuredTool for TestTool {
-        type Output = String;
+    builder.set_http_mode(config.http_mode);
 
-        fn call(&self) -> Self::Output {
-            format!("Processed: {}", self.message)
-        }
+    if let Some(sse_keepalive) = config.sse_keepalive {
+        builder.set_sse_keepalive(sse_keepalive);
     }
 
-    #[mcp_tool(name = "another_tool", description = "A tool that doubles a number")]
-    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
-    pub struct AnotherTool {
-        /// A value to double
-        pub value: i32,
+    if !config.cors_origins.is_empty() {
+        builder.set_cors(&config.cors_origins);
     }
 
-    impl StructuredTool for AnotherTool {
-        type Output = i32;
+    if !config.allowed_ips.is_empty() {
+        builder.set_client_allowlist(config.allowed_ips.iter().copied());
+    }
 
-        fn call(&self) -> Self::Output {
-            self.value * 2
-        }
+    if let Some(tcp_backlog) = config.tcp_backlog {
+        builder.set_tcp_backlog(tcp_backlog);
     }
 
-    // Use the setup_tools macro to create a proper ToolBox
-    setup_tools!(pub TestTools, [
-        structured(TestTool),
-        structured(AnotherTool),
-    ]);
+    if config.tcp_nodelay {
+        builder.set_tcp_nodelay(true);
+    }
 
-    fn get_builder() -> ServerBuilder {
-        ServerBuilder::new()
-            .with_name("test-server")
-            .with_title("Test MCP Server")
-            .with_version("1.0.0")
-            .with_instructions("This is a test server for demonstration purposes")
+    if let Some(warmup) = &config.warmup {
+        builder.set_warmup(warmup);
+        builder.set_warmup_strict(config.warmup_strict);
     }
 
-    #[test]
-    fn test_help_command_snapshot() {
+    if config.no_tools {
+        builder.set_tools_disabled(true);
+    }
+
+    if let Some(locale) = &config.locale {
+        builder.set_locale(locale);
+    }
+
+    if matches.get_flag(ARG_LIST_TOOL_NAMES) {
+        let tool_prefix = config.tool_prefix.as_deref().unwrap_or_default();
+
+        return Ok(write_tool_names::<T>(tool_prefix, output)
+            .map_err(|err| RunError::Command(err.to_string())));
+    }
+
+    if let Some((CMD_DOCTOR, _)) = matches.subcommand() {
+        return Ok(run_doctor::<T>(&config.mode, config.runtime_flavor, output));
+    }
+
+    if let Some((CMD_INITIALIZE_INFO, _)) = matches.subcommand() {
+        return Ok(write_initialize_info::<T>(&builder, output)
+            .map_err(|err| RunError::Command(err.to_string())));
+    }
+
+    if let Some((CMD_MANIFEST, _)) = matches.subcommand() {
+        return Ok(
+            write_manifest::<T>(&builder, output).map_err(|err| RunError::Command(err.to_string()))
+        );
+    }
+
+    if let ServerMode::Http { host, .. } = &config.mode {
+        match check_external_host(&builder, host, config.allow_external) {
+            Ok(Some(warning)) => eprintln!("{}", warning),
+            Ok(None) => {}
+            Err(message) => return Ok(Err(RunError::Command(message))),
+        }
+    }
+
+    let runtime = match build_runtime(config.runtime_flavor) {
+        Ok(runtime) => runtime,
+        Err(err) => return Ok(Err(RunError::Server(err.into()))),
+    };
+
+    let result = runtime.block_on(async {
+        match config.mode {
+            ServerMode::Stdio => builder.start_stdio::<T>().await,
+            ServerMode::Http { host, port } => builder.start_server::<T>(host, port).await,
+        }
+    });
+
+    Ok(result.map_err(RunError::Server))
+}
+
+fn add_extra_args(command: Command, extra_args: Vec<Arg>) -> Result<Command, String> {
+    // building a copy of the command includes the generated `help` and `version` arguments
+    let mut built_command = command.clone();
+    built_command.build();
+
+    for extra_arg in extra_args.iter() {
+        let reserved = built_command.get_arguments().find(|arg| {
+            arg.get_id() == extra_arg.get_id()
+                || (arg.get_long().is_some() && arg.get_long() == extra_arg.get_long())
+                || (arg.get_short().is_some() && arg.get_short() == extra_arg.get_short())
+        });
+
+        if let Some(reserved) = reserved {
+            return Err(format!(
+                "argument `{}` conflicts with the built-in argument `{}`",
+                extra_arg.get_id(),
+                reserved.get_id()
+            ));
+        }
+    }
+
+    Ok(command.args(extra_args))
+}
+
+/// The kind of async runtime the server runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeFlavor {
+    MultiThread { worker_threads: Option<usize> },
+    CurrentThread,
+}
+
+fn runtime_flavor(matches: &ArgMatches) -> RuntimeFlavor {
+    if matches.get_flag(ARG_SINGLE_THREADED) {
+        RuntimeFlavor::CurrentThread
+    } else {
+        RuntimeFlavor::MultiThread {
+            worker_threads: matches
+                .get_one::<u64>(ARG_WORKER_THREADS)
+                .map(|&threads| threads as usize),
+        }
+    }
+}
+
+fn build_runtime(flavor: RuntimeFlavor) -> io::Result<tokio::runtime::Runtime> {
+    let mut runtime_builder = match flavor {
+        RuntimeFlavor::MultiThread { worker_threads } => {
+            let mut runtime_builder = tokio::runtime::Builder::new_multi_thread();
+            if let Some(worker_threads) = worker_threads {
+                runtime_builder.worker_threads(worker_threads);
+            }
+            runtime_builder
+        }
+        RuntimeFlavor::CurrentThread => tokio::runtime::Builder::new_current_thread(),
+    };
+
+    runtime_builder.enable_all().build()
+}
+
+/// Writes the name of every tool as exposed to clients, sorted and one per line.
+fn write_tool_names<T>(tool_prefix: &str, output: &mut impl Write) -> io::Result<()>
+where
+    T: ToolBox,
+{
+    let mut names: Vec<_> = T::get_tools()
+        .into_iter()
+        .map(|tool| format!("{}{}", tool_prefix, tool.name))
+        .collect();
+    names.sort();
+
+    for name in names {
+        writeln!(output, "{}", name)?;
+    }
+
+    Ok(())
+}
+
+fn write_initialize_info<T>(builder: &ServerBuilder, output: &mut impl Write) -> io::Result<()>
+where
+    T: ToolBox,
+{
+    serde_json::to_writer_pretty(&mut *output, &builder.initialize_result::<T>())?;
+    writeln!(output)
+}
+
+/// Writes the server information and the catalog of tools listed to clients, as a JSON
+/// document meant to be published alongside the server.
+fn write_manifest<T>(builder: &ServerBuilder, output: &mut impl Write) -> io::Result<()>
+where
+    T: ToolBox,
+{
+    let initialize_result = builder.initialize_result::<T>();
+    let server_info = initialize_result.server_info;
+
+    let manifest = serde_json::json!({
+        "name": server_info.name,
+        "version": server_info.version,
+        "title": server_info.title,
+        "instructions": initialize_result.instructions,
+        "tools": builder.list_tools::<T>(),
+    });
+
+    serde_json::to_writer_pretty(&mut *output, &manifest)?;
+    writeln!(output)
+}
+
+fn run_validate<T>(matches: &ArgMatches, output: &mut impl Write) -> Result<(), RunError>
+where
+    T: ToolBox,
+{
+    let tool_name = matches
+        .get_one::<String>(ARG_TOOL)
+        .expect("tool argument is required");
+    let tool = T::find_tool(tool_name)
+        .ok_or_else(|| RunError::Command(format!("unknown tool `{}`", tool_name)))?;
+
+    let arguments: serde_json::Value = match matches.get_one::<PathBuf>(ARG_ARGS_FILE) {
+        Some(path) => {
+            let content = fs::read_to_string(path).map_err(|err| {
+                RunError::Command(format!(
+                    "unable to read arguments file `{}`: {}",
+                    path.display(),
+                    err
+                ))
+            })?;
+            serde_json::from_str(&content).map_err(|err| {
+                RunError::Command(format!(
+                    "invalid JSON arguments in `{}`: {}",
+                    path.display(),
+                    err
+                ))
+            })?
+        }
+        None => {
+            let arguments = matches
+                .get_one::<String>(ARG_ARGS)
+                .expect("args argument has a default value");
+
+            serde_json::from_str(arguments)
+                .map_err(|err| RunError::Command(format!("invalid JSON arguments: {}", err)))?
+        }
+    };
+
+    let violations = validate::validate_arguments(&tool, &arguments).map_err(RunError::Command)?;
+
+    if violations.is_empty() {
+        writeln!(output, "arguments are valid for tool `{}`", tool_name)
+            .map_err(|err| RunError::Command(err.to_string()))
+    } else {
+        Err(RunError::Command(format!(
+            "invalid arguments for tool `{}`:\n{}",
+            tool_name,
+            violations.join("\n")
+        )))
+    }
+}
+
+fn run_doctor<T>(
+    mode: &ServerMode,
+    flavor: RuntimeFlavor,
+    output: &mut impl Write,
+) -> Result<(), RunError>
+where
+    T: ToolBox,
+{
+    let mut checks = vec![doctor::Check::new(
+        "async runtime can be built",
+        build_runtime(flavor)
+            .map(|_| ())
+            .map_err(|err| err.to_string()),
+    )];
+    checks.extend(doctor::tool_checks(&T::get_tools()));
+
+    if let ServerMode::Http { host, port } = mode {
+        checks.push(doctor::port_check(host, *port));
+    }
+
+    let failures =
+        doctor::write_report(&checks, output).map_err(|err| RunError::Command(err.to_string()))?;
+
+    if failures == 0 {
+        Ok(())
+    } else {
+        Err(RunError::Command(format!(
+            "{} of {} checks failed",
+            failures,
+            checks.len()
+        )))
+    }
+}
+
+/// Renders the catalog of tools as displayed in the help output of the command run with
+/// `builder`.
+///
+/// Each tool is numbered in the order set with [`ServerBuilder::with_tool_sort`] and shown
+/// with its title (or its name when it has no title), followed by its description on the next
+/// line, as overridden with [`ServerBuilder::with_tool_description_override`].
+///
+/// # Examples
+///
+/// ```rust
+/// use mcp_cli_builder::describe_tools;
+/// use mcp_utils::{tool_prelude::*, server_prelude::*};
+///
+/// #[mcp_tool(name = "example", title = "Example Tool", description = "An example tool")]
+/// #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// pub struct ExampleTool {}
+/// # impl TextTool for ExampleTool {
+/// #     type Output = &'static str;
+/// #     fn call(&self) -> Self::Output { "example" }
+/// # }
+///
+/// setup_tools!(pub MyTools, [
+///     text(ExampleTool),
+/// ]);
+///
+/// fn main() {
+///     assert_eq!(
+///         describe_tools::<MyTools>(&ServerBuilder::new()),
+///         "1. Example Tool\n    An example tool"
+///     );
+/// }
+/// ```
+pub fn describe_tools<T>(builder: &ServerBuilder) -> String
+where
+    T: ToolBox,
+{
+    let plain = clap::builder::styling::Style::new();
+    format_tools::<T>(builder, plain, plain)
+}
+
+fn format_tools<T>(
+    builder: &ServerBuilder,
+    underlined: clap::builder::styling::Style,
+    dimmed: clap::builder::styling::Style,
+) -> String
+where
+    T: ToolBox,
+{
+    let mut tools = T::get_tools();
+    builder.tool_sort().sort(&mut tools);
+    for tool in tools.iter_mut() {
+        if let Some(description) = builder.tool_description_override(&tool.name) {
+            tool.description = Some(description.to_owned());
+        }
+    }
+
+    let tool_names: Vec<_> = tools
+        .iter()
+        .enumerate()
+        .map(|(i, tool)| {
+            if let Some(description) = tool.description.as_ref() {
+                format!(
+                    "{}. {underlined}{}{underlined:#}\n    {}",
+                    i + 1,
+                    tool.title.as_ref().unwrap_or(&tool.name).as_str(),
+                    description
+                )
+            } else {
+                format!(
+                    "{}. {underlined}{}{underlined:#}: {dimmed}no description available{dimmed:#}",
+                    i + 1,
+                    tool.title.as_ref().unwrap_or(&tool.name).as_str(),
+                )
+            }
+        })
+        .collect();
+
+    tool_names.join("\n")
+}
+
+fn build_command<T>(builder: &ServerBuilder, color: ColorChoice) -> Command
+where
+    T: ToolBox,
+{
+    let use_color = match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && io::stdout().is_terminal()
+        }
+    };
+    let style = |style: clap::builder::styling::Style| {
+        if use_color {
+            style
+        } else {
+            clap::builder::styling::Style::new()
+        }
+    };
+
+    let bold = style(clap::builder::styling::Style::new().bold());
+    let underlined = style(clap::builder::styling::Style::new().underline());
+    let dimmed = style(clap::builder::styling::Style::new().dimmed());
+
+    let tool_names = format_tools::<T>(builder, underlined, dimmed);
+
+    let command = if use_color {
+        Command::new(builder.name().to_owned()).color(ColorChoice::Always)
+    } else {
+        Command::new(builder.name().to_owned())
+            .color(ColorChoice::Never)
+            .styles(clap::builder::Styles::plain())
+    };
+
+    command
+        .about(format!(
+            r#"{underlined}{}{underlined:#}
+
+Start the MCP server in stdio mode by running the command:
+  {bold}{}{bold:#}
+
+To use SSE (Server-Sent Events), pass the --http flag or the --host and/or the --port options
+  {bold}{} --port {}{bold:#}
+"#,
+            builder.display_title(),
+            builder.name(),
+            builder.name(),
+            builder.default_port(),
+        ))
+        .version(builder.version().to_owned())
+        .after_long_help(format!(
+            "MCP server: {}\n\n{bold}Instructions:{bold:#}\n{}\n\n{bold}Tools:{bold:#}\n{}",
+            builder.display_title(),
+            builder.instructions(),
+            tool_names
+        ))
+        .arg(
+            Arg::new(ARG_TIMEOUT)
+                .help("Timeout for requests made  (in humantime format, see <https://docs.rs/humantime/latest/humantime/>)")
+                .default_value("60s")
+                .long("timeout")
+                .value_parser(clap::value_parser!(humantime::Duration)),
+        )
+        .arg(
+            Arg::new(ARG_MAX_CALL_DURATION)
+                .help("Maximum duration of a single call to an asynchronous tool (in humantime format)")
+                .long("max-call-duration")
+                .value_name("TIME")
+                .value_parser(clap::value_parser!(humantime::Duration)),
+        )
+        .arg(
+            Arg::new(ARG_HTTP)
+                .help("Start the server in HTTP mode, even without --host or --port")
+                .long("http")
+                .action(ArgAction::SetTrue)
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_STDIO)
+                .help("Start the server in stdio mode")
+                .long("stdio")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([ARG_HOST, ARG_PORT]),
+        )
+        .arg(
+            Arg::new(ARG_COMPRESSION)
+                .help("Compress HTTP responses when the client supports it")
+                .long("compression")
+                .action(ArgAction::SetTrue)
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_HTTP_MODE)
+                .help("HTTP transport to serve (`sse` also serves the legacy SSE endpoints)")
+                .long("http-mode")
+                .value_name("MODE")
+                .value_parser([HTTP_MODE_SSE, HTTP_MODE_STREAMABLE_HTTP])
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_SSE_KEEPALIVE)
+                .help("Interval of the keep-alive pings sent on event streams (in humantime format)")
+                .long("sse-keepalive")
+                .value_name("TIME")
+                .value_parser(clap::value_parser!(humantime::Duration))
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_CORS_ORIGIN)
+                .help("Allow browser clients from this origin (`*` for any), can be repeated")
+                .long("cors-origin")
+                .value_name("ORIGIN")
+                .action(ArgAction::Append)
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_ALLOW_IP)
+                .help("Only accept clients from this IP address or CIDR range, can be repeated")
+                .long("allow-ip")
+                .value_name("ADDRESS")
+                .value_parser(parse_ip_network)
+                .action(ArgAction::Append)
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_TCP_BACKLOG)
+                .help("Maximum number of pending connections of the HTTP server")
+                .long("tcp-backlog")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_TCP_NODELAY)
+                .help("Send HTTP responses without delay by disabling Nagle's algorithm")
+                .long("tcp-nodelay")
+                .action(ArgAction::SetTrue)
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_ALLOW_EXTERNAL)
+                .help("Allow binding the server to all network interfaces (like 0.0.0.0)")
+                .long("allow-external")
+                .action(ArgAction::SetTrue)
+                .conflicts_with(ARG_STDIO),
+        )
+        .arg(
+            Arg::new(ARG_TOOL_PREFIX)
+                .help("Prefix added to the name of every tool")
+                .long("tool-prefix")
+                .value_name("PREFIX"),
+        )
+        .arg(
+            Arg::new(ARG_WARMUP)
+                .help("Call a tool once with empty arguments before accepting requests")
+                .long("warmup")
+                .value_name("TOOL"),
+        )
+        .arg(
+            Arg::new(ARG_WARMUP_STRICT)
+                .help("Exit with an error when the warmup call fails")
+                .long("warmup-strict")
+                .action(ArgAction::SetTrue)
+                .requires(ARG_WARMUP),
+        )
+        .arg(
+            Arg::new(ARG_NO_TOOLS)
+                .help("Serve the server information only, without any tool")
+                .long("no-tools")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([ARG_WARMUP, ARG_LIST_TOOL_NAMES]),
+        )
+        .arg(
+            Arg::new(ARG_LOCALE)
+                .help("Locale of the tool descriptions for clients that don't send one")
+                .long("locale")
+                .value_name("LOCALE"),
+        )
+        .arg(
+            Arg::new(ARG_LIST_TOOL_NAMES)
+                .help("Print the sorted tool names, one per line, and exit")
+                .long("list-tool-names")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(ARG_WORKER_THREADS)
+                .help("Number of async runtime worker threads (defaults to the CPU count)")
+                .long("worker-threads")
+                .value_name("N")
+                .env("MCP_WORKER_THREADS")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new(ARG_SINGLE_THREADED)
+                .help("Run the async runtime on a single thread")
+                .long("single-threaded")
+                .action(ArgAction::SetTrue)
+                .conflicts_with(ARG_WORKER_THREADS),
+        )
+        .arg(
+            Arg::new(ARG_HOST)
+                .help("Host to bind the server to")
+                .long("host")
+                .value_parser(clap::value_parser!(String)),
+        )
+        .arg(
+            Arg::new(ARG_PORT)
+                .help("Port to bind the server to")
+                .long("port")
+                .short('p')
+                .value_parser(clap::value_parser!(u16)),
+        )
+        .subcommand(
+            Command::new(CMD_VALIDATE)
+                .about("Validate tool arguments against the tool's input schema without calling it")
+                .arg(
+                    Arg::new(ARG_TOOL)
+                        .help("Name of the tool")
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                )
+                .arg(
+                    Arg::new(ARG_ARGS)
+                        .help("Arguments of the tool call, as a JSON object")
+                        .long("args")
+                        .default_value("{}")
+                        .value_parser(clap::value_parser!(String)),
+                )
+                .arg(
+                    Arg::new(ARG_ARGS_FILE)
+                        .help("File containing the arguments of the tool call, as a JSON object")
+                        .long("args-file")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .conflicts_with(ARG_ARGS),
+                ),
+        )
+        .subcommand(
+            Command::new(CMD_DOCTOR)
+                .about("Check that the server is able to start and report any problem"),
+        )
+        .subcommand(
+            Command::new(CMD_INITIALIZE_INFO)
+                .about("Print the result of the `initialize` request as JSON without starting the server"),
+        )
+        .subcommand(
+            Command::new(CMD_MANIFEST)
+                .about("Print the server information and its tools as a JSON manifest for publishing"),
+        )
+        .arg(
+            Arg::new(ARG_COLOR)
+                .help("When to use colors in the help output (`auto` respects the NO_COLOR environment variable)")
+                .long("color")
+                .default_value("auto")
+                .value_parser(clap::value_parser!(ColorChoice)),
+        )
+}
+
+/// Finds the value of the `--color` option before the arguments are parsed, since
+/// the styles of the help output must be known when building the command.
+fn find_color_choice(args: &[OsString]) -> ColorChoice {
+    let mut args = args.iter().filter_map(|arg| arg.to_str());
+
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--color") {
+            Some("") => args.next(),
+            Some(value) => value.strip_prefix('='),
+            None => None,
+        };
+        if let Some(choice) = value.and_then(|value| ColorChoice::from_str(value, true).ok()) {
+            return choice;
+        }
+    }
+
+    ColorChoice::Auto
+}
+
+/// The transport used by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerMode {
+    Stdio,
+    Http { host: String, port: u16 },
+}
+
+fn server_mode(builder: &ServerBuilder, matches: &ArgMatches) -> ServerMode {
+    let host = matches.get_one::<String>(ARG_HOST).cloned();
+    let port = matches.get_one::<u16>(ARG_PORT).cloned();
+
+    // without an explicit mode, the server runs over HTTP only when a host or a port is given
+    let use_http = matches.get_flag(ARG_HTTP)
+        || (!matches.get_flag(ARG_STDIO) && (host.is_some() || port.is_some()));
+
+    if use_http {
+        ServerMode::Http {
+            host: host.unwrap_or_else(|| builder.default_host().to_owned()),
+            port: port.unwrap_or(builder.default_port()),
+        }
+    } else {
+        ServerMode::Stdio
+    }
+}
+
+/// Parses an IP address, as a network containing only this address, or a CIDR range.
+fn parse_ip_network(value: &str) -> Result<IpNet, String> {
+    value
+        .parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("`{}` is not an IP address or a CIDR range", value))
+}
+
+fn http_mode(builder: &ServerBuilder, matches: &ArgMatches) -> HttpMode {
+    match matches.get_one::<String>(ARG_HTTP_MODE).map(String::as_str) {
+        Some(HTTP_MODE_SSE) => HttpMode::Sse,
+        Some(HTTP_MODE_STREAMABLE_HTTP) => HttpMode::StreamableHttp,
+        _ => builder.http_mode(),
+    }
+}
+
+/// Checks if the server would be exposed on all network interfaces. Returns a warning to
+/// display, or an error when the builder requires an explicit opt-in.
+fn check_external_host(
+    builder: &ServerBuilder,
+    host: &str,
+    allow_external: bool,
+) -> Result<Option<String>, String> {
+    let is_unspecified = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .is_ok_and(|address| address.is_unspecified());
+
+    if !is_unspecified || allow_external {
+        Ok(None)
+    } else if builder.require_external_optin() {
+        Err(format!(
+            "refusing to bind the server to `{}`, which exposes it to other machines on the network (pass --allow-external to allow it)",
+            host
+        ))
+    } else {
+        Ok(Some(format!(
+            "warning: the server is bound to `{}`, which exposes it to other machines on the network (pass --allow-external to hide this warning)",
+            host
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_utils::server_prelude::{ToolSort, setup_tools};
+    use mcp_utils::tool_prelude::*;
+
+    #[mcp_tool(
+        name = "test_tool",
+        description = "A test tool for demonstration",
+        title = "Test Tool"
+    )]
+    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    pub struct TestTool {
+        /// A message to process
+        pub message: String,
+    }
+
+    impl StructuredTool for TestTool {
+        type Output = String;
+
+        fn call(&self) -> Self::Output {
+            format!("Processed: {}", self.message)
+        }
+    }
+
+    #[mcp_tool(name = "another_tool", description = "A tool that doubles a number")]
+    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    pub struct AnotherTool {
+        /// A value to double
+        pub value: i32,
+    }
+
+    impl StructuredTool for AnotherTool {
+        type Output = i32;
+
+        fn call(&self) -> Self::Output {
+            self.value * 2
+        }
+    }
+
+    // Use the setup_tools macro to create a proper ToolBox
+    setup_tools!(pub TestTools, [
+        structured(TestTool),
+        structured(AnotherTool),
+    ]);
+
+    fn get_builder() -> ServerBuilder {
+        ServerBuilder::new()
+            .with_name("test-server")
+            .with_title("Test MCP Server")
+            .with_version("1.0.0")
+            .with_instructions("This is a test server for demonstration purposes")
+    }
+
+    #[test]
+    fn test_help_command_snapshot() {
+        let builder = get_builder();
+
+        let help_output = match inner_run::<TestTools, _>(
+            builder,
+            Vec::new(),
+            |_| {},
+            ["test-server", "--help", "--color", "never"],
+            &mut io::sink(),
+        ) {
+            Err(e) => e.to_string(),
+            Ok(_) => panic!("Expected help error, but inner_run succeeded"),
+        };
+
+        insta::assert_snapshot!("help_output", help_output);
+    }
+
+    #[test]
+    fn test_short_help_command_snapshot() {
         let builder = get_builder();
 
-        let help_output = match inner_run::<TestTools, _>(builder, ["test-server", "--help"]) {
-            Err(e) => e.to_string(),
-            Ok(_) => panic!("Expected help error, but inner_run succeeded"),
-        };
+        let help_output = match inner_run::<TestTools, _>(
+            builder,
+            Vec::new(),
+            |_| {},
+            ["test-server", "-h", "--color", "never"],
+            &mut io::sink(),
+        ) {
+            Err(e) => e.to_string(),
+            Ok(_) => panic!("Expected help error, but inner_run succeeded"),
+        };
+
+        insta::assert_snapshot!("help_short_output", help_output);
+    }
+
+    #[test]
+    fn test_version_command_snapshot() {
+        let builder = get_builder();
+
+        let output = match inner_run::<TestTools, _>(
+            builder,
+            Vec::new(),
+            |_| {},
+            ["test-server", "--version"],
+            &mut io::sink(),
+        ) {
+            Err(e) => e.to_string(),
+            Ok(_) => panic!("Expected help error, but inner_run succeeded"),
+        };
+
+        insta::assert_snapshot!("version_output", output);
+    }
+
+    #[test]
+    fn test_short_help_without_title_snapshot() {
+        let builder = ServerBuilder::new()
+            .with_name("test-server")
+            .with_version("1.0.0");
+
+        let help_output = match inner_run::<TestTools, _>(
+            builder,
+            Vec::new(),
+            |_| {},
+            ["test-server", "-h", "--color", "never"],
+            &mut io::sink(),
+        ) {
+            Err(e) => e.to_string(),
+            Ok(_) => panic!("Expected help error, but inner_run succeeded"),
+        };
+
+        insta::assert_snapshot!("help_short_without_title_output", help_output);
+    }
+
+    fn get_mode(builder: &ServerBuilder, args: &[&str]) -> ServerMode {
+        resolve_config::<TestTools, _>(builder, args).unwrap().mode
+    }
+
+    #[test]
+    fn test_resolve_config_defaults() {
+        assert_eq!(
+            resolve_config::<TestTools, _>(&get_builder(), ["test-server"]).unwrap(),
+            ResolvedConfig {
+                mode: ServerMode::Stdio,
+                timeout: std::time::Duration::from_secs(60),
+                max_call_duration: None,
+                tool_prefix: None,
+                compression: false,
+                http_mode: HttpMode::Sse,
+                sse_keepalive: None,
+                cors_origins: Vec::new(),
+                allowed_ips: Vec::new(),
+                tcp_backlog: None,
+                tcp_nodelay: false,
+                warmup: None,
+                warmup_strict: false,
+                no_tools: false,
+                locale: None,
+                allow_external: false,
+                runtime_flavor: RuntimeFlavor::MultiThread {
+                    worker_threads: None
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_http_options() {
+        let args = [
+            "test-server",
+            "--host",
+            "0.0.0.0",
+            "--port",
+            "9000",
+            "--timeout",
+            "5s",
+            "--max-call-duration",
+            "2s",
+            "--tool-prefix",
+            "test_",
+            "--compression",
+            "--http-mode",
+            "streamable-http",
+            "--sse-keepalive",
+            "30s",
+            "--cors-origin",
+            "https://app.example",
+            "--cors-origin",
+            "http://localhost:3000",
+            "--allow-ip",
+            "10.0.0.0/8",
+            "--allow-ip",
+            "192.168.1.20",
+            "--tcp-backlog",
+            "4096",
+            "--tcp-nodelay",
+            "--warmup",
+            "echo",
+            "--warmup-strict",
+            "--locale",
+            "fr",
+            "--allow-external",
+            "--single-threaded",
+        ];
+
+        assert_eq!(
+            resolve_config::<TestTools, _>(&get_builder(), args).unwrap(),
+            ResolvedConfig {
+                mode: ServerMode::Http {
+                    host: "0.0.0.0".to_owned(),
+                    port: 9000
+                },
+                timeout: std::time::Duration::from_secs(5),
+                max_call_duration: Some(std::time::Duration::from_secs(2)),
+                tool_prefix: Some("test_".to_owned()),
+                compression: true,
+                http_mode: HttpMode::StreamableHttp,
+                sse_keepalive: Some(std::time::Duration::from_secs(30)),
+                cors_origins: vec![
+                    "https://app.example".to_owned(),
+                    "http://localhost:3000".to_owned()
+                ],
+                allowed_ips: vec![
+                    "10.0.0.0/8".parse().unwrap(),
+                    "192.168.1.20/32".parse().unwrap()
+                ],
+                tcp_backlog: Some(4096),
+                tcp_nodelay: true,
+                warmup: Some("echo".to_owned()),
+                warmup_strict: true,
+                no_tools: false,
+                locale: Some("fr".to_owned()),
+                allow_external: true,
+                runtime_flavor: RuntimeFlavor::CurrentThread,
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_stdio_with_worker_threads() {
+        let args = ["test-server", "--stdio", "--worker-threads", "4"];
+
+        let config = resolve_config::<TestTools, _>(&get_builder(), args).unwrap();
+
+        assert_eq!(config.mode, ServerMode::Stdio);
+        assert_eq!(
+            config.runtime_flavor,
+            RuntimeFlavor::MultiThread {
+                worker_threads: Some(4)
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_rejects_http_options_in_stdio_mode() {
+        let error = resolve_config::<TestTools, _>(
+            &get_builder(),
+            ["test-server", "--stdio", "--compression"],
+        )
+        .unwrap_err();
 
-        insta::assert_snapshot!("help_output", help_output);
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
     }
 
     #[test]
-    fn test_short_help_command_snapshot() {
-        let builder = get_builder();
+    fn test_resolve_config_requires_warmup_for_warmup_strict() {
+        let error =
+            resolve_config::<TestTools, _>(&get_builder(), ["test-server", "--warmup-strict"])
+                .unwrap_err();
 
-        let help_output = match inner_run::<TestTools, _>(builder, ["test-server", "-h"]) {
-            Err(e) => e.to_string(),
+        assert_eq!(
+            error.kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_no_tools() {
+        let config =
+            resolve_config::<TestTools, _>(&get_builder(), ["test-server", "--no-tools"]).unwrap();
+        assert!(config.no_tools);
+
+        let error = resolve_config::<TestTools, _>(
+            &get_builder(),
+            ["test-server", "--no-tools", "--warmup", "echo"],
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+
+        let error = resolve_config::<TestTools, _>(
+            &get_builder(),
+            ["test-server", "--no-tools", "--list-tool-names"],
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_resolve_config_rejects_invalid_timeout() {
+        let error =
+            resolve_config::<TestTools, _>(&get_builder(), ["test-server", "--timeout", "soon"])
+                .unwrap_err();
+
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+    }
+
+    #[test]
+    fn test_resolve_config_rejects_invalid_allowed_ip() {
+        let error = resolve_config::<TestTools, _>(
+            &get_builder(),
+            ["test-server", "--allow-ip", "10.0.0.0/33"],
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        assert!(
+            error
+                .to_string()
+                .contains("`10.0.0.0/33` is not an IP address or a CIDR range"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_returns_help_as_error() {
+        let error =
+            resolve_config::<TestTools, _>(&get_builder(), ["test-server", "--help"]).unwrap_err();
+
+        assert_eq!(error.kind(), clap::error::ErrorKind::DisplayHelp);
+    }
+
+    #[test]
+    fn test_http_mode_uses_builder_defaults() {
+        let builder = get_builder()
+            .with_default_host("0.0.0.0")
+            .with_default_port(3000);
+
+        assert_eq!(
+            get_mode(&builder, &["test-server", "--http"]),
+            ServerMode::Http {
+                host: "0.0.0.0".to_owned(),
+                port: 3000
+            }
+        );
+        assert_eq!(
+            get_mode(&builder, &["test-server", "--host", "localhost"]),
+            ServerMode::Http {
+                host: "localhost".to_owned(),
+                port: 3000
+            }
+        );
+        assert_eq!(
+            get_mode(&builder, &["test-server", "--port", "9000"]),
+            ServerMode::Http {
+                host: "0.0.0.0".to_owned(),
+                port: 9000
+            }
+        );
+    }
+
+    #[test]
+    fn test_http_flag_alone() {
+        assert_eq!(
+            get_mode(&get_builder(), &["test-server", "--http"]),
+            ServerMode::Http {
+                host: "127.0.0.1".to_owned(),
+                port: 8080
+            }
+        );
+    }
+
+    #[test]
+    fn test_stdio_flag_alone() {
+        assert_eq!(
+            get_mode(&get_builder(), &["test-server", "--stdio"]),
+            ServerMode::Stdio
+        );
+    }
+
+    #[test]
+    fn test_no_arguments_defaults_to_stdio() {
+        assert_eq!(
+            get_mode(&get_builder(), &["test-server"]),
+            ServerMode::Stdio
+        );
+    }
+
+    #[test]
+    fn test_stdio_flag_conflicts_with_port() {
+        let error = build_command::<TestTools>(&get_builder(), ColorChoice::Never)
+            .try_get_matches_from(["test-server", "--stdio", "--port", "9000"])
+            .unwrap_err();
+
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_http_flag_conflicts_with_stdio_flag() {
+        let error = build_command::<TestTools>(&get_builder(), ColorChoice::Never)
+            .try_get_matches_from(["test-server", "--http", "--stdio"])
+            .unwrap_err();
+
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    fn run_exit_code(args: &[&str]) -> i32 {
+        exit_code(inner_run::<TestTools, _>(
+            get_builder(),
+            Vec::new(),
+            |_| {},
+            args,
+            &mut io::sink(),
+        ))
+    }
+
+    #[test]
+    fn test_exit_code_of_success_is_zero() {
+        let mut output = Vec::new();
+
+        let code = exit_code(inner_run::<TestTools, _>(
+            get_builder(),
+            Vec::new(),
+            |_| {},
+            ["test-server", "--list-tool-names"],
+            &mut output,
+        ));
+
+        assert_eq!(code, 0);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "another_tool\ntest_tool\n"
+        );
+    }
+
+    #[test]
+    fn test_exit_code_of_help_is_zero() {
+        assert_eq!(run_exit_code(&["test-server", "--help"]), 0);
+    }
+
+    #[test]
+    fn test_exit_code_of_usage_error_comes_from_clap() {
+        assert_eq!(run_exit_code(&["test-server", "--unknown-option"]), 2);
+    }
+
+    #[test]
+    fn test_exit_code_of_run_error_is_one() {
+        assert_eq!(
+            run_exit_code(&["test-server", "validate", "unknown_tool"]),
+            1
+        );
+    }
+
+    fn render_help(args: &[&str]) -> String {
+        render_builder_help(get_builder(), args)
+    }
+
+    fn render_builder_help(builder: ServerBuilder, args: &[&str]) -> String {
+        match inner_run::<TestTools, _>(builder, Vec::new(), |_| {}, args, &mut io::sink()) {
+            Err(e) => e.render().ansi().to_string(),
             Ok(_) => panic!("Expected help error, but inner_run succeeded"),
+        }
+    }
+
+    #[test]
+    fn test_describe_tools_matches_help_catalog() {
+        let help_output = render_help(&["test-server", "--help", "--color", "never"]);
+
+        let catalog = describe_tools::<TestTools>(&get_builder());
+
+        assert_eq!(
+            catalog,
+            "1. Test Tool\n    A test tool for demonstration\n2. another_tool\n    A tool that doubles a number"
+        );
+        assert!(help_output.ends_with(&format!("Tools:\n{}\n", catalog)));
+
+        let builder = get_builder()
+            .with_tool_sort(ToolSort::Alphabetical)
+            .with_tool_description_override("another_tool", "Doubles a number");
+        let help_output = render_builder_help(
+            builder.clone(),
+            &["test-server", "--help", "--color", "never"],
+        );
+
+        let catalog = describe_tools::<TestTools>(&builder);
+
+        assert_eq!(
+            catalog,
+            "1. another_tool\n    Doubles a number\n2. Test Tool\n    A test tool for demonstration"
+        );
+        assert!(help_output.ends_with(&format!("Tools:\n{}\n", catalog)));
+    }
+
+    mod numbered {
+        use mcp_utils::server_prelude::setup_tools;
+        use mcp_utils::tool_prelude::*;
+
+        macro_rules! letter_tools {
+            ($($tool:ident => $name:tt),* $(,)?) => {
+                $(
+                    #[mcp_tool(name = $name, description = "A letter")]
+                    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+                    pub struct $tool {}
+
+                    impl TextTool for $tool {
+                        type Output = &'static str;
+
+                        fn call(&self) -> Self::Output {
+                            $name
+                        }
+                    }
+                )*
+
+                setup_tools!(pub LetterTools, [$(text($tool)),*]);
+            };
+        }
+
+        letter_tools!(
+            L => "l", K => "k", J => "j", I => "i", H => "h", G => "g",
+            F => "f", E => "e", D => "d", C => "c", B => "b", A => "a",
+        );
+    }
+
+    fn catalog_names(tool_sort: ToolSort) -> Vec<String> {
+        let plain = clap::builder::styling::Style::new();
+
+        format_tools::<numbered::LetterTools>(
+            &ServerBuilder::new().with_tool_sort(tool_sort),
+            plain,
+            plain,
+        )
+        .lines()
+        .filter(|line| !line.starts_with(' '))
+        .map(str::to_owned)
+        .collect()
+    }
+
+    #[test]
+    fn test_help_catalog_sorts_tools_alphabetically() {
+        assert_eq!(
+            catalog_names(ToolSort::Alphabetical),
+            [
+                "1. a", "2. b", "3. c", "4. d", "5. e", "6. f", "7. g", "8. h", "9. i", "10. j",
+                "11. k", "12. l",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_help_catalog_keeps_declaration_order() {
+        assert_eq!(
+            catalog_names(ToolSort::Declaration),
+            [
+                "1. l", "2. k", "3. j", "4. i", "5. h", "6. g", "7. f", "8. e", "9. d", "10. c",
+                "11. b", "12. a",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_help_catalog_uses_description_overrides() {
+        let plain = clap::builder::styling::Style::new();
+        let builder = ServerBuilder::new().with_tool_description_override("b", "Une lettre");
+
+        let catalog = format_tools::<numbered::LetterTools>(&builder, plain, plain);
+
+        assert!(catalog.contains("11. b\n    Une lettre"), "{catalog}");
+        assert!(catalog.contains("12. a\n    A letter"), "{catalog}");
+    }
+
+    #[test]
+    fn test_color_never_disables_styles() {
+        let help_output = render_help(&["test-server", "--help", "--color", "never"]);
+
+        assert!(!help_output.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_color_always_enables_styles() {
+        let help_output = render_help(&["test-server", "--help", "--color=always"]);
+
+        assert!(help_output.contains("\x1b[4mTest MCP Server"));
+    }
+
+    #[test]
+    fn test_find_color_choice() {
+        let find = |args: &[&str]| {
+            let args: Vec<OsString> = args.iter().map(Into::into).collect();
+            find_color_choice(&args)
         };
 
-        insta::assert_snapshot!("help_short_output", help_output);
+        assert_eq!(find(&["test-server"]), ColorChoice::Auto);
+        assert_eq!(
+            find(&["test-server", "--color", "never"]),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            find(&["test-server", "--color=always"]),
+            ColorChoice::Always
+        );
+        assert_eq!(
+            find(&["test-server", "--color", "invalid"]),
+            ColorChoice::Auto
+        );
+    }
+
+    fn list_tool_names(tool_prefix: &str) -> String {
+        let mut output = Vec::new();
+        write_tool_names::<TestTools>(tool_prefix, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
     }
 
     #[test]
-    fn test_version_command_snapshot() {
+    fn test_list_tool_names_prints_sorted_names() {
+        assert_eq!(list_tool_names(""), "another_tool\ntest_tool\n");
+    }
+
+    #[test]
+    fn test_list_tool_names_includes_tool_prefix() {
+        assert_eq!(list_tool_names("x_"), "x_another_tool\nx_test_tool\n");
+    }
+
+    #[test]
+    fn test_initialize_info_prints_the_initialize_result() {
+        let mut output = Vec::new();
+        write_initialize_info::<TestTools>(&get_builder(), &mut output).unwrap();
+
+        let result: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(result["serverInfo"]["name"], "test-server");
+        assert_eq!(result["capabilities"]["tools"], serde_json::json!({}));
+        assert_eq!(
+            result["protocolVersion"],
+            rust_mcp_sdk::schema::LATEST_PROTOCOL_VERSION
+        );
+    }
+
+    #[test]
+    fn test_manifest_lists_the_server_and_its_tools() {
+        let mut output = Vec::new();
+        write_manifest::<TestTools>(&get_builder(), &mut output).unwrap();
+
+        let manifest: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(manifest["name"], "test-server");
+
+        let tools = manifest["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), TestTools::get_tools().len());
+        assert!(tools.iter().all(|tool| tool["inputSchema"].is_object()));
+    }
+
+    #[test]
+    fn test_run_with_a_customized_command() {
         let builder = get_builder();
+        let command = build_cli::<TestTools>(&builder)
+            .bin_name("custom-server")
+            .color(ColorChoice::Never);
 
-        let output = match inner_run::<TestTools, _>(builder, ["test-server", "--version"]) {
-            Err(e) => e.to_string(),
-            Ok(_) => panic!("Expected help error, but inner_run succeeded"),
-        };
+        let error = run_command::<TestTools, _>(
+            builder,
+            command,
+            |_| {},
+            ["test-server", "--bad"],
+            &mut io::sink(),
+        )
+        .unwrap_err();
 
-        insta::assert_snapshot!("version_output", output);
+        assert_eq!(error.kind(), clap::error::ErrorKind::UnknownArgument);
+        assert!(
+            error.render().to_string().contains("Usage: custom-server"),
+            "{}",
+            error.render()
+        );
+    }
+
+    fn run_validate_command(args: &[&str]) -> Result<String, String> {
+        let matches = build_command::<TestTools>(&get_builder(), ColorChoice::Never)
+            .try_get_matches_from(args)
+            .unwrap();
+        let (_, sub_matches) = matches.subcommand().unwrap();
+
+        let mut output = Vec::new();
+        run_validate::<TestTools>(sub_matches, &mut output)
+            .map(|()| String::from_utf8(output).unwrap())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn test_validate_reports_missing_argument() {
+        let error =
+            run_validate_command(&["test-server", "validate", "another_tool", "--args", "{}"])
+                .unwrap_err();
+
+        assert_eq!(
+            error,
+            "invalid arguments for tool `another_tool`:\n(root): \"value\" is a required property"
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_invalid_argument_with_pointer() {
+        let error = run_validate_command(&[
+            "test-server",
+            "validate",
+            "another_tool",
+            "--args",
+            r#"{"value": "two"}"#,
+        ])
+        .unwrap_err();
+
+        assert_eq!(
+            error,
+            "invalid arguments for tool `another_tool`:\n/value: \"two\" is not of type \"integer\""
+        );
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_arguments() {
+        let output = run_validate_command(&[
+            "test-server",
+            "validate",
+            "another_tool",
+            "--args",
+            r#"{"value": 2}"#,
+        ])
+        .unwrap();
+
+        assert_eq!(output, "arguments are valid for tool `another_tool`\n");
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_tool() {
+        let error = run_validate_command(&["test-server", "validate", "unknown"]).unwrap_err();
+
+        assert_eq!(error, "unknown tool `unknown`");
+    }
+
+    fn run_validate_with_args_file(name: &str, content: Option<&str>) -> Result<String, String> {
+        let path = std::env::temp_dir().join(format!(
+            "mcp-cli-builder-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        if let Some(content) = content {
+            fs::write(&path, content).unwrap();
+        }
+
+        let result = run_validate_command(&[
+            "test-server",
+            "validate",
+            "another_tool",
+            "--args-file",
+            path.to_str().unwrap(),
+        ]);
+        let _ = fs::remove_file(&path);
+
+        result.map_err(|error| error.replace(path.to_str().unwrap(), "<path>"))
+    }
+
+    #[test]
+    fn test_validate_reads_arguments_from_file() {
+        let output = run_validate_with_args_file("valid", Some(r#"{"value": 2}"#)).unwrap();
+
+        assert_eq!(output, "arguments are valid for tool `another_tool`\n");
+    }
+
+    #[test]
+    fn test_validate_reports_missing_arguments_file() {
+        let error = run_validate_with_args_file("missing", None).unwrap_err();
+
+        assert!(
+            error.starts_with("unable to read arguments file `<path>`: "),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_malformed_arguments_file() {
+        let error = run_validate_with_args_file("malformed", Some("{ value: 2 }")).unwrap_err();
+
+        assert!(
+            error.starts_with("invalid JSON arguments in `<path>`: "),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_validate_args_file_conflicts_with_args() {
+        let error = build_command::<TestTools>(&get_builder(), ColorChoice::Never)
+            .try_get_matches_from([
+                "test-server",
+                "validate",
+                "another_tool",
+                "--args",
+                "{}",
+                "--args-file",
+                "args.json",
+            ])
+            .unwrap_err();
+
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    fn get_runtime_flavor(args: &[&str]) -> Result<RuntimeFlavor, clap::Error> {
+        resolve_config::<TestTools, _>(&get_builder(), args).map(|config| config.runtime_flavor)
+    }
+
+    #[test]
+    fn test_runtime_flavor_defaults_to_multi_thread() {
+        assert_eq!(
+            get_runtime_flavor(&["test-server"]).unwrap(),
+            RuntimeFlavor::MultiThread {
+                worker_threads: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_worker_threads_option() {
+        assert_eq!(
+            get_runtime_flavor(&["test-server", "--worker-threads", "2"]).unwrap(),
+            RuntimeFlavor::MultiThread {
+                worker_threads: Some(2)
+            }
+        );
+    }
+
+    #[test]
+    fn test_worker_threads_must_be_positive() {
+        let error = get_runtime_flavor(&["test-server", "--worker-threads", "0"]).unwrap_err();
+
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+    }
+
+    #[test]
+    fn test_single_threaded_builds_current_thread_runtime() {
+        let flavor = get_runtime_flavor(&["test-server", "--single-threaded"]).unwrap();
+
+        let runtime = build_runtime(flavor).unwrap();
+
+        assert_eq!(
+            runtime.handle().runtime_flavor(),
+            tokio::runtime::RuntimeFlavor::CurrentThread
+        );
+        assert_eq!(runtime.block_on(async { 1 + 1 }), 2);
+    }
+
+    fn get_extra_args_command(extra_args: Vec<Arg>) -> Result<Command, String> {
+        add_extra_args(
+            build_command::<TestTools>(&get_builder(), ColorChoice::Never),
+            extra_args,
+        )
+    }
+
+    #[test]
+    fn test_extra_arg_is_parsed() {
+        let command = get_extra_args_command(vec![Arg::new("region").long("region")]).unwrap();
+
+        let matches = command
+            .try_get_matches_from(["test-server", "--region", "eu-west", "--port", "3000"])
+            .unwrap();
+
+        assert_eq!(
+            matches.get_one::<String>("region").map(String::as_str),
+            Some("eu-west")
+        );
+        assert_eq!(matches.get_one::<u16>(ARG_PORT), Some(&3000));
+    }
+
+    #[test]
+    fn test_extra_arg_with_reserved_name_is_rejected() {
+        let error =
+            get_extra_args_command(vec![Arg::new("timeout").long("my-timeout")]).unwrap_err();
+
+        assert_eq!(
+            error,
+            "argument `timeout` conflicts with the built-in argument `timeout`"
+        );
+    }
+
+    #[test]
+    fn test_extra_arg_with_reserved_flag_is_rejected() {
+        let error = get_extra_args_command(vec![Arg::new("listen-host").long("host")]).unwrap_err();
+
+        assert_eq!(
+            error,
+            "argument `listen-host` conflicts with the built-in argument `host`"
+        );
+    }
+
+    #[test]
+    fn test_extra_arg_with_reserved_short_flag_is_rejected() {
+        let error = get_extra_args_command(vec![Arg::new("profile").short('p')]).unwrap_err();
+
+        assert_eq!(
+            error,
+            "argument `profile` conflicts with the built-in argument `port`"
+        );
+    }
+
+    fn check_host(builder: &ServerBuilder, args: &[&str]) -> Result<Option<String>, String> {
+        let config = resolve_config::<TestTools, _>(builder, args).unwrap();
+
+        match config.mode {
+            ServerMode::Http { host, .. } => {
+                check_external_host(builder, &host, config.allow_external)
+            }
+            ServerMode::Stdio => panic!("expected http mode"),
+        }
+    }
+
+    #[test]
+    fn test_unspecified_host_prints_warning() {
+        let warning = check_host(&get_builder(), &["test-server", "--host", "0.0.0.0"]).unwrap();
+
+        assert_eq!(
+            warning.as_deref(),
+            Some(
+                "warning: the server is bound to `0.0.0.0`, which exposes it to other machines on the network (pass --allow-external to hide this warning)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_allow_external_hides_warning() {
+        let args = ["test-server", "--host", "::", "--allow-external"];
+
+        assert_eq!(check_host(&get_builder(), &args), Ok(None));
+    }
+
+    #[test]
+    fn test_local_host_has_no_warning() {
+        let args = ["test-server", "--host", "127.0.0.1"];
+
+        assert_eq!(check_host(&get_builder(), &args), Ok(None));
+    }
+
+    #[test]
+    fn test_require_external_optin_refuses_unspecified_host() {
+        let builder = get_builder().with_require_external_optin(true);
+
+        assert_eq!(
+            check_host(&builder, &["test-server", "--host", "0.0.0.0"]),
+            Err("refusing to bind the server to `0.0.0.0`, which exposes it to other machines on the network (pass --allow-external to allow it)".to_owned())
+        );
+        assert_eq!(
+            check_host(
+                &builder,
+                &["test-server", "--host", "0.0.0.0", "--allow-external"]
+            ),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_doctor_passes_for_test_tools() {
+        let mut output = Vec::new();
+
+        run_doctor::<TestTools>(
+            &ServerMode::Stdio,
+            RuntimeFlavor::CurrentThread,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[pass] async runtime can be built\n\
+             [pass] tool `test_tool` has a valid input schema\n\
+             [pass] tool `another_tool` has a valid input schema\n\
+             [pass] tool names are unique\n"
+        );
+    }
+
+    #[test]
+    fn test_doctor_fails_when_port_is_taken() {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut output = Vec::new();
+
+        let error = run_doctor::<TestTools>(
+            &ServerMode::Http {
+                host: "127.0.0.1".to_owned(),
+                port,
+            },
+            RuntimeFlavor::CurrentThread,
+            &mut output,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.to_string(), "1 of 5 checks failed");
+        let report = String::from_utf8(output).unwrap();
+        assert!(report.contains(&format!(
+            "[fail] port {} can be bound on `127.0.0.1`: ",
+            port
+        )));
     }
 }
@@ -0,0 +1,36 @@
+use rust_mcp_sdk::schema::Tool;
+
+/// Validates the arguments of a tool call against the input schema of the tool.
+///
+/// Returns one message for each violation, prefixed with the JSON pointer of the
+/// invalid value.
+pub(crate) fn validate_arguments(
+    tool: &Tool,
+    arguments: &serde_json::Value,
+) -> Result<Vec<String>, String> {
+    let schema = serde_json::to_value(&tool.input_schema).map_err(|err| {
+        format!(
+            "unable to serialize the input schema of tool `{}`: {}",
+            tool.name, err
+        )
+    })?;
+
+    let validator = jsonschema::validator_for(&schema)
+        .map_err(|err| format!("invalid input schema for tool `{}`: {}", tool.name, err))?;
+
+    Ok(validator
+        .iter_errors(arguments)
+        .map(|error| {
+            let pointer = error.instance_path().as_str();
+            format!(
+                "{}: {}",
+                if pointer.is_empty() {
+                    "(root)"
+                } else {
+                    pointer
+                },
+                error
+            )
+        })
+        .collect())
+}
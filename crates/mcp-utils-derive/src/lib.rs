@@ -0,0 +1,48 @@
+//! # mcp-utils-derive
+//!
+//! Derive macros for [`mcp-utils`](https://docs.rs/mcp-utils/latest/mcp_utils/index.html),
+//! re-exported by its `tool_prelude` module.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, parse_macro_input};
+
+/// Implements `ToolOutput` for a structured tool output, using the JSON Schema generated by
+/// the `JsonSchema` derive as the output schema of the tool.
+#[proc_macro_derive(ToolOutput)]
+pub fn derive_tool_output(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::mcp_utils::__private::JsonSchema for #name #type_generics #where_clause {
+            fn json_schema() -> ::mcp_utils::__private::serde_json::Map<
+                ::std::string::String,
+                ::mcp_utils::__private::serde_json::Value,
+            > {
+                // the function generated by the `JsonSchema` derive takes precedence over trait
+                // functions. When the derive is missing, this trait makes the call ambiguous
+                // instead of letting it recurse into this function.
+                trait DeriveJsonSchema {
+                    fn json_schema();
+                }
+
+                impl<T: ?Sized> DeriveJsonSchema for T {
+                    fn json_schema() {}
+                }
+
+                Self::json_schema()
+            }
+        }
+
+        impl #impl_generics ::mcp_utils::tool_prelude::ToolOutput for #name #type_generics #where_clause {
+            fn output_schema() -> ::mcp_utils::tool_prelude::ToolOutputSchema {
+                ::mcp_utils::__private::output_schema(
+                    <Self as ::mcp_utils::__private::JsonSchema>::json_schema(),
+                )
+            }
+        }
+    }
+    .into()
+}
@@ -0,0 +1,153 @@
+//! Measures the dispatch of tool calls through a tool box generated by `setup_tools!`: the
+//! matching of the call parameters to a tool with `TryFrom`, and the call of the selected
+//! tool through `get_tool`.
+//!
+//! Run it with `cargo bench -p mcp-utils --bench dispatch`.
+
+use async_trait::async_trait;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use mcp_utils::server_prelude::*;
+use mcp_utils::tool_prelude::*;
+use rust_mcp_sdk::schema::CallToolRequestParams;
+
+#[mcp_tool(name = "echo", description = "Returns its message")]
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct EchoTool {
+    pub message: String,
+}
+
+impl TextTool for EchoTool {
+    type Output = String;
+
+    fn call(&self) -> Self::Output {
+        self.message.clone()
+    }
+}
+
+#[mcp_tool(name = "upper", description = "Converts its message to uppercase")]
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct UpperTool {
+    pub message: String,
+}
+
+impl TextTool for UpperTool {
+    type Output = String;
+
+    fn call(&self) -> Self::Output {
+        self.message.to_uppercase()
+    }
+}
+
+#[mcp_tool(name = "ping", description = "Answers pong")]
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct PingTool {}
+
+#[async_trait]
+impl AsyncTextTool for PingTool {
+    type Output = &'static str;
+
+    async fn call(&self) -> Self::Output {
+        "pong"
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Sum {
+    sum: f64,
+}
+
+#[mcp_tool(name = "sum", description = "Adds the given numbers")]
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct SumTool {
+    pub values: Vec<f64>,
+}
+
+impl StructuredTool for SumTool {
+    type Output = Sum;
+
+    fn call(&self) -> Self::Output {
+        Sum {
+            sum: self.values.iter().sum(),
+        }
+    }
+}
+
+setup_tools!(pub BenchTools, [
+    text(EchoTool),
+    text(UpperTool aliases ["shout"]),
+    async_text(PingTool),
+    structured(SumTool),
+]);
+
+fn params(name: &str, arguments: serde_json::Value) -> CallToolRequestParams {
+    CallToolRequestParams::new(name).with_arguments(arguments.as_object().unwrap().clone())
+}
+
+fn bench_try_from(c: &mut Criterion) {
+    let mut group = c.benchmark_group("try_from");
+    let cases = [
+        (
+            "first_tool",
+            params("echo", serde_json::json!({ "message": "hi" })),
+        ),
+        (
+            "alias",
+            params("shout", serde_json::json!({ "message": "hi" })),
+        ),
+        (
+            "last_tool",
+            params("sum", serde_json::json!({ "values": [1.5, 2.5] })),
+        ),
+        ("unknown_tool", params("missing", serde_json::json!({}))),
+    ];
+
+    for (name, params) in cases {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || params.clone(),
+                BenchTools::try_from,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_dispatch(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("dispatch");
+    let cases = [
+        (
+            "text",
+            params("echo", serde_json::json!({ "message": "hi" })),
+        ),
+        ("async_text", params("ping", serde_json::json!({}))),
+        (
+            "structured",
+            params("sum", serde_json::json!({ "values": [1.5, 2.5] })),
+        ),
+    ];
+
+    for (name, params) in cases {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || params.clone(),
+                |params| {
+                    runtime.block_on(async {
+                        let tools = BenchTools::try_from(params).unwrap();
+                        tools.get_tool().call().await.unwrap()
+                    })
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_try_from, bench_dispatch);
+criterion_main!(benches);
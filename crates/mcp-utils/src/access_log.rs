@@ -0,0 +1,213 @@
+use std::time::{Instant, SystemTime};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+};
+
+/// The format of the access log lines written by the HTTP server, set with
+/// [`ServerBuilder::with_access_log`](crate::server_prelude::ServerBuilder::with_access_log).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// One JSON object per request, with the `timestamp`, `remote_addr`, `method`, `path`,
+    /// `status` and `duration_ms` fields.
+    Json,
+    /// The Apache common log format, followed by the duration of the request in milliseconds.
+    Apache,
+}
+
+/// A request handled by the HTTP server.
+#[derive(Debug)]
+struct AccessLogEntry {
+    timestamp: SystemTime,
+    remote_addr: Option<String>,
+    method: String,
+    path: String,
+    version: String,
+    status: u16,
+    duration_ms: u128,
+}
+
+impl AccessLogEntry {
+    fn format(&self, format: AccessLogFormat) -> String {
+        match format {
+            AccessLogFormat::Json => serde_json::json!({
+                "timestamp": humantime::format_rfc3339_millis(self.timestamp).to_string(),
+                "remote_addr": self.remote_addr,
+                "method": self.method,
+                "path": self.path,
+                "status": self.status,
+                "duration_ms": self.duration_ms,
+            })
+            .to_string(),
+            AccessLogFormat::Apache => format!(
+                "{} - - [{}] \"{} {} {}\" {} - {}",
+                self.remote_addr.as_deref().unwrap_or("-"),
+                apache_timestamp(self.timestamp),
+                self.method,
+                self.path,
+                self.version,
+                self.status,
+                self.duration_ms
+            ),
+        }
+    }
+}
+
+/// Formats a timestamp like `10/Oct/2000:13:55:36 +0000`.
+fn apache_timestamp(timestamp: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    // the RFC 3339 format is `2000-10-10T13:55:36Z`
+    let rfc3339 = humantime::format_rfc3339_seconds(timestamp).to_string();
+    let month = rfc3339[5..7]
+        .parse::<usize>()
+        .ok()
+        .and_then(|month| MONTHS.get(month.wrapping_sub(1)))
+        .unwrap_or(&"---");
+
+    format!(
+        "{}/{}/{}:{} +0000",
+        &rfc3339[8..10],
+        month,
+        &rfc3339[0..4],
+        &rfc3339[11..19]
+    )
+}
+
+/// Writes one line at the `info` level with [`tracing`](https://docs.rs/tracing) for each
+/// request, in the format stored in the application data. Requests pass through untouched
+/// when no format is stored.
+pub(crate) async fn log_access(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(&format) = request.app_data::<AccessLogFormat>() else {
+        return next.call(request).await;
+    };
+
+    let timestamp = SystemTime::now();
+    let start = Instant::now();
+    let remote_addr = request.peer_addr().map(|address| address.ip().to_string());
+    let method = request.method().to_string();
+    let path = request.uri().to_string();
+    let version = format!("{:?}", request.version());
+
+    let response = next.call(request).await?;
+
+    let entry = AccessLogEntry {
+        timestamp,
+        remote_addr,
+        method,
+        path,
+        version,
+        status: response.status().as_u16(),
+        duration_ms: start.elapsed().as_millis(),
+    };
+    tracing::info!("{}", entry.format(format));
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{
+        App, HttpResponse,
+        middleware::from_fn,
+        test::{TestRequest, call_service, init_service},
+        web,
+    };
+
+    use super::*;
+
+    fn entry() -> AccessLogEntry {
+        AccessLogEntry {
+            // 2000-10-10T13:55:36.250Z
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(971_186_136_250),
+            remote_addr: Some("127.0.0.1".to_owned()),
+            method: "POST".to_owned(),
+            path: "/mcp".to_owned(),
+            version: "HTTP/1.1".to_owned(),
+            status: 200,
+            duration_ms: 12,
+        }
+    }
+
+    #[test]
+    fn json_format() {
+        let line: serde_json::Value =
+            serde_json::from_str(&entry().format(AccessLogFormat::Json)).unwrap();
+
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "timestamp": "2000-10-10T13:55:36.250Z",
+                "remote_addr": "127.0.0.1",
+                "method": "POST",
+                "path": "/mcp",
+                "status": 200,
+                "duration_ms": 12,
+            })
+        );
+    }
+
+    #[test]
+    fn apache_format() {
+        assert_eq!(
+            entry().format(AccessLogFormat::Apache),
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "POST /mcp HTTP/1.1" 200 - 12"#
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[actix_web::test]
+    async fn one_json_line_is_logged_per_request() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .without_time()
+            .with_target(false)
+            .with_level(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = init_service(
+            App::new()
+                .app_data(AccessLogFormat::Json)
+                .wrap(from_fn(log_access))
+                .route("/mcp", web::post().to(HttpResponse::Accepted)),
+        )
+        .await;
+        call_service(&app, TestRequest::post().uri("/mcp").to_request()).await;
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 1, "{output}");
+
+        let line: serde_json::Value = serde_json::from_str(lines[0].trim()).unwrap();
+        assert_eq!(line["method"], "POST");
+        assert_eq!(line["path"], "/mcp");
+        assert_eq!(line["status"], 202);
+        assert!(line["timestamp"].is_string());
+        assert!(line["duration_ms"].is_u64());
+    }
+}
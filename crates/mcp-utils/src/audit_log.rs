@@ -0,0 +1,104 @@
+use std::{
+    collections::HashSet,
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+    time::SystemTime,
+};
+
+use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+
+use crate::redaction::redact;
+
+/// Appends one JSON line per tool call to a file, set with
+/// [`ServerBuilder::with_audit_log`](crate::server_prelude::ServerBuilder::with_audit_log).
+///
+/// Lines are written by a background thread, so that tool calls never wait for the file. The
+/// remaining lines are written when the audit log is dropped.
+pub(crate) struct AuditLog {
+    sender: Option<Sender<String>>,
+    writer: Option<JoinHandle<()>>,
+    redacted_fields: HashSet<String>,
+}
+
+impl AuditLog {
+    pub(crate) fn open(path: &Path, redacted_fields: &[String]) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (sender, receiver) = mpsc::channel::<String>();
+
+        let writer = thread::spawn(move || {
+            let mut writer = BufWriter::new(file);
+
+            while let Ok(line) = receiver.recv() {
+                // write the lines already queued before flushing
+                let result = std::iter::once(line)
+                    .chain(receiver.try_iter())
+                    .try_for_each(|line| writeln!(writer, "{}", line))
+                    .and_then(|()| writer.flush());
+
+                if let Err(err) = result {
+                    tracing::error!("unable to write to the audit log: {}", err);
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            redacted_fields: redacted_fields.iter().cloned().collect(),
+        })
+    }
+
+    /// Queues the line describing a tool call.
+    pub(crate) fn record(
+        &self,
+        tool_name: &str,
+        arguments: Option<serde_json::Map<String, serde_json::Value>>,
+        result: &Result<CallToolResult, CallToolError>,
+    ) {
+        let mut arguments = serde_json::Value::Object(arguments.unwrap_or_default());
+        redact(&mut arguments, &|field| {
+            self.redacted_fields.contains(field)
+        });
+
+        let error = match result {
+            Ok(result) if result.is_error == Some(true) => Some(
+                result
+                    .content
+                    .first()
+                    .and_then(|content| content.as_text_content().ok())
+                    .map(|content| content.text.clone())
+                    .unwrap_or_default(),
+            ),
+            Ok(_) => None,
+            Err(error) => Some(error.to_string()),
+        };
+
+        let line = serde_json::json!({
+            "timestamp": humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            "tool": tool_name,
+            "arguments": arguments,
+            "success": error.is_none(),
+            "error": error,
+        });
+
+        if let Some(sender) = &self.sender {
+            // the writer only stops after failing to write, which is already logged
+            let _ = sender.send(line.to_string());
+        }
+    }
+}
+
+impl Drop for AuditLog {
+    fn drop(&mut self) {
+        // closing the channel stops the writer once the queued lines are written
+        self.sender.take();
+
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
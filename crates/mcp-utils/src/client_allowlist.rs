@@ -0,0 +1,93 @@
+use actix_web::{
+    HttpResponse,
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+};
+use ipnet::IpNet;
+
+/// The networks allowed to connect to the HTTP server, set with
+/// [`ServerBuilder::with_client_allowlist`](crate::server_prelude::ServerBuilder::with_client_allowlist).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ClientAllowlist(pub(crate) Vec<IpNet>);
+
+impl ClientAllowlist {
+    fn allows(&self, request: &ServiceRequest) -> bool {
+        request.peer_addr().is_some_and(|address| {
+            // IPv4 clients of a dual-stack socket connect with IPv4-mapped IPv6 addresses
+            let ip = address.ip().to_canonical();
+            self.0.iter().any(|network| network.contains(&ip))
+        })
+    }
+}
+
+/// Rejects the requests of the clients outside the allowlist with a `403 Forbidden`
+/// response, before they reach the MCP endpoints. Requests pass through untouched when no
+/// allowlist is stored in the application data.
+pub(crate) async fn reject_disallowed_clients(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    match request.app_data::<ClientAllowlist>() {
+        Some(allowlist) if !allowlist.allows(&request) => {
+            Ok(request.into_response(HttpResponse::Forbidden().finish()))
+        }
+        _ => Ok(next.call(request).await?.map_into_boxed_body()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use actix_web::{
+        App,
+        http::StatusCode,
+        middleware::from_fn,
+        test::{TestRequest, call_service, init_service},
+        web,
+    };
+
+    use super::*;
+
+    async fn status(networks: &[&str], peer: &str) -> StatusCode {
+        let allowlist = ClientAllowlist(
+            networks
+                .iter()
+                .map(|network| network.parse().unwrap())
+                .collect(),
+        );
+        let app = init_service(
+            App::new()
+                .app_data(allowlist)
+                .wrap(from_fn(reject_disallowed_clients))
+                .route("/mcp", web::post().to(HttpResponse::Accepted)),
+        )
+        .await;
+        let request = TestRequest::post()
+            .uri("/mcp")
+            .peer_addr(peer.parse::<SocketAddr>().unwrap());
+
+        call_service(&app, request.to_request()).await.status()
+    }
+
+    #[actix_web::test]
+    async fn clients_outside_the_allowlist_are_rejected() {
+        assert_eq!(
+            status(&["10.0.0.0/8"], "192.168.1.20:51000").await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[actix_web::test]
+    async fn clients_inside_the_allowlist_pass() {
+        assert_eq!(
+            status(&["10.0.0.0/8", "192.168.1.20/32"], "192.168.1.20:51000").await,
+            StatusCode::ACCEPTED
+        );
+        assert_eq!(
+            status(&["10.0.0.0/8"], "[::ffff:10.1.2.3]:51000").await,
+            StatusCode::ACCEPTED
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use rust_mcp_sdk::schema::ToolInputSchema;
+use serde_json::{Map, Value};
+
+/// Converts the string arguments holding a number or a boolean to the type expected by the
+/// input schema of the tool, for clients that send `"3.5"` instead of `3.5`. Strings are
+/// converted inside arrays and objects too, following the `items` and `properties` of the
+/// schema. Values that can't be converted, and strings where the schema accepts a string,
+/// are left untouched for the tool to report.
+pub(crate) fn coerce_arguments(arguments: &mut Map<String, Value>, schema: &ToolInputSchema) {
+    let Some(properties) = &schema.properties else {
+        return;
+    };
+
+    for (name, value) in arguments.iter_mut() {
+        if let Some(property) = properties.get(name) {
+            coerce(value, property);
+        }
+    }
+}
+
+fn coerce(value: &mut Value, schema: &Map<String, Value>) {
+    match value {
+        Value::String(text) => {
+            if let Some(coerced) = coerce_string(text, schema) {
+                *value = coerced;
+            }
+        }
+        Value::Array(items) => {
+            if let Some(Value::Object(item_schema)) = schema.get("items") {
+                for item in items {
+                    coerce(item, item_schema);
+                }
+            }
+        }
+        Value::Object(object) => {
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                for (name, value) in object.iter_mut() {
+                    if let Some(Value::Object(property)) = properties.get(name) {
+                        coerce(value, property);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn coerce_string(text: &str, schema: &Map<String, Value>) -> Option<Value> {
+    let types = schema_types(schema);
+    if types.contains(&"string") {
+        return None;
+    }
+
+    let text = text.trim();
+    types.into_iter().find_map(|expected| match expected {
+        "integer" => parse_integer(text),
+        "number" => parse_integer(text).or_else(|| {
+            text.parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+        }),
+        "boolean" => match text {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn parse_integer(text: &str) -> Option<Value> {
+    text.parse::<i64>()
+        .map(Value::from)
+        .or_else(|_| text.parse::<u64>().map(Value::from))
+        .ok()
+}
+
+/// Returns the types accepted by the schema, from its `type` keyword.
+fn schema_types(schema: &Map<String, Value>) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn schema(properties: Value) -> ToolInputSchema {
+        let properties: BTreeMap<String, Map<String, Value>> =
+            serde_json::from_value(properties).unwrap();
+        ToolInputSchema::new(Vec::new(), Some(properties), None)
+    }
+
+    fn coerced(arguments: Value, properties: Value) -> Value {
+        let mut arguments = arguments.as_object().unwrap().clone();
+        coerce_arguments(&mut arguments, &schema(properties));
+        Value::Object(arguments)
+    }
+
+    #[test]
+    fn stringified_scalars_are_converted_to_the_schema_type() {
+        let properties = serde_json::json!({
+            "ratio": { "type": "number" },
+            "count": { "type": ["integer", "null"] },
+            "verbose": { "type": "boolean" },
+            "name": { "type": "string" },
+        });
+        let arguments = serde_json::json!({
+            "ratio": "3.5",
+            "count": " 12 ",
+            "verbose": "true",
+            "name": "42",
+        });
+
+        assert_eq!(
+            coerced(arguments, properties),
+            serde_json::json!({ "ratio": 3.5, "count": 12, "verbose": true, "name": "42" })
+        );
+    }
+
+    #[test]
+    fn nested_values_are_converted() {
+        let properties = serde_json::json!({
+            "values": { "type": "array", "items": { "type": "number" } },
+            "point": {
+                "type": "object",
+                "properties": { "x": { "type": "integer" } },
+            },
+        });
+        let arguments = serde_json::json!({
+            "values": ["1", 2, "3.25"],
+            "point": { "x": "-4" },
+        });
+
+        assert_eq!(
+            coerced(arguments, properties),
+            serde_json::json!({ "values": [1, 2, 3.25], "point": { "x": -4 } })
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_left_untouched() {
+        let properties = serde_json::json!({
+            "ratio": { "type": "number" },
+            "verbose": { "type": "boolean" },
+        });
+        let arguments = serde_json::json!({
+            "ratio": "three",
+            "verbose": "yes",
+            "unknown": "1",
+        });
+
+        assert_eq!(coerced(arguments.clone(), properties), arguments);
+    }
+}
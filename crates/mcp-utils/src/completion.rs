@@ -0,0 +1,106 @@
+use rust_mcp_sdk::schema::{
+    CompleteRequestArgument, CompleteRequestParams, CompleteRequestRef, CompleteResult,
+    CompleteResultCompletion,
+};
+
+/// The maximum number of values sent in a completion result, as set by the MCP
+/// specification.
+const MAX_COMPLETION_VALUES: usize = 100;
+
+/// Suggests values for arguments while the user types them, set with
+/// [`ServerBuilder::with_completions`](crate::server_prelude::ServerBuilder::with_completions).
+///
+/// Clients request completions for the arguments of a prompt or of a resource template,
+/// identified by the reference of the request.
+///
+/// ```rust
+/// use mcp_utils::server_prelude::*;
+///
+/// struct LanguageCompletions;
+///
+/// impl CompletionProvider for LanguageCompletions {
+///     fn complete(_reference: &CompleteRequestRef, argument: &CompleteRequestArgument) -> Vec<String> {
+///         if argument.name != "language" {
+///             return Vec::new();
+///         }
+///
+///         ["lua", "luau", "rust"]
+///             .into_iter()
+///             .filter(|language| language.starts_with(&argument.value))
+///             .map(str::to_owned)
+///             .collect()
+///     }
+/// }
+///
+/// let builder = ServerBuilder::new().with_completions::<LanguageCompletions>();
+/// ```
+pub trait CompletionProvider {
+    /// Returns the values suggested for the argument, given the value typed so far. Only the
+    /// first 100 values are sent to the client.
+    fn complete(reference: &CompleteRequestRef, argument: &CompleteRequestArgument) -> Vec<String>;
+}
+
+pub(crate) type CompletionFn = fn(&CompleteRequestRef, &CompleteRequestArgument) -> Vec<String>;
+
+/// Answers a completion request with the values suggested by the given function.
+pub(crate) fn complete(completion: CompletionFn, params: &CompleteRequestParams) -> CompleteResult {
+    let mut values = completion(&params.ref_, &params.argument);
+    let total = values.len();
+    values.truncate(MAX_COMPLETION_VALUES);
+
+    CompleteResult {
+        completion: CompleteResultCompletion {
+            has_more: Some(total > values.len()),
+            total: i64::try_from(total).ok(),
+            values,
+        },
+        meta: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_mcp_sdk::schema::PromptReference;
+
+    use super::*;
+
+    fn params(value: &str) -> CompleteRequestParams {
+        CompleteRequestParams {
+            argument: CompleteRequestArgument {
+                name: "number".to_owned(),
+                value: value.to_owned(),
+            },
+            context: None,
+            meta: None,
+            ref_: CompleteRequestRef::PromptReference(PromptReference::new(
+                "count".to_owned(),
+                None,
+            )),
+        }
+    }
+
+    fn numbers(_reference: &CompleteRequestRef, argument: &CompleteRequestArgument) -> Vec<String> {
+        (0..250)
+            .map(|number| number.to_string())
+            .filter(|number| number.starts_with(&argument.value))
+            .collect()
+    }
+
+    #[test]
+    fn all_values_are_sent_when_under_the_limit() {
+        let result = complete(numbers, &params("2"));
+
+        assert_eq!(result.completion.values.len(), 61);
+        assert_eq!(result.completion.total, Some(61));
+        assert_eq!(result.completion.has_more, Some(false));
+    }
+
+    #[test]
+    fn values_are_truncated_to_the_limit() {
+        let result = complete(numbers, &params(""));
+
+        assert_eq!(result.completion.values.len(), MAX_COMPLETION_VALUES);
+        assert_eq!(result.completion.total, Some(250));
+        assert_eq!(result.completion.has_more, Some(true));
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    fmt,
+    future::Future,
+    io::{self, Write},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+use rust_mcp_sdk::schema::{ClientCapabilities, ProgressNotificationParams, ProgressToken};
+
+use crate::session::SessionState;
+
+tokio::task_local! {
+    static CONTEXT: ToolContext;
+}
+
+/// Information about the tool call being processed.
+///
+/// The context is available with [`ToolContext::current`] from the `call` method of any
+/// tool kind, synchronous or asynchronous.
+///
+/// ```rust
+/// use mcp_utils::tool_prelude::*;
+///
+/// #[mcp_tool(name = "ask_user", description = "Asks the user a question")]
+/// #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// pub struct AskUser {}
+///
+/// impl TextTool for AskUser {
+///     type Output = &'static str;
+///
+///     fn call(&self) -> Self::Output {
+///         let supports_elicitation = ToolContext::current()
+///             .is_some_and(|context| context.client_supports_elicitation());
+///
+///         if supports_elicitation {
+///             "the client can ask the user"
+///         } else {
+///             "the client cannot ask the user"
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ToolContext {
+    client_capabilities: Option<ClientCapabilities>,
+    transport_kind: TransportKind,
+    session: Option<(String, SessionState)>,
+    progress_token: Option<ProgressToken>,
+    progress_notifier: Option<ProgressNotifier>,
+}
+
+/// Sends `notifications/progress` messages to the client of a tool call.
+#[derive(Clone)]
+pub(crate) struct ProgressNotifier(
+    pub(crate) Arc<dyn Fn(ProgressNotificationParams) -> BoxFuture<'static, ()> + Send + Sync>,
+);
+
+impl fmt::Debug for ProgressNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressNotifier")
+    }
+}
+
+/// The transport over which a tool call was received.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransportKind {
+    /// The server was started with [`ServerBuilder::start_stdio`](crate::server_prelude::ServerBuilder::start_stdio).
+    #[default]
+    Stdio,
+    /// The server was started with [`ServerBuilder::start_server`](crate::server_prelude::ServerBuilder::start_server).
+    Http,
+    /// The server was started with `ServerBuilder::start_in_memory`, available with the
+    /// `test-util` feature.
+    InMemory,
+}
+
+impl ToolContext {
+    pub(crate) fn new(
+        client_capabilities: Option<ClientCapabilities>,
+        transport_kind: TransportKind,
+    ) -> Self {
+        Self {
+            client_capabilities,
+            transport_kind,
+            session: None,
+            progress_token: None,
+            progress_notifier: None,
+        }
+    }
+
+    pub(crate) fn with_session(mut self, session_id: String, state: SessionState) -> Self {
+        self.session = Some((session_id, state));
+        self
+    }
+
+    pub(crate) fn with_progress_token(mut self, progress_token: Option<ProgressToken>) -> Self {
+        self.progress_token = progress_token;
+        self
+    }
+
+    pub(crate) fn with_progress_notifier(mut self, progress_notifier: ProgressNotifier) -> Self {
+        self.progress_notifier = Some(progress_notifier);
+        self
+    }
+
+    /// Returns the context of the tool call being processed, or `None` when called outside
+    /// of a tool call.
+    pub fn current() -> Option<Self> {
+        CONTEXT.try_with(Clone::clone).ok()
+    }
+
+    /// Returns the capabilities sent by the client during the initialization handshake.
+    pub fn client_capabilities(&self) -> Option<&ClientCapabilities> {
+        self.client_capabilities.as_ref()
+    }
+
+    /// Returns the transport over which the tool call was received. For example, a tool can
+    /// avoid exposing local file paths to clients connected over the network.
+    pub fn transport_kind(&self) -> TransportKind {
+        self.transport_kind
+    }
+
+    /// Returns the identifier of the HTTP session of the client, sent in the `Mcp-Session-Id`
+    /// header. Calls received over other transports have no session.
+    pub fn session_id(&self) -> Option<&str> {
+        self.session
+            .as_ref()
+            .map(|(session_id, _)| session_id.as_str())
+    }
+
+    /// Returns the values shared by the tool calls of the HTTP session of the client.
+    pub fn session(&self) -> Option<&SessionState> {
+        self.session.as_ref().map(|(_, state)| state)
+    }
+
+    /// Returns the progress token sent by the client in the `_meta` object of the tool call,
+    /// when it asks for progress notifications. Tools can use it to correlate their progress
+    /// with their own telemetry.
+    pub fn progress_token(&self) -> Option<&ProgressToken> {
+        self.progress_token.as_ref()
+    }
+
+    /// Sends a `notifications/progress` message to the client, with the progress made so far
+    /// (increasing with every notification), the total when it is known, and a message. Does
+    /// nothing when the client did not send a progress token with the tool call, or when the
+    /// transport can't send notifications. Failures to send the notification are logged.
+    pub async fn notify_progress(
+        &self,
+        progress: f64,
+        total: Option<f64>,
+        message: Option<String>,
+    ) {
+        let (Some(progress_token), Some(ProgressNotifier(notify))) =
+            (&self.progress_token, &self.progress_notifier)
+        else {
+            return;
+        };
+
+        notify(ProgressNotificationParams {
+            message,
+            meta: None,
+            progress,
+            progress_token: progress_token.clone(),
+            total,
+        })
+        .await;
+    }
+
+    /// Writes a line to the standard error output of the server, for example to report the
+    /// progress of a long tool call. Tools must not print to the standard output, which
+    /// carries the JSON-RPC messages of the stdio transport.
+    pub fn emit_stderr(&self, message: impl fmt::Display) {
+        // diagnostics are best effort: a closed stderr must not fail the tool call
+        let _ = writeln!(io::stderr().lock(), "{}", message);
+    }
+
+    pub fn client_supports_roots(&self) -> bool {
+        self.client_capabilities
+            .as_ref()
+            .is_some_and(|capabilities| capabilities.roots.is_some())
+    }
+
+    pub fn client_supports_sampling(&self) -> bool {
+        self.client_capabilities
+            .as_ref()
+            .is_some_and(|capabilities| capabilities.sampling.is_some())
+    }
+
+    pub fn client_supports_elicitation(&self) -> bool {
+        self.client_capabilities
+            .as_ref()
+            .is_some_and(|capabilities| capabilities.elicitation.is_some())
+    }
+
+    pub(crate) async fn scope<F>(self, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        CONTEXT.scope(self, future).await
+    }
+
+    pub(crate) fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        CONTEXT.sync_scope(self, f)
+    }
+}
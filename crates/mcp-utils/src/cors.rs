@@ -0,0 +1,189 @@
+use actix_web::{
+    HttpResponse,
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        Method,
+        header::{self, HeaderMap, HeaderValue},
+    },
+    middleware::Next,
+};
+
+/// The methods used by the MCP endpoints.
+const ALLOWED_METHODS: &str = "GET, POST, DELETE, OPTIONS";
+
+/// The request headers used by MCP clients, allowed when the preflight request does not list
+/// the headers it needs.
+const ALLOWED_HEADERS: &str =
+    "Authorization, Content-Type, Last-Event-ID, Mcp-Protocol-Version, Mcp-Session-Id";
+
+/// How long browsers can cache the result of a preflight request, in seconds.
+const PREFLIGHT_MAX_AGE: &str = "86400";
+
+/// The origins allowed to call the HTTP server from a browser, set with
+/// [`ServerBuilder::with_cors`](crate::server_prelude::ServerBuilder::with_cors). The `*`
+/// origin allows every origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CorsOrigins(pub(crate) Vec<String>);
+
+impl CorsOrigins {
+    /// Returns the value of the `Access-Control-Allow-Origin` header for a request sent from
+    /// the given origin, or `None` when the origin is not allowed.
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        if self.0.iter().any(|allowed| allowed == "*") {
+            Some(HeaderValue::from_static("*"))
+        } else {
+            let origin_str = origin.to_str().ok()?;
+            self.0
+                .iter()
+                .any(|allowed| allowed == origin_str)
+                .then(|| origin.clone())
+        }
+    }
+}
+
+/// Answers the preflight requests of allowed origins and adds the CORS headers to the other
+/// responses sent to them. Requests pass through untouched when no origins are stored in the
+/// application data.
+pub(crate) async fn apply_cors(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let allow_origin = request
+        .app_data::<CorsOrigins>()
+        .zip(request.headers().get(header::ORIGIN))
+        .and_then(|(origins, origin)| origins.allow_origin(origin));
+
+    let Some(allow_origin) = allow_origin else {
+        return Ok(next.call(request).await?.map_into_boxed_body());
+    };
+
+    let is_preflight = request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+    if is_preflight {
+        let allow_headers = request
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .cloned()
+            .unwrap_or_else(|| HeaderValue::from_static(ALLOWED_HEADERS));
+
+        let mut response = HttpResponse::NoContent().finish();
+        let headers = response.headers_mut();
+        insert_common_headers(headers, allow_origin);
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static(ALLOWED_METHODS),
+        );
+        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allow_headers);
+        headers.insert(
+            header::ACCESS_CONTROL_MAX_AGE,
+            HeaderValue::from_static(PREFLIGHT_MAX_AGE),
+        );
+
+        return Ok(request.into_response(response));
+    }
+
+    let mut response = next.call(request).await?.map_into_boxed_body();
+    let headers = response.headers_mut();
+    insert_common_headers(headers, allow_origin);
+    // lets browser clients read the session identifier assigned by the server
+    headers.insert(
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static("Mcp-Session-Id"),
+    );
+
+    Ok(response)
+}
+
+fn insert_common_headers(headers: &mut HeaderMap, allow_origin: HeaderValue) {
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+    headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        http::StatusCode,
+        middleware::from_fn,
+        test::{TestRequest, call_service, init_service},
+        web,
+    };
+
+    use super::*;
+
+    async fn send(origins: &[&str], request: TestRequest) -> ServiceResponse {
+        let origins = CorsOrigins(origins.iter().map(|origin| origin.to_string()).collect());
+        let app = init_service(
+            App::new()
+                .app_data(origins)
+                .wrap(from_fn(apply_cors))
+                .route("/mcp", web::post().to(HttpResponse::Accepted)),
+        )
+        .await;
+
+        call_service(&app, request.to_request()).await
+    }
+
+    fn preflight(origin: &str) -> TestRequest {
+        TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/mcp")
+            .insert_header((header::ORIGIN, origin))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+    }
+
+    fn header_value(response: &ServiceResponse, name: header::HeaderName) -> Option<&str> {
+        response
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[actix_web::test]
+    async fn preflight_from_configured_origin_is_allowed() {
+        let response = send(&["https://app.example"], preflight("https://app.example")).await;
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("https://app.example")
+        );
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_METHODS),
+            Some(ALLOWED_METHODS)
+        );
+    }
+
+    #[actix_web::test]
+    async fn preflight_from_other_origin_is_not_allowed() {
+        let response = send(&["https://app.example"], preflight("https://evil.example")).await;
+
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            None
+        );
+    }
+
+    #[actix_web::test]
+    async fn wildcard_allows_every_origin() {
+        let request = TestRequest::post()
+            .uri("/mcp")
+            .insert_header((header::ORIGIN, "https://app.example"));
+
+        let response = send(&["*"], request).await;
+
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("*")
+        );
+        assert_eq!(
+            header_value(&response, header::ACCESS_CONTROL_EXPOSE_HEADERS),
+            Some("Mcp-Session-Id")
+        );
+    }
+}
@@ -0,0 +1,77 @@
+/// Replaces the `${VAR}` and `${VAR:-default}` references of the text with the values returned
+/// by `lookup`. A variable without a value expands to its default, or to an empty string with a
+/// warning when it has no default. Text that is not a complete reference is kept as is.
+pub(crate) fn expand_env(text: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+
+        let reference = &rest[start + 2..];
+        let Some(end) = reference.find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let (name, default) = match reference[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&reference[..end], None),
+        };
+
+        match (lookup(name), default) {
+            (Some(value), _) => expanded.push_str(&value),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => {
+                tracing::warn!(variable = name, "environment variable is not set");
+            }
+        }
+
+        rest = &reference[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "REGION" => Some("eu-west".to_owned()),
+            "EMPTY" => Some("".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn variables_are_replaced_by_their_value() {
+        assert_eq!(
+            expand_env("Deployed in ${REGION}.", lookup),
+            "Deployed in eu-west."
+        );
+    }
+
+    #[test]
+    fn unset_variables_use_their_default() {
+        assert_eq!(
+            expand_env("${STAGE:-production} (${REGION:-local})", lookup),
+            "production (eu-west)"
+        );
+    }
+
+    #[test]
+    fn unset_variables_without_default_expand_to_nothing() {
+        assert_eq!(expand_env("[${STAGE}][${EMPTY}]", lookup), "[][]");
+    }
+
+    #[test]
+    fn incomplete_references_are_kept() {
+        assert_eq!(
+            expand_env("costs $5 in ${REGION", lookup),
+            "costs $5 in ${REGION"
+        );
+    }
+}
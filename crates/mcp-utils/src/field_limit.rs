@@ -0,0 +1,91 @@
+/// Determines how a server name or title longer than the limit set with
+/// [`ServerBuilder::with_field_limits`](crate::server_prelude::ServerBuilder::with_field_limits)
+/// is handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OversizedField {
+    /// The server fails to start.
+    #[default]
+    Reject,
+    /// The field is cut to the maximum number of characters.
+    Truncate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FieldLimits {
+    pub(crate) max_chars: usize,
+    pub(crate) oversized: OversizedField,
+}
+
+impl FieldLimits {
+    /// Applies the limit to the value of the field with the given name, or returns an error
+    /// message when the value is rejected.
+    pub(crate) fn apply(&self, field: &str, value: &mut String) -> Result<(), String> {
+        let length = value.chars().count();
+
+        if length <= self.max_chars {
+            return Ok(());
+        }
+
+        match self.oversized {
+            OversizedField::Reject => Err(format!(
+                "server {} is {} characters long, which exceeds the limit of {} characters",
+                field, length, self.max_chars
+            )),
+            OversizedField::Truncate => {
+                if let Some((end, _)) = value.char_indices().nth(self.max_chars) {
+                    value.truncate(end);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_chars: usize, oversized: OversizedField) -> FieldLimits {
+        FieldLimits {
+            max_chars,
+            oversized,
+        }
+    }
+
+    #[test]
+    fn field_within_limit_is_unchanged() {
+        let mut value = "server".to_owned();
+
+        assert!(
+            limits(6, OversizedField::Reject)
+                .apply("name", &mut value)
+                .is_ok()
+        );
+        assert_eq!(value, "server");
+    }
+
+    #[test]
+    fn oversized_field_is_rejected() {
+        let mut value = "my-server".to_owned();
+
+        assert_eq!(
+            limits(4, OversizedField::Reject).apply("name", &mut value),
+            Err(
+                "server name is 9 characters long, which exceeds the limit of 4 characters"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn oversized_field_is_truncated_to_whole_characters() {
+        let mut value = "héllo".to_owned();
+
+        assert!(
+            limits(2, OversizedField::Truncate)
+                .apply("title", &mut value)
+                .is_ok()
+        );
+        assert_eq!(value, "hé");
+    }
+}
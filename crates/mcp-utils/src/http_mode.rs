@@ -0,0 +1,31 @@
+use rust_mcp_actix::ActixServerOptions;
+
+/// Selects the transports served by the HTTP server, set with
+/// [`ServerBuilder::with_http_mode`](crate::server_prelude::ServerBuilder::with_http_mode).
+///
+/// Both modes are served by [`rust-mcp-actix`](https://docs.rs/rust-mcp-actix), the HTTP
+/// server of `rust-mcp-sdk`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpMode {
+    /// Serves the Streamable HTTP endpoint (`/mcp`) and the legacy HTTP+SSE endpoints (`/sse`
+    /// and `/messages`). Responses to requests can be streamed as server-sent events.
+    #[default]
+    Sse,
+    /// Serves only the Streamable HTTP endpoint (`/mcp`), and answers each request with a
+    /// plain JSON response instead of an event stream.
+    StreamableHttp,
+}
+
+impl HttpMode {
+    pub(crate) fn apply(self, options: &mut ActixServerOptions) {
+        match self {
+            Self::Sse => {
+                options.sse_support = true;
+            }
+            Self::StreamableHttp => {
+                options.sse_support = false;
+                options.enable_json_response = Some(true);
+            }
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use std::{
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::Arc,
+    time::Duration,
+};
+
+use actix_web::{
+    App, HttpResponse, HttpServer,
+    body::{self, BoxBody, MessageBody},
+    dev::{Server, ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderValue},
+    middleware::{Compress, Condition, Next, from_fn},
+    rt::net::TcpStream,
+    web,
+};
+use rust_mcp_actix::{ActixServerOptions, create_actix_server, mcp_scope};
+use rust_mcp_sdk::{
+    error::McpSdkError,
+    mcp_http::{McpHttpHandler, Middleware, resolve_dns_middleware},
+    mcp_server::McpServerHandler,
+    schema::{InitializeResult, Tool},
+};
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::{
+    access_log::{AccessLogFormat, log_access},
+    client_allowlist::{ClientAllowlist, reject_disallowed_clients},
+    cors::{CorsOrigins, apply_cors},
+    landing_page::render_landing_page,
+};
+
+/// Responses smaller than this size (in bytes) are not compressed.
+const MIN_COMPRESSED_SIZE: u64 = 1024;
+
+/// The middlewares added around the MCP endpoints of the HTTP server, and the settings of its
+/// socket.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HttpLayers {
+    /// Compresses responses according to the `Accept-Encoding` header sent by the client.
+    ///
+    /// Compressing an event stream would hold back events until enough data is buffered, so
+    /// event streams are sent uncompressed.
+    pub(crate) compression: bool,
+    pub(crate) access_log: Option<AccessLogFormat>,
+    pub(crate) cors: Option<CorsOrigins>,
+    /// The networks of the clients allowed to send requests, when restricted.
+    pub(crate) client_allowlist: Option<ClientAllowlist>,
+    /// The tools listed by the HTML page served at `/`, when the landing page is enabled.
+    pub(crate) landing_page: Option<Vec<Tool>>,
+    /// The maximum number of pending connections, or the default of the operating system.
+    pub(crate) tcp_backlog: Option<u32>,
+    /// The `TCP_NODELAY` option of accepted connections, or the default of the operating system.
+    pub(crate) tcp_nodelay: Option<bool>,
+    /// How long the in-flight requests are awaited when the server stops, rounded up to the
+    /// second, or the default of actix-web (30 seconds).
+    pub(crate) shutdown_timeout: Option<Duration>,
+}
+
+impl HttpLayers {
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.compression
+            && self.access_log.is_none()
+            && self.cors.is_none()
+            && self.client_allowlist.is_none()
+            && self.landing_page.is_none()
+            && self.tcp_backlog.is_none()
+            && self.tcp_nodelay.is_none()
+            && self.shutdown_timeout.is_none()
+    }
+}
+
+/// Starts an HTTP server that serves the MCP endpoints wrapped in the given layers.
+pub(crate) async fn start_http_server(
+    server_details: InitializeResult,
+    handler: Arc<dyn McpServerHandler>,
+    options: ActixServerOptions,
+    layers: HttpLayers,
+) -> Result<(), McpSdkError> {
+    let address = options
+        .resolve_server_address()
+        .map_err(|description| McpSdkError::Internal { description })?;
+    let listener = bind(address, layers.tcp_backlog)?;
+
+    tracing::info!(
+        "Streamable HTTP Server is available at http://{}{}{}",
+        address,
+        options.streamable_http_endpoint(),
+        if layers.compression {
+            " (with compression)"
+        } else {
+            ""
+        }
+    );
+
+    serve(listener, server_details, handler, options, layers)?.await?;
+
+    Ok(())
+}
+
+fn bind(address: SocketAddr, backlog: Option<u32>) -> io::Result<TcpListener> {
+    let Some(backlog) = backlog else {
+        return TcpListener::bind(address);
+    };
+
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(i32::try_from(backlog).unwrap_or(i32::MAX))?;
+
+    Ok(socket.into())
+}
+
+fn serve(
+    listener: TcpListener,
+    server_details: InitializeResult,
+    handler: Arc<dyn McpServerHandler>,
+    mut options: ActixServerOptions,
+    layers: HttpLayers,
+) -> Result<Server, McpSdkError> {
+    let mount_options = options.resolve_mount_options();
+    let middlewares: Vec<Arc<dyn Middleware>> =
+        resolve_dns_middleware(&mut options.dns_rebinding, &options.host, options.port)
+            .map(|middleware| Arc::new(middleware) as Arc<dyn Middleware>)
+            .into_iter()
+            .collect();
+    let http_handler = Arc::new(McpHttpHandler::new(
+        None,
+        middlewares,
+        options.health_handler.clone(),
+    ));
+    // the landing page is public, so it is not served when clients must authenticate
+    let landing_page = layers
+        .landing_page
+        .as_ref()
+        .filter(|_| options.auth.is_none())
+        .map(|tools| render_landing_page(&server_details, tools));
+    let state = create_actix_server(server_details, handler, options).state();
+    let tcp_nodelay = layers.tcp_nodelay;
+    let shutdown_timeout = layers.shutdown_timeout;
+
+    let mut server = HttpServer::new(move || {
+        let mut app = App::new();
+
+        if let Some(html) = landing_page.clone() {
+            app = app.route(
+                "/",
+                web::get().to(move || {
+                    let html = html.clone();
+                    async move {
+                        HttpResponse::Ok()
+                            .content_type("text/html; charset=utf-8")
+                            .body(html)
+                    }
+                }),
+            );
+        }
+
+        if let Some(format) = layers.access_log {
+            app = app.app_data(format);
+        }
+        if let Some(origins) = &layers.cors {
+            app = app.app_data(origins.clone());
+        }
+        if let Some(allowlist) = &layers.client_allowlist {
+            app = app.app_data(allowlist.clone());
+        }
+
+        app.wrap(Condition::new(
+            layers.compression,
+            from_fn(skip_small_responses),
+        ))
+        .wrap(Condition::new(layers.compression, Compress::default()))
+        .wrap(Condition::new(
+            layers.client_allowlist.is_some(),
+            from_fn(reject_disallowed_clients),
+        ))
+        .wrap(Condition::new(
+            layers.access_log.is_some(),
+            from_fn(log_access),
+        ))
+        .wrap(Condition::new(layers.cors.is_some(), from_fn(apply_cors)))
+        .service(mcp_scope(
+            state.clone(),
+            http_handler.clone(),
+            &mount_options,
+        ))
+    })
+    .on_connect(move |connection, _| {
+        if let Some(nodelay) = tcp_nodelay
+            && let Some(stream) = connection.downcast_ref::<TcpStream>()
+            && let Err(err) = stream.set_nodelay(nodelay)
+        {
+            tracing::warn!("unable to set TCP_NODELAY: {}", err);
+        }
+    });
+
+    if let Some(shutdown_timeout) = shutdown_timeout {
+        let seconds = shutdown_timeout.as_secs() + u64::from(shutdown_timeout.subsec_nanos() > 0);
+        server = server.shutdown_timeout(seconds);
+    }
+
+    Ok(server.listen(listener)?.run())
+}
+
+/// Marks small responses and event streams as already encoded, so that the [`Compress`]
+/// middleware leaves them untouched. Other responses are buffered to find their size.
+async fn skip_small_responses(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let response = next.call(request).await?;
+
+    let is_event_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"text/event-stream"));
+
+    let (request, response) = response.into_parts();
+    let (response, body) = response.into_parts();
+
+    let (compress, body) = if is_event_stream {
+        (false, body.boxed())
+    } else {
+        let body = body::to_bytes(body)
+            .await
+            .map_err(|err| actix_web::error::ErrorInternalServerError(err.into()))?;
+        (body.len() as u64 >= MIN_COMPRESSED_SIZE, body.boxed())
+    };
+
+    let mut response = ServiceResponse::new(request, response.set_body(body));
+
+    if !compress {
+        response.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static("identity"),
+        );
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use rust_mcp_sdk::{
+        McpServer, ToMcpServerHandler,
+        mcp_server::ServerHandler,
+        schema::{
+            Implementation, LATEST_PROTOCOL_VERSION, ListToolsResult, PaginatedRequestParams,
+            RpcError, ServerCapabilities, ServerCapabilitiesTools,
+        },
+    };
+
+    use super::*;
+
+    struct ListToolsHandler {
+        description: String,
+        delay: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl ServerHandler for ListToolsHandler {
+        async fn handle_list_tools_request(
+            &self,
+            _params: Option<PaginatedRequestParams>,
+            _runtime: Arc<dyn McpServer>,
+        ) -> Result<ListToolsResult, RpcError> {
+            tokio::time::sleep(self.delay).await;
+
+            let tool = serde_json::from_value(serde_json::json!({
+                "name": "tool",
+                "description": self.description,
+                "inputSchema": { "type": "object" },
+            }))
+            .unwrap();
+
+            Ok(ListToolsResult {
+                meta: None,
+                next_cursor: None,
+                tools: vec![tool],
+            })
+        }
+    }
+
+    fn server_details() -> InitializeResult {
+        InitializeResult {
+            server_info: Implementation {
+                name: "compressed-server".to_owned(),
+                version: "1.0.0".to_owned(),
+                title: None,
+                description: None,
+                website_url: None,
+                icons: Default::default(),
+            },
+            capabilities: ServerCapabilities {
+                tools: Some(ServerCapabilitiesTools { list_changed: None }),
+                ..Default::default()
+            },
+            meta: None,
+            instructions: None,
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+        }
+    }
+
+    /// Sends a JSON-RPC message and returns the connection to read the response from.
+    fn send(
+        address: std::net::SocketAddr,
+        session_id: Option<&str>,
+        message: serde_json::Value,
+    ) -> std::net::TcpStream {
+        let body = message.to_string();
+        let session_header = session_id
+            .map(|session_id| format!("Mcp-Session-Id: {session_id}\r\n"))
+            .unwrap_or_default();
+
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST /mcp HTTP/1.1\r\n\
+             Host: {address}\r\n\
+             Content-Type: application/json\r\n\
+             Accept: application/json, text/event-stream\r\n\
+             Accept-Encoding: gzip\r\n\
+             {session_header}\
+             Connection: close\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+
+        stream
+    }
+
+    /// Sends a JSON-RPC message and returns the response headers in lowercase.
+    fn post(
+        address: std::net::SocketAddr,
+        session_id: Option<&str>,
+        message: serde_json::Value,
+    ) -> String {
+        let mut stream = send(address, session_id, message);
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let headers_end = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap();
+
+        String::from_utf8(response[..headers_end].to_vec())
+            .unwrap()
+            .to_lowercase()
+    }
+
+    fn initialize(address: std::net::SocketAddr) -> String {
+        let headers = post(
+            address,
+            None,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": {
+                    "capabilities": {},
+                    "clientInfo": { "name": "test-client", "version": "1.0.0" },
+                    "protocolVersion": LATEST_PROTOCOL_VERSION,
+                },
+            }),
+        );
+        headers
+            .lines()
+            .find_map(|line| line.strip_prefix("mcp-session-id: "))
+            .unwrap()
+            .to_owned()
+    }
+
+    fn list_tools_request() -> serde_json::Value {
+        serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" })
+    }
+
+    fn list_tools(address: std::net::SocketAddr) -> String {
+        let session_id = initialize(address);
+
+        post(address, Some(&session_id), list_tools_request())
+    }
+
+    fn start(
+        handler: ListToolsHandler,
+        layers: HttpLayers,
+    ) -> (std::net::SocketAddr, actix_web::dev::ServerHandle) {
+        start_with_options(handler, ActixServerOptions::default(), layers)
+    }
+
+    fn start_with_options(
+        handler: ListToolsHandler,
+        options: ActixServerOptions,
+        layers: HttpLayers,
+    ) -> (std::net::SocketAddr, actix_web::dev::ServerHandle) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let options = ActixServerOptions {
+            port: address.port(),
+            ..options
+        };
+
+        let server = serve(
+            listener,
+            server_details(),
+            handler.to_mcp_server_handler(),
+            options,
+            layers,
+        )
+        .unwrap();
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        (address, handle)
+    }
+
+    async fn list_tools_headers(description: String, json_response: bool) -> String {
+        let handler = ListToolsHandler {
+            description,
+            delay: Duration::ZERO,
+        };
+        let (address, handle) = start_with_options(
+            handler,
+            ActixServerOptions {
+                enable_json_response: Some(json_response),
+                ..Default::default()
+            },
+            HttpLayers {
+                compression: true,
+                tcp_nodelay: Some(true),
+                ..Default::default()
+            },
+        );
+
+        let headers = tokio::task::spawn_blocking(move || list_tools(address))
+            .await
+            .unwrap();
+        handle.stop(false).await;
+
+        headers
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn large_responses_are_compressed() {
+        let headers = list_tools_headers("a".repeat(4096), true).await;
+
+        assert!(headers.contains("content-encoding: gzip"), "{headers}");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn small_responses_are_not_compressed() {
+        let headers = list_tools_headers("A small tool".to_owned(), true).await;
+
+        assert!(!headers.contains("content-encoding: gzip"), "{headers}");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn event_streams_are_kept_and_not_compressed() {
+        let headers = list_tools_headers("a".repeat(4096), false).await;
+
+        assert!(
+            headers.contains("content-type: text/event-stream"),
+            "{headers}"
+        );
+        assert!(!headers.contains("content-encoding: gzip"), "{headers}");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn slow_requests_are_abandoned_after_the_shutdown_timeout() {
+        let handler = ListToolsHandler {
+            description: "A slow tool".to_owned(),
+            delay: Duration::from_secs(120),
+        };
+        let (address, handle) = start(
+            handler,
+            HttpLayers {
+                shutdown_timeout: Some(Duration::from_secs(1)),
+                ..Default::default()
+            },
+        );
+
+        let session_id = tokio::task::spawn_blocking(move || initialize(address))
+            .await
+            .unwrap();
+        let mut stream = send(address, Some(&session_id), list_tools_request());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let start = std::time::Instant::now();
+        handle.stop(true).await;
+
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "{:?}",
+            start.elapsed()
+        );
+        // the connection is closed without a response
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response);
+        assert!(!String::from_utf8_lossy(&response).contains("result"));
+    }
+
+    #[test]
+    fn listeners_with_a_backlog_accept_connections() {
+        let listener = bind(([127, 0, 0, 1], 0).into(), Some(16)).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let _client = std::net::TcpStream::connect(address).unwrap();
+
+        assert!(listener.accept().is_ok());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use rust_mcp_sdk::schema::{CallToolRequestParams, CallToolResult};
+use serde_json::{Map, Value};
+
+use crate::tool::ToolError;
+
+/// The field of the `_meta` object of a tool call holding its idempotency key.
+pub(crate) const IDEMPOTENCY_KEY_FIELD: &str = "idempotencyKey";
+
+/// Returns the idempotency key sent in the `_meta` object of the tool call, if any.
+pub(crate) fn idempotency_key(params: &CallToolRequestParams) -> Option<&str> {
+    params
+        .meta
+        .as_ref()?
+        .extra
+        .as_ref()?
+        .get(IDEMPOTENCY_KEY_FIELD)?
+        .as_str()
+}
+
+/// Identifies the calls that share a cached result: the calls to the same tool with the same
+/// idempotency key, from the same HTTP session. Calls received without a session, like the
+/// calls of the stdio transport, share the keys of their single client.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct IdempotentCall {
+    session_id: Option<String>,
+    tool_name: String,
+    key: String,
+}
+
+impl IdempotentCall {
+    pub(crate) fn new(session_id: Option<&str>, tool_name: &str, key: &str) -> Self {
+        Self {
+            session_id: session_id.map(str::to_owned),
+            tool_name: tool_name.to_owned(),
+            key: key.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CachedResult {
+    arguments: Option<Map<String, Value>>,
+    result: CallToolResult,
+    stored_at: Instant,
+}
+
+/// The results of the recent tool calls sent with an idempotency key, replayed when the same
+/// call is sent again, until they are older than the TTL.
+///
+/// A result is stored once its call completes, so concurrent calls with the same key all run
+/// the tool.
+#[derive(Debug)]
+pub(crate) struct IdempotencyCache {
+    ttl: Duration,
+    results: Mutex<HashMap<IdempotentCall, CachedResult>>,
+}
+
+impl IdempotencyCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            results: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the result of the previous call with the same key, or an error when the key
+    /// was used with other arguments.
+    pub(crate) fn get(
+        &self,
+        call: &IdempotentCall,
+        arguments: Option<&Map<String, Value>>,
+    ) -> Result<Option<CallToolResult>, ToolError> {
+        self.get_at(call, arguments, Instant::now())
+    }
+
+    pub(crate) fn insert(
+        &self,
+        call: IdempotentCall,
+        arguments: Option<Map<String, Value>>,
+        result: CallToolResult,
+    ) {
+        self.insert_at(call, arguments, result, Instant::now());
+    }
+
+    fn get_at(
+        &self,
+        call: &IdempotentCall,
+        arguments: Option<&Map<String, Value>>,
+        now: Instant,
+    ) -> Result<Option<CallToolResult>, ToolError> {
+        let mut results = self.lock();
+        results.retain(|_, cached| now.saturating_duration_since(cached.stored_at) <= self.ttl);
+
+        match results.get(call) {
+            Some(cached) if cached.arguments.as_ref() != arguments => {
+                Err(ToolError::from(format!(
+                    "the idempotency key `{}` was already used to call `{}` with other arguments",
+                    call.key, call.tool_name
+                )))
+            }
+            Some(cached) => Ok(Some(cached.result.clone())),
+            None => Ok(None),
+        }
+    }
+
+    fn insert_at(
+        &self,
+        call: IdempotentCall,
+        arguments: Option<Map<String, Value>>,
+        result: CallToolResult,
+        now: Instant,
+    ) {
+        self.lock().insert(
+            call,
+            CachedResult {
+                arguments,
+                result,
+                stored_at: now,
+            },
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<IdempotentCall, CachedResult>> {
+        self.results
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_result(text: &str) -> CallToolResult {
+        CallToolResult::text_content(vec![text.to_owned().into()])
+    }
+
+    fn text(result: Option<CallToolResult>) -> Option<String> {
+        result.map(|result| result.content[0].as_text_content().unwrap().text.clone())
+    }
+
+    fn call(session_id: Option<&str>, tool_name: &str, key: &str) -> IdempotentCall {
+        IdempotentCall::new(session_id, tool_name, key)
+    }
+
+    fn amount(value: i64) -> Option<Map<String, Value>> {
+        serde_json::json!({ "amount": value }).as_object().cloned()
+    }
+
+    #[test]
+    fn results_are_cached_per_session_tool_and_key() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+
+        cache.insert(call(None, "transfer", "key-1"), None, text_result("done"));
+        cache.insert(
+            call(Some("session-1"), "transfer", "key-1"),
+            None,
+            text_result("done in session"),
+        );
+
+        let get = |call: IdempotentCall| text(cache.get(&call, None).unwrap());
+        assert_eq!(
+            get(call(None, "transfer", "key-1")).as_deref(),
+            Some("done")
+        );
+        assert_eq!(
+            get(call(Some("session-1"), "transfer", "key-1")).as_deref(),
+            Some("done in session")
+        );
+        assert!(get(call(Some("session-2"), "transfer", "key-1")).is_none());
+        assert!(get(call(None, "transfer", "key-2")).is_none());
+        assert!(get(call(None, "refund", "key-1")).is_none());
+    }
+
+    #[test]
+    fn keys_reused_with_other_arguments_are_rejected() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let transfer = call(None, "transfer", "key-1");
+
+        cache.insert(transfer.clone(), amount(10), text_result("done"));
+
+        assert_eq!(
+            text(cache.get(&transfer, amount(10).as_ref()).unwrap()).as_deref(),
+            Some("done")
+        );
+        assert_eq!(
+            cache
+                .get(&transfer, amount(20).as_ref())
+                .unwrap_err()
+                .to_string(),
+            "the idempotency key `key-1` was already used to call `transfer` with other arguments"
+        );
+    }
+
+    #[test]
+    fn results_expire_after_the_ttl() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let transfer = call(None, "transfer", "key-1");
+        let start = Instant::now();
+
+        cache.insert_at(transfer.clone(), None, text_result("done"), start);
+
+        assert!(
+            cache
+                .get_at(&transfer, None, start + Duration::from_secs(30))
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            cache
+                .get_at(&transfer, None, start + Duration::from_secs(120))
+                .unwrap()
+                .is_none()
+        );
+    }
+}
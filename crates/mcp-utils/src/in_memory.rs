@@ -0,0 +1,80 @@
+use rust_mcp_sdk::schema::{
+    CallToolRequestParams, CallToolResult, ClientCapabilities, CompleteRequestParams,
+    CompleteResult, RpcError, Tool, schema_utils::CallToolError,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{server::Handler, tool_box::ToolBox};
+
+/// A client connected to a server running in the same process, returned by
+/// [`ServerBuilder::start_in_memory`](crate::server_prelude::ServerBuilder::start_in_memory).
+///
+/// Requests and results are converted to JSON and back, like they would be when sent over
+/// a transport, but no I/O is involved. This makes end-to-end tests of a tool box fast and
+/// deterministic.
+pub struct InMemoryClient<T> {
+    handler: Handler<T>,
+    client_capabilities: Option<ClientCapabilities>,
+}
+
+impl<T> InMemoryClient<T>
+where
+    T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+{
+    pub(crate) fn new(handler: Handler<T>) -> Self {
+        Self {
+            handler,
+            client_capabilities: None,
+        }
+    }
+
+    /// Sets the capabilities that tools see as the capabilities of the client.
+    pub fn with_client_capabilities(mut self, client_capabilities: ClientCapabilities) -> Self {
+        self.client_capabilities = Some(client_capabilities);
+        self
+    }
+
+    pub fn list_tools(&self) -> Vec<Tool> {
+        self.handler
+            .list_tools()
+            .iter()
+            .map(round_trip)
+            .collect::<Result<_, _>>()
+            .expect("tools must convert to JSON and back")
+    }
+
+    pub async fn call_tool(
+        &self,
+        params: CallToolRequestParams,
+    ) -> Result<CallToolResult, CallToolError> {
+        let params = round_trip(&params).map_err(CallToolError::new)?;
+
+        let result = self
+            .handler
+            .call_tool(
+                params,
+                self.handler.context(self.client_capabilities.clone(), None),
+            )
+            .await?;
+
+        round_trip(&result).map_err(CallToolError::new)
+    }
+
+    /// Requests the values suggested for an argument by the provider set with
+    /// [`ServerBuilder::with_completions`](crate::server_prelude::ServerBuilder::with_completions).
+    pub fn complete(&self, params: CompleteRequestParams) -> Result<CompleteResult, RpcError> {
+        let params = round_trip(&params)
+            .map_err(|error| RpcError::invalid_params().with_message(error.to_string()))?;
+
+        let result = self.handler.complete(&params)?;
+
+        Ok(round_trip(&result).expect("completion results must convert to JSON and back"))
+    }
+}
+
+fn round_trip<V>(value: &V) -> Result<V, serde_json::Error>
+where
+    V: Serialize + DeserializeOwned,
+{
+    serde_json::to_string(value).and_then(|json| serde_json::from_str(&json))
+}
@@ -0,0 +1,124 @@
+use std::fmt::Write;
+
+use rust_mcp_sdk::schema::{InitializeResult, Tool};
+
+/// Renders the HTML page served at the root of the HTTP server, set with
+/// [`ServerBuilder::with_landing_page`](crate::server_prelude::ServerBuilder::with_landing_page).
+pub(crate) fn render_landing_page(server_details: &InitializeResult, tools: &[Tool]) -> String {
+    let server_info = &server_details.server_info;
+    let title = escape(server_info.title.as_deref().unwrap_or(&server_info.name));
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n\
+         <body>\n<h1>{title}</h1>\n<p><code>{}</code> version {}</p>\n",
+        escape(&server_info.name),
+        escape(&server_info.version),
+    );
+
+    if let Some(description) = &server_info.description {
+        let _ = writeln!(html, "<p>{}</p>", escape(description));
+    }
+    if let Some(instructions) = server_details
+        .instructions
+        .as_deref()
+        .filter(|instructions| !instructions.is_empty())
+    {
+        let _ = writeln!(html, "<pre>{}</pre>", escape(instructions));
+    }
+
+    html.push_str("<h2>Tools</h2>\n<ul>\n");
+    for tool in tools {
+        let _ = write!(html, "<li><code>{}</code>", escape(&tool.name));
+        if let Some(description) = &tool.description {
+            let _ = write!(html, ": {}", escape(description));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+
+    html
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_mcp_sdk::schema::{Implementation, LATEST_PROTOCOL_VERSION};
+
+    use super::*;
+
+    fn server_details(title: Option<&str>) -> InitializeResult {
+        InitializeResult {
+            server_info: Implementation {
+                name: "weather-server".to_owned(),
+                version: "1.2.0".to_owned(),
+                title: title.map(str::to_owned),
+                description: None,
+                website_url: None,
+                icons: Default::default(),
+            },
+            capabilities: Default::default(),
+            meta: None,
+            instructions: Some("Ask about the weather.".to_owned()),
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+        }
+    }
+
+    fn tool(name: &str, description: &str) -> Tool {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "description": description,
+            "inputSchema": { "type": "object" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn landing_page_describes_the_server_and_its_tools() {
+        let html = render_landing_page(
+            &server_details(Some("Weather Station")),
+            &[tool("forecast", "Returns the forecast")],
+        );
+
+        assert!(html.contains("<title>Weather Station</title>"), "{html}");
+        assert!(
+            html.contains("<code>weather-server</code> version 1.2.0"),
+            "{html}"
+        );
+        assert!(html.contains("Ask about the weather."), "{html}");
+        assert!(
+            html.contains("<li><code>forecast</code>: Returns the forecast</li>"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn landing_page_escapes_html() {
+        let html = render_landing_page(
+            &server_details(Some("<script>")),
+            &[tool("compare", "Checks if a < b & b > c")],
+        );
+
+        assert!(html.contains("<h1>&lt;script&gt;</h1>"), "{html}");
+        assert!(html.contains("Checks if a &lt; b &amp; b &gt; c"), "{html}");
+    }
+}
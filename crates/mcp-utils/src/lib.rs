@@ -15,6 +15,10 @@
 //! - [`tool::AsyncTextTool`] – Returns plain text responses (asynchronous)
 //! - [`tool::StructuredTool`] – Returns structured JSON data (synchronous)
 //! - [`tool::AsyncStructuredTool`] – Returns structured JSON data (asynchronous)
+//! - [`tool::AudioTool`] – Returns audio data (synchronous)
+//! - [`tool::AsyncAudioTool`] – Returns audio data (asynchronous)
+//! - [`tool::EmbeddedResourceTool`] – Returns a resource with its contents (synchronous)
+//! - [`tool::AsyncEmbeddedResourceTool`] – Returns a resource with its contents (asynchronous)
 //!
 //! All traits provide flexible output handling. Return [`Result`](https://doc.rust-lang.org/std/result/enum.Result.html)
 //! objects, plain strings, or anything that implements [`Serialize`](https://docs.rs/serde/latest/serde/trait.Serialize.html).
@@ -83,10 +87,35 @@
 //! - [`tool_prelude`] - Everything needed for defining tools
 //! - [`server_prelude`] - Everything needed for server setup and tool aggregation
 
+extern crate self as mcp_utils;
+
+mod access_log;
+mod audit_log;
+mod client_allowlist;
+mod coercion;
+mod completion;
+mod context;
+mod cors;
+mod env_expansion;
+mod field_limit;
+mod http_mode;
+mod http_server;
+mod idempotency;
+#[cfg(feature = "test-util")]
+mod in_memory;
+mod landing_page;
+mod locale;
+mod markdown_check;
+mod rate_limit;
+mod redaction;
+mod result_limit;
 mod server;
 mod server_config;
+mod session;
 mod tool;
 mod tool_box;
+mod types;
+mod wire_trace;
 
 pub mod tool_prelude {
     //! Everything needed for defining MCP tools.
@@ -94,10 +123,17 @@ pub mod tool_prelude {
     //! This module re-exports the tool traits, error types, and necessary macros
     //! from both this crate and `rust-mcp-sdk`.
 
+    pub use super::context::{ToolContext, TransportKind};
+    pub use super::session::SessionState;
     pub use super::tool::{
-        AsyncStructuredTool, AsyncTextTool, CustomTool, StructuredTool, TextTool, ToolError,
+        Annotated, AsyncAudioTool, AsyncEmbeddedResourceTool, AsyncStructuredTool, AsyncTextTool,
+        Audio, AudioTool, Base64, ChannelTextTool, CustomTool, EmbeddedResourceTool, Resource,
+        StructuredTool, TextBatch, TextTool, ToolError, ToolOutput, WithMeta,
     };
+    pub use super::types::HumanDuration;
+    pub use mcp_utils_derive::ToolOutput;
     pub use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
+    pub use rust_mcp_sdk::schema::{ContentBlock, ProgressToken, Role, ToolOutputSchema};
     pub use serde::{Deserialize, Serialize};
 }
 
@@ -106,7 +142,25 @@ pub mod server_prelude {
     //!
     //! This module provides the server builder, tool aggregation macro, and related types.
 
-    pub use super::server::ServerBuilder;
-    pub use super::tool_box::{ToolBox, setup_tools};
+    pub use super::access_log::AccessLogFormat;
+    pub use super::completion::CompletionProvider;
+    pub use super::field_limit::OversizedField;
+    pub use super::http_mode::HttpMode;
+    #[cfg(feature = "test-util")]
+    pub use super::in_memory::InMemoryClient;
+    pub use super::result_limit::OversizedResult;
+    pub use super::server::{ServerBuilder, TOOL_TIMEOUT_ERROR_CODE};
+    pub use super::tool_box::{CompositeToolBox, ToolBox, ToolSort, setup_tools};
+    pub use ipnet::IpNet;
+    pub use rust_mcp_actix::ActixServerOptions;
     pub use rust_mcp_sdk::mcp_server::ServerRuntime;
+    pub use rust_mcp_sdk::schema::{CompleteRequestArgument, CompleteRequestRef};
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Items used by the code generated by the derive macros.
+
+    pub use super::tool::{JsonSchema, output_schema};
+    pub use serde_json;
 }
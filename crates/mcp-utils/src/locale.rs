@@ -0,0 +1,92 @@
+use rust_mcp_sdk::schema::InitializeRequestParams;
+
+/// The field of the `_meta` object of the `initialize` request holding the locales preferred
+/// by the client.
+pub(crate) const LOCALE_FIELD: &str = "locale";
+
+/// Returns the locale preference sent in the `_meta` object of the `initialize` request, if
+/// any.
+pub(crate) fn client_locale(params: &InitializeRequestParams) -> Option<&str> {
+    params
+        .meta
+        .as_ref()?
+        .extra
+        .as_ref()?
+        .get(LOCALE_FIELD)?
+        .as_str()
+}
+
+/// Returns the available locale that best matches the preference, written like an
+/// `Accept-Language` header: `fr-CA, fr;q=0.8, en;q=0.5`. Preferred locales are tried by
+/// decreasing weight, first as written and then by their language alone, so `fr-CA` selects
+/// `fr` when no Canadian French is available. Locales are compared without case.
+pub(crate) fn negotiate_locale<'a>(
+    preference: &str,
+    available: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let available: Vec<&str> = available.into_iter().collect();
+
+    let mut preferred: Vec<(&str, f32)> = preference
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            let weight = parts
+                .find_map(|parameter| parameter.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |weight| weight.trim().parse().ok())?;
+
+            (!tag.is_empty() && tag != "*" && weight > 0.0).then_some((tag, weight))
+        })
+        .collect();
+    preferred.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    preferred.into_iter().find_map(|(tag, _)| {
+        available
+            .iter()
+            .find(|locale| locale.eq_ignore_ascii_case(tag))
+            .or_else(|| {
+                available
+                    .iter()
+                    .find(|locale| language(locale).eq_ignore_ascii_case(language(tag)))
+            })
+            .copied()
+    })
+}
+
+fn language(locale: &str) -> &str {
+    locale.split(['-', '_']).next().unwrap_or(locale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AVAILABLE: [&str; 3] = ["en", "fr", "pt-BR"];
+
+    #[test]
+    fn exact_locales_are_selected() {
+        assert_eq!(negotiate_locale("fr", AVAILABLE), Some("fr"));
+        assert_eq!(negotiate_locale("PT-br", AVAILABLE), Some("pt-BR"));
+    }
+
+    #[test]
+    fn regional_locales_fall_back_to_their_language() {
+        assert_eq!(negotiate_locale("fr-CA", AVAILABLE), Some("fr"));
+        assert_eq!(negotiate_locale("pt", AVAILABLE), Some("pt-BR"));
+    }
+
+    #[test]
+    fn preferences_are_tried_by_decreasing_weight() {
+        assert_eq!(
+            negotiate_locale("de, en;q=0.5, fr;q=0.8", AVAILABLE),
+            Some("fr")
+        );
+        assert_eq!(negotiate_locale("fr;q=0, en", AVAILABLE), Some("en"));
+    }
+
+    #[test]
+    fn unavailable_locales_select_nothing() {
+        assert_eq!(negotiate_locale("de, *", AVAILABLE), None);
+        assert_eq!(negotiate_locale("", AVAILABLE), None);
+    }
+}
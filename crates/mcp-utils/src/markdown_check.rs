@@ -0,0 +1,99 @@
+/// Returns a description of the obvious markdown mistakes of the text: code fences that are
+/// never closed and headings without a space after their `#` markers. The check is line based
+/// and does not try to parse the markdown, so it only reports what clients are very likely to
+/// render badly.
+pub(crate) fn find_markdown_issues(text: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+    let mut open_fence: Option<(usize, &str)> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let trimmed = line.trim_start();
+
+        if let Some(fence) = fence_marker(trimmed) {
+            match open_fence {
+                Some((_, opening)) if fence.starts_with(opening) && is_closing_fence(trimmed) => {
+                    open_fence = None;
+                }
+                Some(_) => {}
+                None => open_fence = Some((line_number, fence)),
+            }
+            continue;
+        }
+
+        if open_fence.is_none() && is_heading_without_space(trimmed) {
+            issues.push(format!(
+                "line {}: heading is missing a space after `#`",
+                line_number
+            ));
+        }
+    }
+
+    if let Some((line_number, _)) = open_fence {
+        issues.push(format!("line {}: code fence is never closed", line_number));
+    }
+
+    issues
+}
+
+/// Returns the run of backticks or tildes opening a code fence, when the line starts one.
+fn fence_marker(line: &str) -> Option<&str> {
+    let character = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = line.chars().take_while(|c| *c == character).count();
+
+    (length >= 3).then(|| &line[..length])
+}
+
+fn is_closing_fence(line: &str) -> bool {
+    line.trim_start_matches(['`', '~']).trim().is_empty()
+}
+
+fn is_heading_without_space(line: &str) -> bool {
+    let level = line.chars().take_while(|c| *c == '#').count();
+
+    (1..=6).contains(&level)
+        && line[level..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn well_formed_markdown_has_no_issues() {
+        let text = "# Usage\n\nCall the tool:\n\n```json\n{}\n#not-a-heading\n```\n";
+
+        assert!(find_markdown_issues(text).is_empty());
+    }
+
+    #[test]
+    fn headings_without_space_are_reported() {
+        assert_eq!(
+            find_markdown_issues("Intro\n##Usage"),
+            vec!["line 2: heading is missing a space after `#`".to_owned()]
+        );
+    }
+
+    #[test]
+    fn unbalanced_code_fences_are_reported() {
+        let text = "# Usage\n\n```json\n{}\n\nMore text";
+
+        assert_eq!(
+            find_markdown_issues(text),
+            vec!["line 3: code fence is never closed".to_owned()]
+        );
+    }
+
+    #[test]
+    fn fences_only_close_with_the_same_marker() {
+        let text = "````\n```\nnested\n```\n";
+
+        assert_eq!(
+            find_markdown_issues(text),
+            vec!["line 1: code fence is never closed".to_owned()]
+        );
+    }
+}
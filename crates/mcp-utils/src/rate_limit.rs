@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use rust_mcp_sdk::schema::schema_utils::CallToolError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RateLimit {
+    pub(crate) max_per_interval: u32,
+    pub(crate) interval: Duration,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.max_per_interval as f64,
+            last_refill: now,
+        }
+    }
+
+    /// Takes a token from the bucket, or returns how long to wait until a token is available.
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let capacity = self.limit.max_per_interval as f64;
+        let elapsed = now.saturating_duration_since(self.last_refill);
+
+        self.tokens = (self.tokens
+            + capacity * elapsed.as_secs_f64() / self.limit.interval.as_secs_f64())
+        .min(capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.limit.interval.mul_f64((1.0 - self.tokens) / capacity))
+        }
+    }
+}
+
+/// Limits the number of calls to tools that have a configured [`RateLimit`].
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limits: &HashMap<String, RateLimit>) -> Self {
+        let now = Instant::now();
+
+        Self {
+            buckets: Mutex::new(
+                limits
+                    .iter()
+                    .map(|(name, &limit)| (name.clone(), TokenBucket::new(limit, now)))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub(crate) fn check(&self, tool_name: &str) -> Result<(), CallToolError> {
+        self.check_at(tool_name, Instant::now())
+    }
+
+    fn check_at(&self, tool_name: &str, now: Instant) -> Result<(), CallToolError> {
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match buckets.get_mut(tool_name) {
+            Some(bucket) => bucket.take(now).map_err(|retry_after| {
+                CallToolError::from_message(format!(
+                    "tool `{}` is rate limited, retry after {}s",
+                    tool_name,
+                    retry_after.as_secs_f64().ceil()
+                ))
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(max_per_interval: u32, interval: Duration) -> RateLimiter {
+        RateLimiter::new(&HashMap::from([(
+            "limited".to_owned(),
+            RateLimit {
+                max_per_interval,
+                interval,
+            },
+        )]))
+    }
+
+    #[test]
+    fn calls_fail_once_the_bucket_is_empty() {
+        let limiter = limiter(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert!(limiter.check_at("limited", now).is_ok());
+        assert!(limiter.check_at("limited", now).is_ok());
+        assert_eq!(
+            limiter.check_at("limited", now).unwrap_err().to_string(),
+            "tool `limited` is rate limited, retry after 5s"
+        );
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let limiter = limiter(1, Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert!(limiter.check_at("limited", now).is_ok());
+        assert!(limiter.check_at("limited", now).is_err());
+        assert!(
+            limiter
+                .check_at("limited", now + Duration::from_secs(10))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn tools_without_limit_are_not_limited() {
+        let limiter = limiter(1, Duration::from_secs(10));
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert!(limiter.check_at("unlimited", now).is_ok());
+        }
+    }
+}
@@ -0,0 +1,23 @@
+/// The value written in place of redacted fields.
+const REDACTED: &str = "[redacted]";
+
+/// Replaces the values of the object fields whose name is secret, at any depth.
+pub(crate) fn redact(value: &mut serde_json::Value, is_secret: &impl Fn(&str) -> bool) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if is_secret(key) {
+                    *value = REDACTED.into();
+                } else {
+                    redact(value, is_secret);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                redact(value, is_secret);
+            }
+        }
+        _ => {}
+    }
+}
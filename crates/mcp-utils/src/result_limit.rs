@@ -0,0 +1,190 @@
+use rust_mcp_sdk::schema::{CallToolResult, ContentBlock, schema_utils::CallToolError};
+use serde_json::{Map, Value};
+
+/// Determines how a tool result larger than the limit set with
+/// [`ServerBuilder::with_max_result_bytes`](crate::server_prelude::ServerBuilder::with_max_result_bytes)
+/// is handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OversizedResult {
+    /// The result is replaced with a tool error.
+    #[default]
+    Reject,
+    /// The content of the result is cut to fit within the limit. Since the structured content
+    /// would no longer match the truncated content, it is removed. Results holding only
+    /// structured content have nothing left to truncate, so they are rejected.
+    Truncate,
+}
+
+pub(crate) fn limit_result(
+    mut result: CallToolResult,
+    max_bytes: usize,
+    oversized: OversizedResult,
+) -> Result<CallToolResult, CallToolError> {
+    let size = result.content.iter().map(content_size).sum::<usize>()
+        + result
+            .structured_content
+            .as_ref()
+            .map(structured_content_size)
+            .unwrap_or_default();
+
+    if size <= max_bytes {
+        return Ok(result);
+    }
+
+    match oversized {
+        OversizedResult::Truncate if !result.content.is_empty() => {
+            let mut remaining = max_bytes;
+
+            result.content = result
+                .content
+                .into_iter()
+                .filter_map(|mut block| {
+                    let size = content_size(&block);
+
+                    if size <= remaining {
+                        remaining -= size;
+                        return Some(block);
+                    }
+
+                    match &mut block {
+                        ContentBlock::TextContent(text_content) if remaining > 0 => {
+                            let end = floor_char_boundary(&text_content.text, remaining);
+                            text_content.text.truncate(end);
+                            remaining -= end;
+                            Some(block)
+                        }
+                        _ => None,
+                    }
+                })
+                .collect();
+            result.structured_content = None;
+
+            Ok(result)
+        }
+        _ => Err(CallToolError::from_message(format!(
+            "tool result is {} bytes, which exceeds the limit of {} bytes",
+            size, max_bytes
+        ))),
+    }
+}
+
+fn structured_content_size(structured_content: &Map<String, Value>) -> usize {
+    serde_json::to_string(structured_content)
+        .map(|json| json.len())
+        .unwrap_or_default()
+}
+
+fn content_size(block: &ContentBlock) -> usize {
+    match block {
+        ContentBlock::TextContent(text_content) => text_content.text.len(),
+        ContentBlock::ImageContent(image_content) => image_content.data.len(),
+        ContentBlock::AudioContent(audio_content) => audio_content.data.len(),
+        other => serde_json::to_string(other)
+            .map(|json| json.len())
+            .unwrap_or_default(),
+    }
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    (0..=index.min(text.len()))
+        .rev()
+        .find(|&index| text.is_char_boundary(index))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_result(texts: &[&str]) -> CallToolResult {
+        CallToolResult::text_content(texts.iter().map(|&text| text.to_owned().into()).collect())
+    }
+
+    fn texts(result: &CallToolResult) -> Vec<&str> {
+        result
+            .content
+            .iter()
+            .map(|block| block.as_text_content().unwrap().text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn result_within_limit_is_unchanged() {
+        let result = limit_result(text_result(&["hello"]), 5, OversizedResult::Reject).unwrap();
+
+        assert_eq!(texts(&result), vec!["hello"]);
+    }
+
+    #[test]
+    fn oversized_result_is_rejected() {
+        let error =
+            limit_result(text_result(&["hello", "world"]), 8, OversizedResult::Reject).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "tool result is 10 bytes, which exceeds the limit of 8 bytes"
+        );
+    }
+
+    #[test]
+    fn oversized_result_is_truncated() {
+        let result = limit_result(
+            text_result(&["hello", "world", "!"]),
+            8,
+            OversizedResult::Truncate,
+        )
+        .unwrap();
+
+        assert_eq!(texts(&result), vec!["hello", "wor"]);
+    }
+
+    fn structured_result(fallback: bool) -> CallToolResult {
+        let structured = serde_json::json!({ "temperature": 21 });
+        let mut result = CallToolResult::from_content(Vec::new())
+            .with_structured_content(structured.as_object().unwrap().clone());
+        if fallback {
+            result
+                .content
+                .push(ContentBlock::text_content(structured.to_string()));
+        }
+        result
+    }
+
+    #[test]
+    fn structured_content_counts_toward_the_limit() {
+        let error =
+            limit_result(structured_result(false), 16, OversizedResult::Reject).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "tool result is 18 bytes, which exceeds the limit of 16 bytes"
+        );
+        assert!(limit_result(structured_result(false), 18, OversizedResult::Reject).is_ok());
+    }
+
+    #[test]
+    fn truncation_removes_the_structured_content() {
+        let result = limit_result(structured_result(true), 20, OversizedResult::Truncate).unwrap();
+
+        assert_eq!(texts(&result), vec!["{\"temperature\":21}"]);
+        assert!(result.structured_content.is_none());
+    }
+
+    #[test]
+    fn structured_only_results_are_rejected_instead_of_truncated() {
+        let error =
+            limit_result(structured_result(false), 16, OversizedResult::Truncate).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "tool result is 18 bytes, which exceeds the limit of 16 bytes"
+        );
+    }
+
+    #[test]
+    fn truncation_keeps_characters_whole() {
+        let result = limit_result(text_result(&["héllo"]), 2, OversizedResult::Truncate).unwrap();
+
+        assert_eq!(texts(&result), vec!["h"]);
+    }
+}
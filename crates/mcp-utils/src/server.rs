@@ -1,19 +1,67 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+    panic::AssertUnwindSafe,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
+use futures::FutureExt;
+use ipnet::IpNet;
 use rust_mcp_actix::{ActixServerOptions, create_actix_server};
 use rust_mcp_sdk::{
-    McpServer, StdioTransport, ToMcpServerHandler, TransportOptions,
+    McpObserver, McpServer, SessionId, StdioTransport, ToMcpServerHandler, TransportError,
+    TransportOptions,
     error::McpSdkError,
+    event_store::InMemoryEventStore,
     mcp_server::{McpServerOptions, ServerHandler, server_runtime::create_server},
     schema::{
-        CallToolRequestParams, CallToolResult, Implementation, InitializeResult,
-        LATEST_PROTOCOL_VERSION, ListToolsResult, PaginatedRequestParams, RpcError,
-        ServerCapabilities, ServerCapabilitiesTools, schema_utils::CallToolError,
+        CallToolRequestParams, CallToolResult, ClientCapabilities, ClientMessage, CompleteRequest,
+        CompleteRequestParams, CompleteResult, Implementation, InitializeRequestParams,
+        InitializeResult, LATEST_PROTOCOL_VERSION, ListToolsResult, PaginatedRequestParams,
+        RpcError, ServerCapabilities, ServerCapabilitiesTools, ServerMessage, Tool,
+        schema_utils::{CallToolError, UnknownTool},
+    },
+};
+
+#[cfg(feature = "test-util")]
+use crate::in_memory::InMemoryClient;
+use crate::{
+    access_log::AccessLogFormat,
+    audit_log::AuditLog,
+    client_allowlist::ClientAllowlist,
+    coercion::coerce_arguments,
+    completion::{CompletionProvider, complete},
+    context::{ProgressNotifier, ToolContext, TransportKind},
+    cors::CorsOrigins,
+    env_expansion::expand_env,
+    field_limit::{FieldLimits, OversizedField},
+    http_mode::HttpMode,
+    http_server::{HttpLayers, start_http_server},
+    idempotency::{IdempotencyCache, IdempotentCall, idempotency_key},
+    locale::{client_locale, negotiate_locale},
+    markdown_check::find_markdown_issues,
+    rate_limit::{RateLimit, RateLimiter},
+    result_limit::{OversizedResult, limit_result},
+    server_config::{ActixOptionsHook, FallbackTool, InitHook, ServerConfig},
+    session::SessionStates,
+    tool::ToolError,
+    tool_box::{
+        ToolBox, ToolIndex, ToolSort, find_duplicate_tool_name, find_invalid_tools,
+        find_nonconforming_tool_names,
     },
+    wire_trace::WireTrace,
 };
 
-use crate::{server_config::ServerConfig, tool_box::ToolBox};
+/// The error code sent in the `_meta` field of the result of a tool call that exceeded the
+/// duration set with [`ServerBuilder::with_max_call_duration`], under the `error_code` key.
+///
+/// The code comes from the range -32000 to -32099 that JSON-RPC reserves for server errors,
+/// and matches the request timeout code of the MCP TypeScript SDK.
+pub const TOOL_TIMEOUT_ERROR_CODE: i64 = -32001;
 
 #[derive(Debug, Clone, Default)]
 pub struct ServerBuilder {
@@ -30,11 +78,24 @@ impl ServerBuilder {
         self
     }
 
+    /// Returns a copy of this builder with a different name, leaving this builder unchanged.
+    ///
+    /// This is convenient to start multiple servers that share the rest of their configuration.
+    pub fn rename(&self, name: impl Into<String>) -> Self {
+        self.clone().with_name(name)
+    }
+
     pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
         self.config.instructions = instructions.into();
         self
     }
 
+    /// Adds a fragment at the end of the instructions, on a new line.
+    pub fn with_instructions_append(mut self, instructions: impl AsRef<str>) -> Self {
+        self.append_instructions(instructions);
+        self
+    }
+
     pub fn with_version(mut self, version: impl Into<String>) -> Self {
         self.config.version = version.into();
         self
@@ -50,6 +111,503 @@ impl ServerBuilder {
         self
     }
 
+    /// When enabled, a panic raised while a tool is called is caught and reported to the
+    /// client as a tool error instead of unwinding through the server. Disabled by default.
+    pub fn with_catch_panics(mut self, catch_panics: bool) -> Self {
+        self.config.catch_panics = catch_panics;
+        self
+    }
+
+    /// Sets the host used by the HTTP server when no host is provided. Defaults to `127.0.0.1`.
+    pub fn with_default_host(mut self, host: impl Into<String>) -> Self {
+        self.config.default_host = host.into();
+        self
+    }
+
+    /// Sets the port used by the HTTP server when no port is provided. Defaults to `8080`.
+    pub fn with_default_port(mut self, port: u16) -> Self {
+        self.config.default_port = port;
+        self
+    }
+
+    /// When enabled, the client information and capabilities received during the
+    /// initialization handshake are logged at the `info` level with [`tracing`](https://docs.rs/tracing).
+    /// Disabled by default.
+    pub fn with_log_initialize(mut self, log_initialize: bool) -> Self {
+        self.config.log_initialize = log_initialize;
+        self
+    }
+
+    /// Prepends the given prefix to the name of every tool listed by the server. Calls must
+    /// use the prefixed names, which are stripped before the tool is resolved. Empty by default.
+    pub fn with_tool_prefix(mut self, tool_prefix: impl Into<String>) -> Self {
+        self.config.tool_prefix = tool_prefix.into();
+        self
+    }
+
+    /// Limits the size of the content returned by tools, in bytes. Text is measured by its
+    /// UTF-8 length, images or audio by their encoded data and structured content by its JSON
+    /// serialization. Unlimited by default.
+    pub fn with_max_result_bytes(mut self, max_result_bytes: usize) -> Self {
+        self.config.max_result_bytes = Some(max_result_bytes);
+        self
+    }
+
+    /// Sets how results exceeding the limit from [`with_max_result_bytes`](Self::with_max_result_bytes)
+    /// are handled. Defaults to [`OversizedResult::Reject`].
+    pub fn with_oversized_result(mut self, oversized_result: OversizedResult) -> Self {
+        self.config.oversized_result = oversized_result;
+        self
+    }
+
+    /// When enabled, the command-line interface refuses to bind the HTTP server to all network
+    /// interfaces (like `0.0.0.0`) unless `--allow-external` is passed. Disabled by default.
+    pub fn with_require_external_optin(mut self, require_external_optin: bool) -> Self {
+        self.config.require_external_optin = require_external_optin;
+        self
+    }
+
+    /// Registers an asynchronous function that runs once the transport is set up, before the
+    /// server accepts requests. If it fails, the server does not start and the error is returned.
+    pub fn with_init<F, Fut, E>(mut self, init: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.set_init(init);
+        self
+    }
+
+    /// When enabled, the HTTP server compresses responses of at least 1 KiB with gzip or
+    /// deflate, depending on the `Accept-Encoding` header of the client. Event streams are
+    /// sent uncompressed, since compressing them would delay the events until enough data is
+    /// buffered. Disabled by default.
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.config.compression = compression;
+        self
+    }
+
+    /// Sets the maximum number of pending connections of the HTTP server socket. Defaults to the
+    /// value of the operating system.
+    pub fn with_tcp_backlog(mut self, tcp_backlog: u32) -> Self {
+        self.set_tcp_backlog(tcp_backlog);
+        self
+    }
+
+    /// Sets the `TCP_NODELAY` option of the connections accepted by the HTTP server, to send
+    /// small responses without waiting for more data. Defaults to the value of the operating
+    /// system.
+    pub fn with_tcp_nodelay(mut self, tcp_nodelay: bool) -> Self {
+        self.set_tcp_nodelay(tcp_nodelay);
+        self
+    }
+
+    /// Sets how long the HTTP server waits for the in-flight requests to complete when it stops
+    /// after receiving a `SIGINT` or `SIGTERM` signal. Requests still running after this
+    /// duration, rounded up to the second, are abandoned. Defaults to 30 seconds.
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.set_shutdown_timeout(shutdown_timeout);
+        self
+    }
+
+    /// Registers an asynchronous function that handles calls to unknown tool names, instead of
+    /// returning an `Unknown tool` error. It receives the parameters of the call as sent by
+    /// the client, including the tool prefix if any.
+    ///
+    /// Only unknown names are routed to the fallback: a call to an existing tool with invalid
+    /// arguments still fails with an error describing the invalid arguments.
+    pub fn with_fallback_tool<F, Fut>(mut self, fallback_tool: F) -> Self
+    where
+        F: Fn(CallToolRequestParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<CallToolResult, CallToolError>> + Send + 'static,
+    {
+        self.set_fallback_tool(fallback_tool);
+        self
+    }
+
+    /// Sets the value of the `field` argument of the tool named `tool` when a call omits it,
+    /// before the arguments are parsed. This is useful for optional arguments whose default is
+    /// configured by the server, like a model name. Arguments sent by the client are kept.
+    /// Defaults also apply to the calls made through an alias of the tool.
+    pub fn with_argument_default(
+        mut self,
+        tool: impl Into<String>,
+        field: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.set_argument_default(tool, field, value);
+        self
+    }
+
+    /// Replaces the description of the tool named `name` in the tools listed to clients and in
+    /// the help output, for example to localize descriptions without recompiling. Calls to
+    /// the tool are not affected. Overrides for unknown tool names are logged as warnings when
+    /// the server starts.
+    pub fn with_tool_description_override(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.set_tool_description_override(name, description);
+        self
+    }
+
+    /// Sets the descriptions of the tools for the given locale, like `fr` or `pt-BR`, as a map
+    /// from tool names to descriptions. Tools are listed with the descriptions of the locale
+    /// that best matches the `locale` field of the `_meta` object the client sends with its
+    /// `initialize` request, written like an `Accept-Language` header, or else the locale set
+    /// with [`with_locale`](Self::with_locale). Tools without a description in that locale
+    /// keep their default description.
+    pub fn with_tool_descriptions(
+        mut self,
+        locale: impl Into<String>,
+        descriptions: HashMap<String, String>,
+    ) -> Self {
+        self.set_tool_descriptions(locale, descriptions);
+        self
+    }
+
+    /// Sets the locale of the tool descriptions listed to clients that don't send a locale
+    /// preference, see [`with_tool_descriptions`](Self::with_tool_descriptions). No locale by
+    /// default, which lists the default descriptions.
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.set_locale(locale);
+        self
+    }
+
+    /// Limits the calls to the tool named `name` to `max_per_interval` calls per `interval`.
+    /// Calls are allowed in bursts as long as the average rate stays within the limit. When
+    /// the limit is exceeded, calls fail with an error indicating when to retry. Tools are
+    /// not limited by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_per_interval` is zero or if `interval` is zero.
+    pub fn with_tool_rate_limit(
+        mut self,
+        name: impl Into<String>,
+        max_per_interval: u32,
+        interval: Duration,
+    ) -> Self {
+        self.set_tool_rate_limit(name, max_per_interval, interval);
+        self
+    }
+
+    /// Limits how long a single tool call can run, independently of the transport timeout set
+    /// with [`with_timeout`](Self::with_timeout). Calls running longer are logged as warnings
+    /// and fail with an error result naming the tool and the limit, with the
+    /// [`TOOL_TIMEOUT_ERROR_CODE`] code. Unlimited by default.
+    ///
+    /// The limit only applies to asynchronous tools, the fallback tool and the tools declared
+    /// with the `blocking_structured` kind of [`setup_tools!`](crate::server_prelude::setup_tools).
+    /// Other synchronous tools run to completion on the thread of the async runtime and can't be
+    /// interrupted: move their work to [`tokio::task::spawn_blocking`] in an asynchronous tool
+    /// to limit it. The error of a blocking tool that exceeds the limit is sent right away, but
+    /// its thread keeps running the tool until it returns.
+    pub fn with_max_call_duration(mut self, max_call_duration: Duration) -> Self {
+        self.config.max_call_duration = Some(max_call_duration);
+        self
+    }
+
+    /// When enabled, the stdio server stops without an error when the client closes the
+    /// pipe, since it is the usual way for clients to disconnect. Enabled by default.
+    pub fn with_treat_pipe_close_as_clean(mut self, treat_pipe_close_as_clean: bool) -> Self {
+        self.config.treat_pipe_close_as_clean = treat_pipe_close_as_clean;
+        self
+    }
+
+    /// When enabled, the duration of each tool call is added to the `_meta` field of its result,
+    /// as a number of milliseconds under the `duration_ms` key. Disabled by default.
+    pub fn with_timing_meta(mut self, timing_meta: bool) -> Self {
+        self.config.timing_meta = timing_meta;
+        self
+    }
+
+    /// Selects the transports served by the HTTP server. Defaults to [`HttpMode::Sse`], which
+    /// also serves the legacy server-sent events endpoints.
+    pub fn with_http_mode(mut self, http_mode: HttpMode) -> Self {
+        self.config.http_mode = http_mode;
+        self
+    }
+
+    /// Limits the length of the server name and title to `max_chars` characters, since long
+    /// names are not displayed well by some clients. The limit is checked when the server
+    /// starts, and `oversized` determines if longer fields are truncated or prevent the server
+    /// from starting. Unlimited by default.
+    pub fn with_field_limits(mut self, max_chars: usize, oversized: OversizedField) -> Self {
+        self.set_field_limits(max_chars, oversized);
+        self
+    }
+
+    /// When enabled, tool calls are matched to tools without considering the case of the tool
+    /// name, so a call to `SUM` reaches the `sum` tool. Tools are still listed with their
+    /// declared names, and the server fails to start when two tools differ only by their
+    /// case. Disabled by default.
+    pub fn with_case_insensitive_tools(mut self, case_insensitive_tools: bool) -> Self {
+        self.config.case_insensitive_tools = case_insensitive_tools;
+        self
+    }
+
+    /// When enabled, string arguments holding a number or a boolean are converted to the type
+    /// declared by the input schema of the tool before the tool is parsed, so a call to `sum`
+    /// with `["1.5", "2"]` is accepted from clients that stringify their arguments. Disabled
+    /// by default.
+    pub fn with_lenient_argument_coercion(mut self, lenient_argument_coercion: bool) -> Self {
+        self.config.lenient_argument_coercion = lenient_argument_coercion;
+        self
+    }
+
+    /// When enabled, the server advertises no tools capability, lists no tools and rejects
+    /// every tool call, whatever tools it was built with, to isolate transport issues while
+    /// diagnosing a client. Disabled by default.
+    pub fn with_tools_disabled(mut self, tools_disabled: bool) -> Self {
+        self.config.tools_disabled = tools_disabled;
+        self
+    }
+
+    /// Sets the interval of the keep-alive pings sent by the HTTP server on open event streams,
+    /// which prevents proxies from closing idle connections. Defaults to the interval of
+    /// `rust-mcp-actix` (12 seconds).
+    pub fn with_sse_keepalive(mut self, interval: Duration) -> Self {
+        self.config.sse_keepalive = Some(interval);
+        self
+    }
+
+    /// Sets how many incoming messages are buffered for each session of the HTTP server before
+    /// they are handled. When the buffer is full, the server stops reading the messages of the
+    /// session until the handler catches up: messages are delayed but never dropped, and
+    /// clients sending bursts of messages see their requests take longer. Defaults to the
+    /// capacity of `rust-mcp-sdk` (36 messages).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn with_sse_buffer_size(mut self, size: usize) -> Self {
+        self.set_sse_buffer_size(size);
+        self
+    }
+
+    /// Registers a function that customizes the options of the HTTP server from
+    /// `rust-mcp-actix`, to set the fields that the builder does not expose. It runs when the
+    /// server starts, after the builder filled the options, so it also overrides the settings
+    /// of the builder, including the host and port to listen on.
+    pub fn with_actix_options<F>(mut self, customize: F) -> Self
+    where
+        F: Fn(&mut ActixServerOptions) + Send + Sync + 'static,
+    {
+        self.set_actix_options(customize);
+        self
+    }
+
+    /// Writes one access log line for each request received by the HTTP server, with its
+    /// timestamp, remote address, method, path, status and duration. Lines are logged at the
+    /// `info` level with [`tracing`](https://docs.rs/tracing), under the
+    /// `mcp_utils::access_log` target. Disabled by default.
+    pub fn with_access_log(mut self, format: AccessLogFormat) -> Self {
+        self.config.access_log = Some(format);
+        self
+    }
+
+    /// Answers the completion requests of clients with the values suggested by the given
+    /// provider, and advertises the `completions` capability. Disabled by default.
+    pub fn with_completions<C: CompletionProvider>(mut self) -> Self {
+        self.set_completions::<C>();
+        self
+    }
+
+    /// Calls the tool with the given name once with empty arguments, after the initialization
+    /// function and before the server accepts requests. This triggers the lazy initialization
+    /// of expensive tools, like loading a model. The name is the one declared by the tool,
+    /// without the tool prefix. Disabled by default.
+    ///
+    /// The duration of the call is logged at the `info` level with
+    /// [`tracing`](https://docs.rs/tracing). A failed call is logged as a warning, unless
+    /// [`with_warmup_strict`](Self::with_warmup_strict) is enabled.
+    pub fn with_warmup(mut self, tool_name: impl Into<String>) -> Self {
+        self.set_warmup(tool_name);
+        self
+    }
+
+    /// When enabled, the server does not start if the warmup call set with
+    /// [`with_warmup`](Self::with_warmup) fails. Disabled by default.
+    pub fn with_warmup_strict(mut self, warmup_strict: bool) -> Self {
+        self.config.warmup_strict = warmup_strict;
+        self
+    }
+
+    /// Sets the order in which tools are listed to clients and in the help output of the
+    /// command-line interface. Defaults to [`ToolSort::Declaration`].
+    pub fn with_tool_sort(mut self, tool_sort: ToolSort) -> Self {
+        self.config.tool_sort = tool_sort;
+        self
+    }
+
+    /// When enabled, the `${VAR}` references in the title and the instructions are replaced by
+    /// the value of the environment variable when the server starts. A reference can provide a
+    /// default value with `${VAR:-default}`; a variable that is not set and has no default
+    /// expands to an empty string, and a warning is logged with
+    /// [`tracing`](https://docs.rs/tracing). Disabled by default.
+    pub fn with_env_expansion(mut self, env_expansion: bool) -> Self {
+        self.config.env_expansion = env_expansion;
+        self
+    }
+
+    /// When enabled, the results of structured tools also contain their value as pretty-printed
+    /// JSON in a text block, for the clients that ignore the structured content. When disabled,
+    /// the value is only sent as structured content. Enabled by default.
+    pub fn with_structured_text_fallback(mut self, structured_text_fallback: bool) -> Self {
+        self.config.structured_text_fallback = structured_text_fallback;
+        self
+    }
+
+    /// When enabled, the instructions are checked for obvious markdown mistakes when the server
+    /// starts, like code fences that are never closed, and a warning is logged with
+    /// [`tracing`](https://docs.rs/tracing) for each of them. The server starts either way.
+    /// Disabled by default.
+    pub fn with_validate_instructions(mut self, validate_instructions: bool) -> Self {
+        self.config.validate_instructions = validate_instructions;
+        self
+    }
+
+    /// When enabled, the name sent to clients in the `initialize` result is cleaned up for the
+    /// clients that display or log it verbatim: control characters (like newlines, tabs and
+    /// escape sequences) are removed, then leading and trailing whitespace is trimmed. Other
+    /// characters are kept. Disabled by default.
+    pub fn with_sanitize_name(mut self, sanitize_name: bool) -> Self {
+        self.config.sanitize_name = sanitize_name;
+        self
+    }
+
+    /// When enabled, tools with an input schema that is not a valid JSON Schema are skipped with
+    /// a warning logged with [`tracing`](https://docs.rs/tracing): they are not listed and
+    /// calls to them fail like calls to unknown tools. Otherwise, the server does not start
+    /// when a tool has an invalid input schema. Disabled by default.
+    pub fn with_skip_invalid_tools(mut self, skip_invalid_tools: bool) -> Self {
+        self.config.skip_invalid_tools = skip_invalid_tools;
+        self
+    }
+
+    /// The names of the listed tools, with the tool prefix, are checked against the pattern
+    /// `^[a-zA-Z0-9_-]+$` when the server starts, since some clients reject tools with spaces,
+    /// dots or other characters in their name. When enabled, the server does not start if a
+    /// name does not match. Otherwise, a warning is logged with
+    /// [`tracing`](https://docs.rs/tracing) for each of them. Disabled by default.
+    pub fn with_enforce_tool_name_rules(mut self, enforce_tool_name_rules: bool) -> Self {
+        self.config.enforce_tool_name_rules = enforce_tool_name_rules;
+        self
+    }
+
+    /// When enabled, the aliases declared in `setup_tools!` are listed to clients as tools
+    /// of their own, next to the tools they refer to. Disabled by default: aliases can be
+    /// called but are not listed.
+    pub fn with_list_aliases(mut self, list_aliases: bool) -> Self {
+        self.config.list_aliases = list_aliases;
+        self
+    }
+
+    /// Lets browser clients served from the given origins call the HTTP server, by answering
+    /// CORS preflight requests and adding the `Access-Control-Allow-Origin` header to the
+    /// responses. The `*` origin allows every origin. Disabled by default.
+    pub fn with_cors<I, S>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.set_cors(origins);
+        self
+    }
+
+    /// Only accepts HTTP requests from clients whose IP address belongs to one of the given
+    /// networks, like `10.0.0.0/8`, or addresses. Other clients receive a `403 Forbidden`
+    /// response before their request reaches the MCP endpoints. An empty allowlist allows all
+    /// clients, which is the default.
+    ///
+    /// ```rust
+    /// use mcp_utils::server_prelude::*;
+    /// use std::net::{IpAddr, Ipv4Addr};
+    ///
+    /// let builder = ServerBuilder::new().with_client_allowlist([
+    ///     "10.0.0.0/8".parse::<IpNet>().unwrap(),
+    ///     IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)).into(),
+    /// ]);
+    /// ```
+    pub fn with_client_allowlist<I, N>(mut self, networks: I) -> Self
+    where
+        I: IntoIterator<Item = N>,
+        N: Into<IpNet>,
+    {
+        self.set_client_allowlist(networks);
+        self
+    }
+
+    /// Appends one JSON line to the file at the given path for each tool call, with its
+    /// `timestamp`, `tool` name, `arguments`, `success` and `error` message. Lines are written
+    /// by a background thread. The server does not start if the file cannot be opened.
+    /// Disabled by default.
+    pub fn with_audit_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.set_audit_log(path);
+        self
+    }
+
+    /// Replaces the value of the arguments with the given field names (at any depth) in the
+    /// lines written to the audit log set with [`with_audit_log`](Self::with_audit_log).
+    pub fn with_audit_log_redaction<I, S>(mut self, field_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.set_audit_log_redaction(field_names);
+        self
+    }
+
+    /// Sets how long the [`SessionState`](crate::tool_prelude::SessionState) of an HTTP
+    /// session is kept after its last tool call. 30 minutes by default.
+    pub fn with_session_ttl(mut self, session_ttl: Duration) -> Self {
+        self.set_session_ttl(session_ttl);
+        self
+    }
+
+    /// Replays the result of a tool call when a client sends the same call again, for
+    /// at-most-once semantics over unreliable transports. Calls opt in by sending an
+    /// idempotency key in the `idempotencyKey` field of their `_meta` object: a later call to
+    /// the same tool with the same key receives the cached result for `ttl` instead of running
+    /// the tool again. Keys are scoped to the HTTP session of the client, and a key sent again
+    /// with other arguments is rejected with an error instead of replaying the result.
+    ///
+    /// Only the tools annotated with `idempotent_hint = true` are cached, hidden or not and
+    /// whether they are called by name or through an alias. Failed calls are never cached.
+    /// Results are cached once their call completes, so a retry sent while the first call is
+    /// still running runs the tool again. Disabled by default.
+    pub fn with_idempotency_cache(mut self, ttl: Duration) -> Self {
+        self.set_idempotency_cache(ttl);
+        self
+    }
+
+    /// Keeps the recent messages sent to each HTTP session, so that clients reconnecting with
+    /// the `Last-Event-ID` header receive the messages they missed. Disabled by default.
+    pub fn with_session_resumption(mut self, session_resumption: bool) -> Self {
+        self.set_session_resumption(session_resumption);
+        self
+    }
+
+    /// Serves an HTML page at the root of the HTTP server, with the name, title, version and
+    /// instructions of the server and the list of its tools. The page is not served when the
+    /// HTTP server requires authentication. Disabled by default.
+    pub fn with_landing_page(mut self, landing_page: bool) -> Self {
+        self.set_landing_page(landing_page);
+        self
+    }
+
+    /// Writes every JSON-RPC message received and sent over the stdio and HTTP transports to
+    /// the writer, one JSON line per message, for protocol debugging. The values of fields
+    /// named like secrets (`password`, `token`, `api_key`, ...) are redacted. Disabled by
+    /// default.
+    pub fn with_wire_trace(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.set_wire_trace(writer);
+        self
+    }
+
     pub fn set_name(&mut self, name: impl Into<String>) {
         self.config.name = name.into();
     }
@@ -58,6 +616,13 @@ impl ServerBuilder {
         self.config.instructions = instructions.into();
     }
 
+    pub fn append_instructions(&mut self, instructions: impl AsRef<str>) {
+        if !self.config.instructions.is_empty() {
+            self.config.instructions.push('\n');
+        }
+        self.config.instructions.push_str(instructions.as_ref());
+    }
+
     pub fn set_version(&mut self, version: impl Into<String>) {
         self.config.version = version.into();
     }
@@ -70,138 +635,3042 @@ impl ServerBuilder {
         self.config.timeout = timeout;
     }
 
-    pub fn name(&self) -> &str {
-        &self.config.name
+    pub fn set_catch_panics(&mut self, catch_panics: bool) {
+        self.config.catch_panics = catch_panics;
     }
 
-    pub fn title(&self) -> &str {
-        &self.config.title
+    pub fn set_default_host(&mut self, host: impl Into<String>) {
+        self.config.default_host = host.into();
     }
 
-    pub fn version(&self) -> &str {
-        &self.config.version
+    pub fn set_default_port(&mut self, port: u16) {
+        self.config.default_port = port;
     }
 
-    pub fn instructions(&self) -> &str {
-        &self.config.instructions
+    pub fn set_log_initialize(&mut self, log_initialize: bool) {
+        self.config.log_initialize = log_initialize;
     }
 
-    pub async fn start_stdio<T>(self) -> Result<(), McpSdkError>
-    where
-        T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
-    {
-        let transport_options = TransportOptions {
-            timeout: self.config.timeout,
-            ..Default::default()
-        };
+    pub fn set_tool_prefix(&mut self, tool_prefix: impl Into<String>) {
+        self.config.tool_prefix = tool_prefix.into();
+    }
 
-        create_server(McpServerOptions {
-            server_details: self.get_server_details::<T>(),
-            transport: StdioTransport::new(transport_options)?,
-            handler: Handler::<T>::new().to_mcp_server_handler(),
-            task_store: None,
-            client_task_store: None,
-            message_observer: None,
-        })
-        .start()
-        .await
+    pub fn set_max_result_bytes(&mut self, max_result_bytes: usize) {
+        self.config.max_result_bytes = Some(max_result_bytes);
     }
 
-    pub async fn start_server<T>(
-        self,
-        host: impl Into<String>,
-        port: u16,
-    ) -> Result<(), McpSdkError>
+    pub fn set_oversized_result(&mut self, oversized_result: OversizedResult) {
+        self.config.oversized_result = oversized_result;
+    }
+
+    pub fn set_require_external_optin(&mut self, require_external_optin: bool) {
+        self.config.require_external_optin = require_external_optin;
+    }
+
+    pub fn set_init<F, Fut, E>(&mut self, init: F)
     where
-        T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
     {
-        let transport_options = TransportOptions {
-            timeout: self.config.timeout,
-            ..Default::default()
-        };
+        self.config.init = Some(InitHook(Arc::new(move || {
+            init().map(|result| result.map_err(Into::into)).boxed()
+        })));
+    }
 
-        create_actix_server(
-            self.get_server_details::<T>(),
-            Handler::<T>::new().to_mcp_server_handler(),
-            ActixServerOptions {
-                host: Some(host.into())
-                    .filter(|host| !host.is_empty())
-                    .unwrap_or_else(|| "127.0.0.1".to_string()),
-                port,
-                transport_options: Arc::new(transport_options),
-                ..Default::default()
-            },
-        )
-        .start()
-        .await
+    pub fn set_compression(&mut self, compression: bool) {
+        self.config.compression = compression;
     }
 
-    fn get_server_details<T>(self) -> InitializeResult
+    pub fn set_tcp_backlog(&mut self, tcp_backlog: u32) {
+        self.config.tcp_backlog = Some(tcp_backlog);
+    }
+
+    pub fn set_tcp_nodelay(&mut self, tcp_nodelay: bool) {
+        self.config.tcp_nodelay = Some(tcp_nodelay);
+    }
+
+    pub fn set_shutdown_timeout(&mut self, shutdown_timeout: Duration) {
+        self.config.shutdown_timeout = Some(shutdown_timeout);
+    }
+
+    pub fn set_fallback_tool<F, Fut>(&mut self, fallback_tool: F)
     where
-        T: ToolBox,
+        F: Fn(CallToolRequestParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<CallToolResult, CallToolError>> + Send + 'static,
     {
-        InitializeResult {
-            server_info: Implementation {
-                name: self.config.name,
-                version: self.config.version,
-                title: Some(self.config.title).filter(|title| !title.is_empty()),
-                description: Some(self.config.description)
-                    .filter(|description| !description.is_empty()),
-                website_url: None,
-                icons: Default::default(),
-            },
-            capabilities: ServerCapabilities {
-                tools: if T::get_tools().is_empty() {
-                    None
-                } else {
-                    Some(ServerCapabilitiesTools { list_changed: None })
-                },
-                ..Default::default()
-            },
-            meta: None,
-            instructions: Some(self.config.instructions),
-            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
-        }
+        self.config.fallback_tool = Some(FallbackTool(Arc::new(move |params| {
+            fallback_tool(params).boxed()
+        })));
     }
-}
 
-struct Handler<T> {
-    _phantom: std::marker::PhantomData<T>,
-}
+    pub fn set_argument_default(
+        &mut self,
+        tool: impl Into<String>,
+        field: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) {
+        self.config
+            .argument_defaults
+            .entry(tool.into())
+            .or_default()
+            .insert(field.into(), value.into());
+    }
 
-impl<T> Handler<T> {
-    pub fn new() -> Self {
-        Self {
-            _phantom: std::marker::PhantomData,
-        }
+    pub fn set_tool_description_override(
+        &mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) {
+        self.config
+            .tool_descriptions
+            .insert(name.into(), description.into());
     }
-}
 
-#[async_trait]
-#[allow(unused)]
-impl<T> ServerHandler for Handler<T>
-where
-    T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+    pub fn set_tool_descriptions(
+        &mut self,
+        locale: impl Into<String>,
+        descriptions: HashMap<String, String>,
+    ) {
+        self.config
+            .localized_tool_descriptions
+            .entry(locale.into())
+            .or_default()
+            .extend(descriptions);
+    }
+
+    pub fn set_locale(&mut self, locale: impl Into<String>) {
+        self.config.locale = Some(locale.into());
+    }
+
+    pub fn set_tool_rate_limit(
+        &mut self,
+        name: impl Into<String>,
+        max_per_interval: u32,
+        interval: Duration,
+    ) {
+        assert!(max_per_interval > 0, "the rate limit must allow calls");
+        assert!(
+            !interval.is_zero(),
+            "the rate limit interval must not be zero"
+        );
+
+        self.config.tool_rate_limits.insert(
+            name.into(),
+            RateLimit {
+                max_per_interval,
+                interval,
+            },
+        );
+    }
+
+    pub fn set_max_call_duration(&mut self, max_call_duration: Duration) {
+        self.config.max_call_duration = Some(max_call_duration);
+    }
+
+    pub fn set_treat_pipe_close_as_clean(&mut self, treat_pipe_close_as_clean: bool) {
+        self.config.treat_pipe_close_as_clean = treat_pipe_close_as_clean;
+    }
+
+    pub fn set_timing_meta(&mut self, timing_meta: bool) {
+        self.config.timing_meta = timing_meta;
+    }
+
+    pub fn set_http_mode(&mut self, http_mode: HttpMode) {
+        self.config.http_mode = http_mode;
+    }
+
+    pub fn set_field_limits(&mut self, max_chars: usize, oversized: OversizedField) {
+        self.config.field_limits = Some(FieldLimits {
+            max_chars,
+            oversized,
+        });
+    }
+
+    pub fn set_case_insensitive_tools(&mut self, case_insensitive_tools: bool) {
+        self.config.case_insensitive_tools = case_insensitive_tools;
+    }
+
+    pub fn set_lenient_argument_coercion(&mut self, lenient_argument_coercion: bool) {
+        self.config.lenient_argument_coercion = lenient_argument_coercion;
+    }
+
+    pub fn set_tools_disabled(&mut self, tools_disabled: bool) {
+        self.config.tools_disabled = tools_disabled;
+    }
+
+    pub fn set_sse_keepalive(&mut self, interval: Duration) {
+        self.config.sse_keepalive = Some(interval);
+    }
+
+    pub fn set_sse_buffer_size(&mut self, size: usize) {
+        assert!(size > 0, "the SSE buffer size must not be zero");
+        self.config.sse_buffer_size = Some(size);
+    }
+
+    pub fn set_actix_options<F>(&mut self, customize: F)
+    where
+        F: Fn(&mut ActixServerOptions) + Send + Sync + 'static,
+    {
+        self.config.actix_options = Some(ActixOptionsHook(Arc::new(customize)));
+    }
+
+    pub fn set_access_log(&mut self, format: AccessLogFormat) {
+        self.config.access_log = Some(format);
+    }
+
+    pub fn set_completions<C: CompletionProvider>(&mut self) {
+        self.config.completions = Some(C::complete);
+    }
+
+    pub fn set_warmup(&mut self, tool_name: impl Into<String>) {
+        self.config.warmup_tool = Some(tool_name.into());
+    }
+
+    pub fn set_warmup_strict(&mut self, warmup_strict: bool) {
+        self.config.warmup_strict = warmup_strict;
+    }
+
+    pub fn set_tool_sort(&mut self, tool_sort: ToolSort) {
+        self.config.tool_sort = tool_sort;
+    }
+
+    pub fn set_env_expansion(&mut self, env_expansion: bool) {
+        self.config.env_expansion = env_expansion;
+    }
+
+    pub fn set_skip_invalid_tools(&mut self, skip_invalid_tools: bool) {
+        self.config.skip_invalid_tools = skip_invalid_tools;
+    }
+
+    pub fn set_list_aliases(&mut self, list_aliases: bool) {
+        self.config.list_aliases = list_aliases;
+    }
+
+    pub fn set_audit_log(&mut self, path: impl Into<PathBuf>) {
+        self.config.audit_log = Some(path.into());
+    }
+
+    pub fn set_audit_log_redaction<I, S>(&mut self, field_names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.audit_log_redacted_fields = field_names.into_iter().map(Into::into).collect();
+    }
+
+    pub fn set_cors<I, S>(&mut self, origins: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.cors_origins = origins.into_iter().map(Into::into).collect();
+    }
+
+    pub fn set_client_allowlist<I, N>(&mut self, networks: I)
+    where
+        I: IntoIterator<Item = N>,
+        N: Into<IpNet>,
+    {
+        self.config.client_allowlist = networks.into_iter().map(Into::into).collect();
+    }
+
+    pub fn set_session_ttl(&mut self, session_ttl: Duration) {
+        self.config.session_ttl = session_ttl;
+    }
+
+    pub fn set_idempotency_cache(&mut self, ttl: Duration) {
+        self.config.idempotency_ttl = Some(ttl);
+    }
+
+    pub fn set_session_resumption(&mut self, session_resumption: bool) {
+        self.config.session_resumption = session_resumption;
+    }
+
+    pub fn set_landing_page(&mut self, landing_page: bool) {
+        self.config.landing_page = landing_page;
+    }
+
+    pub fn set_wire_trace(&mut self, writer: impl Write + Send + 'static) {
+        self.config.wire_trace = Some(WireTrace::new(writer));
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub fn title(&self) -> &str {
+        &self.config.title
+    }
+
+    /// Returns the title of the server, or its name when no title is set.
+    ///
+    /// This is meant for display purposes: the server still reports no title to
+    /// clients when the title is empty.
+    pub fn display_title(&self) -> &str {
+        if self.config.title.is_empty() {
+            &self.config.name
+        } else {
+            &self.config.title
+        }
+    }
+
+    pub fn version(&self) -> &str {
+        &self.config.version
+    }
+
+    pub fn instructions(&self) -> &str {
+        &self.config.instructions
+    }
+
+    pub fn default_host(&self) -> &str {
+        &self.config.default_host
+    }
+
+    pub fn default_port(&self) -> u16 {
+        self.config.default_port
+    }
+
+    pub fn require_external_optin(&self) -> bool {
+        self.config.require_external_optin
+    }
+
+    pub fn http_mode(&self) -> HttpMode {
+        self.config.http_mode
+    }
+
+    pub fn tool_sort(&self) -> ToolSort {
+        self.config.tool_sort
+    }
+
+    /// Returns the description set with
+    /// [`with_tool_description_override`](Self::with_tool_description_override) for the tool
+    /// named `name`, if any.
+    pub fn tool_description_override(&self, name: &str) -> Option<&str> {
+        self.config.tool_descriptions.get(name).map(String::as_str)
+    }
+
+    /// Returns the result of the `initialize` request as sent to clients, with the server
+    /// information, the capabilities and the instructions, without starting the server.
+    pub fn initialize_result<T>(&self) -> InitializeResult
+    where
+        T: ToolBox,
+    {
+        self.clone().get_server_details::<T>()
+    }
+
+    /// Returns the tools as listed to clients, with the configured prefix, sort order and
+    /// aliases, without starting the server.
+    pub fn list_tools<T>(&self) -> Vec<Tool>
+    where
+        T: ToolBox,
+    {
+        Handler::<T>::new(self.config.clone(), TransportKind::default()).list_tools()
+    }
+
+    /// Starts the server in the current process and returns a client connected to it, without
+    /// any transport. The initialization function set with [`with_init`](Self::with_init) runs
+    /// before the client is returned.
+    #[cfg(feature = "test-util")]
+    pub async fn start_in_memory<T>(mut self) -> Result<InMemoryClient<T>, McpSdkError>
+    where
+        T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+    {
+        check_tools::<T>(&self.config)?;
+        self.apply_field_limits()?;
+
+        self.run_init().await?;
+
+        let audit_log = self.open_audit_log()?;
+        let handler =
+            Handler::<T>::new(self.config, TransportKind::InMemory).with_audit_log(audit_log);
+        handler.warm_up().await?;
+
+        Ok(InMemoryClient::new(handler))
+    }
+
+    pub async fn start_stdio<T>(mut self) -> Result<(), McpSdkError>
+    where
+        T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+    {
+        check_tools::<T>(&self.config)?;
+        self.apply_field_limits()?;
+
+        let transport_options = TransportOptions {
+            timeout: self.config.timeout,
+            ..Default::default()
+        };
+
+        let handler = Handler::<T>::new(self.config.clone(), TransportKind::Stdio)
+            .with_audit_log(self.open_audit_log()?);
+        let transport = StdioTransport::new(transport_options)?;
+        let treat_pipe_close_as_clean = self.config.treat_pipe_close_as_clean;
+
+        self.run_init().await?;
+        handler.warm_up().await?;
+        let handler = handler.to_mcp_server_handler();
+
+        let message_observer = self.message_observer();
+        let result = create_server(McpServerOptions {
+            server_details: self.get_server_details::<T>(),
+            transport,
+            handler,
+            task_store: None,
+            client_task_store: None,
+            message_observer,
+        })
+        .start()
+        .await;
+
+        match result {
+            Err(error) if treat_pipe_close_as_clean && is_pipe_closed(&error) => Ok(()),
+            result => result,
+        }
+    }
+
+    pub async fn start_server<T>(
+        mut self,
+        host: impl Into<String>,
+        port: u16,
+    ) -> Result<(), McpSdkError>
+    where
+        T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+    {
+        check_tools::<T>(&self.config)?;
+        self.apply_field_limits()?;
+
+        let transport_options = self.http_transport_options();
+
+        let handler = Handler::<T>::new(self.config.clone(), TransportKind::Http)
+            .with_audit_log(self.open_audit_log()?);
+        let mut layers = self.http_layers();
+        layers.landing_page = self.config.landing_page.then(|| handler.list_tools());
+
+        self.run_init().await?;
+        handler.warm_up().await?;
+        let handler = handler.to_mcp_server_handler();
+
+        let options = self.http_options(host.into(), port, transport_options);
+        let server_details = self.get_server_details::<T>();
+
+        if !layers.is_empty() {
+            start_http_server(server_details, handler, options, layers).await
+        } else {
+            create_actix_server(server_details, handler, options)
+                .start()
+                .await
+        }
+    }
+
+    fn http_layers(&self) -> HttpLayers {
+        HttpLayers {
+            compression: self.config.compression,
+            access_log: self.config.access_log,
+            cors: Some(CorsOrigins(self.config.cors_origins.clone()))
+                .filter(|origins| !origins.0.is_empty()),
+            client_allowlist: Some(ClientAllowlist(self.config.client_allowlist.clone()))
+                .filter(|allowlist| !allowlist.0.is_empty()),
+            landing_page: None,
+            tcp_backlog: self.config.tcp_backlog,
+            tcp_nodelay: self.config.tcp_nodelay,
+            shutdown_timeout: self.config.shutdown_timeout,
+        }
+    }
+
+    fn http_transport_options(&self) -> TransportOptions {
+        let mut transport_options = TransportOptions {
+            timeout: self.config.timeout,
+            ..Default::default()
+        };
+        if let Some(sse_buffer_size) = self.config.sse_buffer_size {
+            transport_options.channel_capacity = sse_buffer_size;
+        }
+        transport_options
+    }
+
+    fn http_options(
+        &self,
+        host: String,
+        port: u16,
+        transport_options: TransportOptions,
+    ) -> ActixServerOptions {
+        let mut options = ActixServerOptions {
+            host: Some(host)
+                .filter(|host| !host.is_empty())
+                .unwrap_or_else(|| self.config.default_host.clone()),
+            port,
+            transport_options: Arc::new(transport_options),
+            ..Default::default()
+        };
+        if let Some(sse_keepalive) = self.config.sse_keepalive {
+            options.ping_interval = sse_keepalive;
+        }
+        options.message_observer = self.message_observer();
+        if self.config.session_resumption {
+            options.event_store = Some(Arc::new(InMemoryEventStore::default()));
+        }
+        self.config.http_mode.apply(&mut options);
+        if let Some(ActixOptionsHook(customize)) = &self.config.actix_options {
+            customize(&mut options);
+        }
+        options
+    }
+
+    fn message_observer(&self) -> Option<Arc<dyn McpObserver<ClientMessage, ServerMessage>>> {
+        self.config
+            .wire_trace
+            .clone()
+            .map(|wire_trace| Arc::new(wire_trace) as Arc<dyn McpObserver<_, _>>)
+    }
+
+    fn apply_field_limits(&mut self) -> Result<(), McpSdkError> {
+        if let Some(field_limits) = self.config.field_limits {
+            field_limits
+                .apply("name", &mut self.config.name)
+                .and_then(|()| field_limits.apply("title", &mut self.config.title))
+                .map_err(|description| McpSdkError::Internal { description })?;
+        }
+
+        Ok(())
+    }
+
+    fn open_audit_log(&self) -> Result<Option<AuditLog>, McpSdkError> {
+        self.config
+            .audit_log
+            .as_deref()
+            .map(|path| {
+                AuditLog::open(path, &self.config.audit_log_redacted_fields).map_err(|err| {
+                    McpSdkError::Internal {
+                        description: format!(
+                            "unable to open the audit log `{}`: {}",
+                            path.display(),
+                            err
+                        ),
+                    }
+                })
+            })
+            .transpose()
+    }
+
+    async fn run_init(&self) -> Result<(), McpSdkError> {
+        run_init(self.config.init.as_ref()).await
+    }
+
+    fn get_server_details<T>(self) -> InitializeResult
+    where
+        T: ToolBox,
+    {
+        let env_expansion = self.config.env_expansion;
+        let env_lookup = self.config.env_lookup.clone();
+        let expand = |text: String| {
+            if env_expansion {
+                expand_env(&text, |name| (env_lookup.0)(name))
+            } else {
+                text
+            }
+        };
+
+        let instructions = expand(self.config.instructions);
+        if self.config.validate_instructions {
+            for issue in find_markdown_issues(&instructions) {
+                tracing::warn!(%issue, "instructions contain malformed markdown");
+            }
+        }
+
+        if !self.config.tool_descriptions.is_empty() {
+            let tools = T::get_tools();
+            for name in self.config.tool_descriptions.keys() {
+                if !tools.iter().any(|tool| &tool.name == name) {
+                    tracing::warn!(tool = %name, "ignoring the description override of an unknown tool");
+                }
+            }
+        }
+
+        if !self.config.localized_tool_descriptions.is_empty() {
+            let tools = T::get_tools();
+            for (locale, descriptions) in &self.config.localized_tool_descriptions {
+                for name in descriptions.keys() {
+                    if !tools.iter().any(|tool| &tool.name == name) {
+                        tracing::warn!(tool = %name, %locale, "ignoring the localized description of an unknown tool");
+                    }
+                }
+            }
+        }
+
+        let name = if self.config.sanitize_name {
+            sanitize_name(&self.config.name)
+        } else {
+            self.config.name
+        };
+
+        InitializeResult {
+            server_info: Implementation {
+                name,
+                version: self.config.version,
+                title: Some(expand(self.config.title)).filter(|title| !title.is_empty()),
+                description: Some(self.config.description)
+                    .filter(|description| !description.is_empty()),
+                website_url: None,
+                icons: Default::default(),
+            },
+            capabilities: ServerCapabilities {
+                tools: if self.config.tools_disabled
+                    || T::tool_count() == 0 && T::get_hidden_tools().is_empty()
+                {
+                    None
+                } else {
+                    Some(ServerCapabilitiesTools { list_changed: None })
+                },
+                completions: self.config.completions.map(|_| Default::default()),
+                ..Default::default()
+            },
+            meta: None,
+            instructions: Some(instructions),
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+        }
+    }
+}
+
+async fn run_init(init: Option<&InitHook>) -> Result<(), McpSdkError> {
+    match init {
+        Some(InitHook(init)) => init().await.map_err(|err| McpSdkError::Internal {
+            description: format!("initialization failed: {}", err),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Returns true when the error comes from the other end of a pipe being closed.
+fn is_pipe_closed(error: &McpSdkError) -> bool {
+    let io_error = match error {
+        McpSdkError::Io(io_error) | McpSdkError::Transport(TransportError::Io(io_error)) => {
+            io_error
+        }
+        _ => return false,
+    };
+
+    matches!(
+        io_error.kind(),
+        io::ErrorKind::BrokenPipe | io::ErrorKind::UnexpectedEof
+    )
+}
+
+fn check_tools<T>(config: &ServerConfig) -> Result<(), McpSdkError>
+where
+    T: ToolBox,
+{
+    match find_duplicate_tool_name::<T>(config.case_insensitive_tools) {
+        Some(name) if config.case_insensitive_tools => {
+            return Err(McpSdkError::Internal {
+                description: format!("multiple tools are named `{}` when ignoring case", name),
+            });
+        }
+        Some(name) => {
+            return Err(McpSdkError::Internal {
+                description: format!("multiple tools are named `{}`", name),
+            });
+        }
+        None => {}
+    }
+
+    for (name, reason) in find_invalid_tools::<T>() {
+        if !config.skip_invalid_tools {
+            return Err(McpSdkError::Internal {
+                description: format!("tool `{}` has an invalid input schema: {}", name, reason),
+            });
+        }
+
+        tracing::warn!(tool = %name, reason = %reason, "skipping tool with an invalid input schema");
+    }
+
+    for name in find_nonconforming_tool_names::<T>(&config.tool_prefix) {
+        if config.enforce_tool_name_rules {
+            return Err(McpSdkError::Internal {
+                description: format!("tool name `{}` does not match `^[a-zA-Z0-9_-]+$`", name),
+            });
+        }
+
+        tracing::warn!(tool = %name, "tool name does not match `^[a-zA-Z0-9_-]+$`, some clients may reject it");
+    }
+
+    Ok(())
+}
+
+pub(crate) struct Handler<T> {
+    config: ServerConfig,
+    transport_kind: TransportKind,
+    tools: ToolIndex,
+    skipped_tools: HashSet<String>,
+    audit_log: Option<AuditLog>,
+    rate_limiter: RateLimiter,
+    sessions: SessionStates,
+    idempotency_cache: Option<IdempotencyCache>,
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T: ToolBox> Handler<T> {
+    pub fn new(config: ServerConfig, transport_kind: TransportKind) -> Self {
+        let skipped_tools = if config.skip_invalid_tools {
+            find_invalid_tools::<T>()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        Self {
+            rate_limiter: RateLimiter::new(&config.tool_rate_limits),
+            sessions: SessionStates::new(config.session_ttl),
+            idempotency_cache: config.idempotency_ttl.map(IdempotencyCache::new),
+            tools: ToolIndex::new::<T>(),
+            skipped_tools,
+            audit_log: None,
+            config,
+            transport_kind,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    pub(crate) fn with_audit_log(mut self, audit_log: Option<AuditLog>) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    pub(crate) fn list_tools(&self) -> Vec<Tool> {
+        self.list_localized_tools(None)
+    }
+
+    /// Lists the tools with their descriptions in the locale that best matches the preference
+    /// of the client, or the locale of the server when the client has no preference.
+    pub(crate) fn list_localized_tools(&self, client_locale: Option<&str>) -> Vec<Tool> {
+        if self.config.tools_disabled {
+            return Vec::new();
+        }
+
+        let mut tools = T::get_tools();
+        tools.retain(|tool| !self.skipped_tools.contains(&tool.name));
+
+        let localized = self.localized_tool_descriptions(client_locale);
+        for tool in tools.iter_mut() {
+            if let Some(description) = localized
+                .and_then(|descriptions| descriptions.get(&tool.name))
+                .or_else(|| self.config.tool_descriptions.get(&tool.name))
+            {
+                tool.description = Some(description.clone());
+            }
+        }
+
+        if self.config.list_aliases {
+            for (alias, name) in T::get_aliases() {
+                if let Some(tool) = tools.iter().find(|tool| tool.name == name) {
+                    tools.push(Tool {
+                        name: alias,
+                        ..tool.clone()
+                    });
+                }
+            }
+        }
+
+        self.config.tool_sort.sort(&mut tools);
+
+        if !self.config.tool_prefix.is_empty() {
+            for tool in tools.iter_mut() {
+                tool.name = format!("{}{}", self.config.tool_prefix, tool.name);
+            }
+        }
+
+        tools
+    }
+
+    /// Returns the tool descriptions of the locale negotiated with the preference of the
+    /// client, or with the locale of the server.
+    fn localized_tool_descriptions(
+        &self,
+        client_locale: Option<&str>,
+    ) -> Option<&HashMap<String, String>> {
+        let descriptions = &self.config.localized_tool_descriptions;
+        let negotiate = |preference: &str| {
+            negotiate_locale(preference, descriptions.keys().map(String::as_str))
+        };
+
+        let locale = client_locale
+            .and_then(negotiate)
+            .or_else(|| self.config.locale.as_deref().and_then(negotiate))?;
+
+        descriptions.get(locale)
+    }
+}
+
+impl<T> Handler<T>
+where
+    T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
+{
+    fn find_tool(&self, mut params: CallToolRequestParams) -> Result<T, CallToolError> {
+        if !self.config.tool_prefix.is_empty() {
+            match params.name.strip_prefix(&self.config.tool_prefix) {
+                Some(name) => params.name = name.to_owned(),
+                None => return Err(CallToolError::unknown_tool(params.name)),
+            }
+        }
+
+        params.name = self.canonical_tool_name(&params.name);
+
+        if self.skipped_tools.contains(&params.name) {
+            return Err(CallToolError::unknown_tool(params.name));
+        }
+
+        if let Some(defaults) = self.config.argument_defaults.get(&params.name) {
+            let arguments = params.arguments.get_or_insert_default();
+            for (field, value) in defaults {
+                arguments
+                    .entry(field.as_str())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+
+        if self.config.lenient_argument_coercion
+            && let Some(arguments) = params.arguments.as_mut()
+            && let Some(tool) = self.tools.get(&params.name)
+        {
+            coerce_arguments(arguments, &tool.input_schema);
+        }
+
+        T::try_from(params)
+    }
+
+    /// Returns the name of the tool called with `name`: the tool an alias refers to, or the
+    /// name of the tool as declared when tool names are matched without case sensitivity.
+    /// Other names are returned unchanged.
+    fn canonical_tool_name(&self, name: &str) -> String {
+        self.tools
+            .canonical_name(name, self.config.case_insensitive_tools)
+            .to_owned()
+    }
+
+    /// Returns whether the tool named `name`, listed or hidden, is annotated with
+    /// `idempotent_hint = true`.
+    fn is_idempotent(&self, name: &str) -> bool {
+        self.tools
+            .get(name)
+            .and_then(|tool| tool.annotations.as_ref())
+            .and_then(|annotations| annotations.idempotent_hint)
+            == Some(true)
+    }
+
+    /// Returns the context of a tool call received over the transport of this handler.
+    pub(crate) fn context(
+        &self,
+        client_capabilities: Option<ClientCapabilities>,
+        session_id: Option<SessionId>,
+    ) -> ToolContext {
+        let context = ToolContext::new(client_capabilities, self.transport_kind);
+
+        match session_id {
+            Some(session_id) => {
+                let state = self.sessions.get(&session_id);
+                context.with_session(session_id, state)
+            }
+            None => context,
+        }
+    }
+
+    pub(crate) fn complete(
+        &self,
+        params: &CompleteRequestParams,
+    ) -> Result<CompleteResult, RpcError> {
+        match self.config.completions {
+            Some(completion) => Ok(complete(completion, params)),
+            None => Err(RpcError::method_not_found().with_message(format!(
+                "No handler is implemented for '{}'.",
+                CompleteRequest::method_value()
+            ))),
+        }
+    }
+
+    /// Calls the warmup tool once with empty arguments, if any. A failed call is only logged,
+    /// unless the warmup is strict.
+    pub(crate) async fn warm_up(&self) -> Result<(), McpSdkError> {
+        let Some(tool_name) = &self.config.warmup_tool else {
+            return Ok(());
+        };
+
+        let params =
+            CallToolRequestParams::new(format!("{}{}", self.config.tool_prefix, tool_name));
+        let start = Instant::now();
+        let failure = match self.call_tool(params, self.context(None, None)).await {
+            Ok(result) if result.is_error != Some(true) => None,
+            Ok(result) => Some(
+                result
+                    .content
+                    .first()
+                    .and_then(|content| content.as_text_content().ok())
+                    .map(|content| content.text.clone())
+                    .unwrap_or_else(|| "the tool returned an error".to_owned()),
+            ),
+            Err(error) => Some(error.to_string()),
+        };
+        let duration_ms = start.elapsed().as_millis();
+
+        match failure {
+            None => {
+                tracing::info!(tool = %tool_name, duration_ms, "warmup completed");
+                Ok(())
+            }
+            Some(message) if self.config.warmup_strict => Err(McpSdkError::Internal {
+                description: format!("warmup of tool `{}` failed: {}", tool_name, message),
+            }),
+            Some(message) => {
+                tracing::warn!(tool = %tool_name, duration_ms, error = %message, "warmup failed");
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the fallback tool when the error comes from an unknown tool name.
+    fn fallback_tool(&self, error: CallToolError) -> Result<&FallbackTool, CallToolError> {
+        match &self.config.fallback_tool {
+            Some(fallback_tool) if error.0.is::<UnknownTool>() => Ok(fallback_tool),
+            _ => Err(error),
+        }
+    }
+
+    /// Adds the names of the listed tools to the errors coming from an unknown tool name, so
+    /// that clients can find the name they meant. Other errors are returned unchanged.
+    fn describe_unknown_tool(&self, error: CallToolError, requested_name: &str) -> CallToolError {
+        if !error.0.is::<UnknownTool>() {
+            return error;
+        }
+
+        let names: Vec<_> = self
+            .list_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+
+        if names.is_empty() {
+            CallToolError::unknown_tool(format!("{} (no tools are available)", requested_name))
+        } else {
+            CallToolError::unknown_tool(format!(
+                "{} (available tools: {})",
+                requested_name,
+                names.join(", ")
+            ))
+        }
+    }
+
+    pub(crate) async fn call_tool(
+        &self,
+        params: CallToolRequestParams,
+        context: ToolContext,
+    ) -> Result<CallToolResult, CallToolError> {
+        let Some(audit_log) = &self.audit_log else {
+            return self.run_tool(params, context).await;
+        };
+
+        let tool_name = params.name.clone();
+        let arguments = params.arguments.clone();
+        let result = self.run_tool(params, context).await;
+        audit_log.record(&tool_name, arguments, &result);
+
+        result
+    }
+
+    async fn run_tool(
+        &self,
+        params: CallToolRequestParams,
+        context: ToolContext,
+    ) -> Result<CallToolResult, CallToolError> {
+        if self.config.tools_disabled {
+            return Err(CallToolError::new(ToolError::from(format!(
+                "tools are disabled on this server, `{}` was not called",
+                params.name
+            ))));
+        }
+
+        let tool_name = self.canonical_tool_name(
+            params
+                .name
+                .strip_prefix(&self.config.tool_prefix)
+                .unwrap_or(&params.name),
+        );
+
+        let idempotency = self
+            .idempotency_cache
+            .as_ref()
+            .zip(idempotency_key(&params))
+            .filter(|_| self.is_idempotent(&tool_name))
+            .map(|(cache, key)| {
+                let call = IdempotentCall::new(context.session_id(), &tool_name, key);
+                (cache, call, params.arguments.clone())
+            });
+        if let Some((cache, call, arguments)) = &idempotency
+            && let Some(result) = cache
+                .get(call, arguments.as_ref())
+                .map_err(CallToolError::new)?
+        {
+            return Ok(result);
+        }
+
+        self.rate_limiter.check(&tool_name)?;
+
+        // the error is resolved before awaiting, since `CallToolError` is not `Send`
+        let found_tool = match self.find_tool(params.clone()) {
+            Ok(custom_tool) => Ok(custom_tool),
+            Err(error) => Err(self
+                .fallback_tool(error)
+                .map_err(|error| self.describe_unknown_tool(error, &params.name))?),
+        };
+
+        let interruptible = match &found_tool {
+            Ok(custom_tool) => custom_tool.get_tool().is_interruptible(),
+            Err(_) => true,
+        };
+
+        let progress_token = params
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.progress_token.clone());
+        let context = context.with_progress_token(progress_token);
+
+        let call = async {
+            match found_tool {
+                Ok(custom_tool) => self.call_custom_tool(custom_tool, context).await,
+                Err(FallbackTool(fallback_tool)) => context.scope(fallback_tool(params)).await,
+            }
+        };
+
+        let start = Instant::now();
+
+        let mut result = match self.config.max_call_duration.filter(|_| interruptible) {
+            Some(max_call_duration) => tokio::time::timeout(max_call_duration, call)
+                .await
+                .unwrap_or_else(|_| {
+                    let limit = humantime::format_duration(max_call_duration);
+                    tracing::warn!(tool = %tool_name, %limit, "tool call timed out");
+                    Err(CallToolError::new(
+                        ToolError::from(format!("tool `{}` exceeded {}", tool_name, limit))
+                            .with_code(TOOL_TIMEOUT_ERROR_CODE),
+                    ))
+                }),
+            None => call.await,
+        }?;
+
+        if !self.config.structured_text_fallback && result.structured_content.is_some() {
+            result.content.clear();
+        }
+
+        if self.config.timing_meta {
+            result.meta.get_or_insert_default().insert(
+                "duration_ms".to_owned(),
+                (start.elapsed().as_millis() as u64).into(),
+            );
+        }
+
+        let result = match self.config.max_result_bytes {
+            Some(max_bytes) => limit_result(result, max_bytes, self.config.oversized_result)?,
+            None => result,
+        };
+
+        if let Some((cache, call, arguments)) = idempotency {
+            cache.insert(call, arguments, result.clone());
+        }
+
+        Ok(result)
+    }
+
+    async fn call_custom_tool(
+        &self,
+        custom_tool: T,
+        context: ToolContext,
+    ) -> Result<CallToolResult, CallToolError> {
+        let tool = custom_tool.get_tool();
+        let call = context.scope(tool.call());
+
+        if self.config.catch_panics {
+            // synchronous tools run inside the first poll of the future, so
+            // this also catches panics from non-async tools
+            AssertUnwindSafe(call)
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|payload| {
+                    Err(CallToolError::from_message(format!(
+                        "tool panicked: {}",
+                        panic_message(payload.as_ref())
+                    )))
+                })
+        } else {
+            call.await
+        }
+    }
+}
+
+/// Converts a tool error with a code or additional content into an error result that
+/// carries the code in its `_meta` field and the content after the error message. Other
+/// errors are left for the SDK to convert.
+fn tool_error_result(error: CallToolError) -> Result<CallToolResult, CallToolError> {
+    let (code, content) = match error.0.downcast_ref::<ToolError>() {
+        Some(tool_error) if tool_error.code().is_some() || !tool_error.content().is_empty() => {
+            (tool_error.code(), tool_error.content().to_vec())
+        }
+        _ => return Err(error),
+    };
+
+    let mut result = CallToolResult::from(error);
+    result.content.extend(content);
+
+    if let Some(code) = code {
+        let mut meta = serde_json::Map::new();
+        meta.insert("error_code".to_owned(), code.into());
+        result = result.with_meta(Some(meta));
+    }
+
+    Ok(result)
+}
+
+fn sanitize_name(name: &str) -> String {
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    name.trim().to_owned()
+}
+
+fn log_client_initialize(params: &InitializeRequestParams) {
+    tracing::info!(
+        client_name = %params.client_info.name,
+        client_version = %params.client_info.version,
+        protocol_version = %params.protocol_version,
+        capabilities = %serde_json::to_string(&params.capabilities).unwrap_or_default(),
+        "client initialized"
+    );
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic payload"
+    }
+}
+
+#[async_trait]
+#[allow(unused)]
+impl<T> ServerHandler for Handler<T>
+where
+    T: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError> + Send + Sync + 'static,
 {
+    async fn on_initialized(&self, runtime: Arc<dyn McpServer>) {
+        if self.config.log_initialize
+            && let Some(client_info) = runtime.client_info()
+        {
+            log_client_initialize(&client_info);
+        }
+    }
+
     async fn handle_list_tools_request(
         &self,
         params: Option<PaginatedRequestParams>,
         runtime: Arc<dyn McpServer>,
     ) -> Result<ListToolsResult, RpcError> {
+        let client_info = runtime.client_info();
+
         Ok(ListToolsResult {
             meta: None,
-            next_cursor: None,
-            tools: T::get_tools(),
+            next_cursor: None,
+            tools: self.list_localized_tools(client_info.as_ref().and_then(client_locale)),
+        })
+    }
+
+    async fn handle_call_tool_request(
+        &self,
+        params: CallToolRequestParams,
+        runtime: Arc<dyn McpServer>,
+    ) -> Result<CallToolResult, CallToolError> {
+        let client_capabilities = runtime.client_info().map(|info| info.capabilities);
+        let context = self
+            .context(client_capabilities, runtime.session_id())
+            .with_progress_notifier(ProgressNotifier(Arc::new(move |params| {
+                let runtime = runtime.clone();
+                async move {
+                    if let Err(error) = runtime.notify_progress(params).await {
+                        tracing::warn!(%error, "unable to send a progress notification");
+                    }
+                }
+                .boxed()
+            })));
+
+        self.call_tool(params, context)
+            .await
+            .or_else(tool_error_result)
+    }
+
+    async fn handle_complete_request(
+        &self,
+        params: CompleteRequestParams,
+        runtime: Arc<dyn McpServer>,
+    ) -> Result<CompleteResult, RpcError> {
+        self.complete(&params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server_config::EnvLookup;
+    use crate::server_prelude::{CompleteRequestArgument, CompleteRequestRef, setup_tools};
+    use crate::tool_prelude::*;
+    use rust_mcp_sdk::schema::{CallToolMeta, PromptReference, ToolInputSchema};
+
+    #[mcp_tool(name = "panicking_tool", description = "A tool that always panics")]
+    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    pub struct PanickingTool {}
+
+    impl TextTool for PanickingTool {
+        type Output = String;
+
+        fn call(&self) -> Self::Output {
+            panic!("something went terribly wrong")
+        }
+    }
+
+    #[mcp_tool(name = "echo_tool", description = "A tool that echoes its message")]
+    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    pub struct EchoTool {
+        message: String,
+    }
+
+    impl TextTool for EchoTool {
+        type Output = String;
+
+        fn call(&self) -> Self::Output {
+            self.message.clone()
+        }
+    }
+
+    #[mcp_tool(
+        name = "sampling_support",
+        description = "A tool that reports if the client supports sampling"
+    )]
+    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    pub struct SamplingSupportTool {}
+
+    impl TextTool for SamplingSupportTool {
+        type Output = String;
+
+        fn call(&self) -> Self::Output {
+            match ToolContext::current() {
+                Some(context) => context.client_supports_sampling().to_string(),
+                None => "no context".to_owned(),
+            }
+        }
+    }
+
+    #[mcp_tool(
+        name = "transport_tool",
+        description = "A tool that reports the transport of the call"
+    )]
+    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    pub struct TransportTool {}
+
+    impl TextTool for TransportTool {
+        type Output = String;
+
+        fn call(&self) -> Self::Output {
+            match ToolContext::current() {
+                Some(context) => format!("{:?}", context.transport_kind()),
+                None => "no context".to_owned(),
+            }
+        }
+    }
+
+    #[mcp_tool(
+        name = "sleeping_tool",
+        description = "A tool that sleeps before answering"
+    )]
+    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    pub struct SleepingTool {}
+
+    #[async_trait]
+    impl AsyncTextTool for SleepingTool {
+        type Output = &'static str;
+
+        async fn call(&self) -> Self::Output {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            "awake"
+        }
+    }
+
+    setup_tools!(
+        TestTools,
+        [
+            text(PanickingTool),
+            text(EchoTool aliases ["repeat_tool"]),
+            text(SamplingSupportTool),
+            text(TransportTool),
+            async_text(SleepingTool),
+        ]
+    );
+
+    fn echo_params(name: &str) -> CallToolRequestParams {
+        let mut params = echo_message_params("hello");
+        params.name = name.to_owned();
+        params
+    }
+
+    fn echo_message_params(message: &str) -> CallToolRequestParams {
+        EchoTool::request_params().with_arguments(
+            serde_json::json!({ "message": message })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )
+    }
+
+    #[tokio::test]
+    async fn max_result_bytes_rejects_large_results() {
+        let config = ServerBuilder::new().with_max_result_bytes(16).config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let error = handler
+            .call_tool(
+                echo_message_params(&"a".repeat(1000)),
+                ToolContext::default(),
+            )
+            .await
+            .expect_err("expected the large result to be rejected");
+
+        assert_eq!(
+            error.to_string(),
+            "tool result is 1000 bytes, which exceeds the limit of 16 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn max_result_bytes_truncates_large_results() {
+        let config = ServerBuilder::new()
+            .with_max_result_bytes(16)
+            .with_oversized_result(OversizedResult::Truncate)
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(
+                echo_message_params(&"a".repeat(1000)),
+                ToolContext::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "a".repeat(16)
+        );
+    }
+
+    #[tokio::test]
+    async fn case_insensitive_tools_match_names_in_any_case() {
+        let config = ServerBuilder::new()
+            .with_case_insensitive_tools(true)
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(echo_params("ECHO_TOOL"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.content[0].as_text_content().unwrap().text, "hello");
+        assert!(
+            handler
+                .list_tools()
+                .iter()
+                .any(|tool| tool.name == "echo_tool")
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_names_are_case_sensitive_by_default() {
+        let handler = Handler::<TestTools>::new(ServerConfig::default(), TransportKind::Stdio);
+
+        let error = handler
+            .call_tool(echo_params("ECHO_TOOL"), ToolContext::default())
+            .await
+            .expect_err("expected the tool name to be unknown");
+
+        assert_eq!(
+            error.to_string(),
+            "Unknown tool: ECHO_TOOL (available tools: panicking_tool, echo_tool, sampling_support, transport_tool, sleeping_tool)"
+        );
+    }
+
+    mod ambiguous {
+        use super::EchoTool;
+        use crate::server_prelude::setup_tools;
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "ECHO_TOOL", description = "A tool that shouts its message")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct ShoutingEchoTool {
+            message: String,
+        }
+
+        impl TextTool for ShoutingEchoTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                self.message.to_uppercase()
+            }
+        }
+
+        setup_tools!(pub AmbiguousTools, [text(EchoTool), text(ShoutingEchoTool)]);
+    }
+
+    mod warmup {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::server_prelude::setup_tools;
+        use crate::tool_prelude::*;
+
+        pub static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        #[mcp_tool(name = "counting_tool", description = "A tool that counts its calls")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct CountingTool {}
+
+        impl TextTool for CountingTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                CALLS.fetch_add(1, Ordering::SeqCst).to_string()
+            }
+        }
+
+        setup_tools!(pub WarmupTools, [text(CountingTool)]);
+    }
+
+    #[tokio::test]
+    async fn warmup_calls_the_tool_once() {
+        let config = ServerBuilder::new()
+            .with_tool_prefix("test_")
+            .with_warmup("counting_tool")
+            .config;
+        let handler = Handler::<warmup::WarmupTools>::new(config, TransportKind::Stdio);
+
+        handler.warm_up().await.unwrap();
+
+        assert_eq!(warmup::CALLS.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    mod sessions {
+        use crate::server_prelude::setup_tools;
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(
+            name = "session_counter",
+            description = "A tool that counts the calls of the session"
+        )]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct SessionCounterTool {}
+
+        impl TextTool for SessionCounterTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                let Some(session) =
+                    ToolContext::current().and_then(|context| context.session().cloned())
+                else {
+                    return "no session".to_owned();
+                };
+
+                let count = session
+                    .get("count")
+                    .and_then(|count| count.as_u64())
+                    .unwrap_or(0)
+                    + 1;
+                session.insert("count", count);
+                count.to_string()
+            }
+        }
+
+        setup_tools!(pub SessionTools, [text(SessionCounterTool)]);
+    }
+
+    mod idempotent {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::server_prelude::setup_tools;
+        use crate::tool_prelude::*;
+
+        pub static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        #[mcp_tool(
+            name = "create_ticket",
+            description = "Creates a ticket and returns its number",
+            idempotent_hint = true
+        )]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct CreateTicketTool {}
+
+        impl TextTool for CreateTicketTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                format!("ticket #{}", CALLS.fetch_add(1, Ordering::SeqCst) + 1)
+            }
+        }
+
+        pub static BOOKINGS: AtomicUsize = AtomicUsize::new(0);
+
+        #[mcp_tool(
+            name = "book_room",
+            description = "Books a room and returns the booking number",
+            idempotent_hint = true
+        )]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct BookRoomTool {
+            pub room: String,
+        }
+
+        impl TextTool for BookRoomTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                format!(
+                    "room {} booking #{}",
+                    self.room,
+                    BOOKINGS.fetch_add(1, Ordering::SeqCst) + 1
+                )
+            }
+        }
+
+        pub static REFUNDS: AtomicUsize = AtomicUsize::new(0);
+
+        #[mcp_tool(
+            name = "refund_order",
+            description = "Refunds an order and returns the refund number",
+            idempotent_hint = true
+        )]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct RefundOrderTool {}
+
+        impl TextTool for RefundOrderTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                format!("refund #{}", REFUNDS.fetch_add(1, Ordering::SeqCst) + 1)
+            }
+        }
+
+        setup_tools!(pub IdempotentTools, [
+            text(CreateTicketTool),
+            text(BookRoomTool),
+            text(RefundOrderTool hidden),
+        ]);
+    }
+
+    mod spaced {
+        use crate::server_prelude::setup_tools;
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "get weather", description = "Returns the weather")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct GetWeatherTool {}
+
+        impl TextTool for GetWeatherTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "sunny"
+            }
+        }
+
+        setup_tools!(pub SpacedTools, [text(GetWeatherTool)]);
+    }
+
+    mod progress {
+        use crate::server_prelude::setup_tools;
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(
+            name = "progress_token",
+            description = "Returns the progress token of the call"
+        )]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct ProgressTokenTool {}
+
+        impl TextTool for ProgressTokenTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                match ToolContext::current().and_then(|context| context.progress_token().cloned()) {
+                    Some(ProgressToken::String(token)) => token,
+                    Some(ProgressToken::Integer(token)) => token.to_string(),
+                    None => "no progress token".to_owned(),
+                }
+            }
+        }
+
+        setup_tools!(pub ProgressTools, [text(ProgressTokenTool)]);
+    }
+
+    mod structured {
+        use crate::server_prelude::setup_tools;
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "forecast", description = "Returns the forecast")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct ForecastTool {}
+
+        impl StructuredTool for ForecastTool {
+            type Output = serde_json::Value;
+
+            fn call(&self) -> Self::Output {
+                serde_json::json!({ "temperature": 21 })
+            }
+        }
+
+        setup_tools!(pub StructuredTools, [structured(ForecastTool)]);
+    }
+
+    async fn call_forecast(structured_text_fallback: bool) -> CallToolResult {
+        use structured::{ForecastTool, StructuredTools};
+
+        let config = ServerBuilder::new()
+            .with_structured_text_fallback(structured_text_fallback)
+            .config;
+        let handler = Handler::<StructuredTools>::new(config, TransportKind::Stdio);
+
+        handler
+            .call_tool(ForecastTool::request_params(), ToolContext::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn structured_results_include_a_text_fallback_by_default() {
+        let result = call_forecast(true).await;
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "{\n  \"temperature\": 21\n}"
+        );
+        assert_eq!(
+            result.structured_content.unwrap()["temperature"],
+            serde_json::json!(21)
+        );
+    }
+
+    #[tokio::test]
+    async fn structured_text_fallback_can_be_disabled() {
+        let result = call_forecast(false).await;
+
+        assert!(result.content.is_empty());
+        assert_eq!(
+            result.structured_content.unwrap()["temperature"],
+            serde_json::json!(21)
+        );
+    }
+
+    #[tokio::test]
+    async fn max_result_bytes_counts_structured_content_without_text_fallback() {
+        use structured::{ForecastTool, StructuredTools};
+
+        let config = ServerBuilder::new()
+            .with_structured_text_fallback(false)
+            .with_max_result_bytes(16)
+            .config;
+        let handler = Handler::<StructuredTools>::new(config, TransportKind::Stdio);
+
+        let error = handler
+            .call_tool(ForecastTool::request_params(), ToolContext::default())
+            .await
+            .expect_err("expected the structured result to be rejected");
+
+        assert_eq!(
+            error.to_string(),
+            "tool result is 18 bytes, which exceeds the limit of 16 bytes"
+        );
+    }
+
+    mod slow_sync {
+        use std::time::Duration;
+
+        use crate::server_prelude::setup_tools;
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(
+            name = "slow_sync",
+            description = "A synchronous tool that blocks before answering"
+        )]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct SlowSyncTool {}
+
+        impl TextTool for SlowSyncTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                std::thread::sleep(Duration::from_millis(50));
+                "done"
+            }
+        }
+
+        setup_tools!(pub SlowSyncTools, [text(SlowSyncTool)]);
+    }
+
+    #[tokio::test]
+    async fn max_call_duration_does_not_apply_to_sync_tools() {
+        use slow_sync::{SlowSyncTool, SlowSyncTools};
+
+        let config = ServerBuilder::new()
+            .with_max_call_duration(Duration::from_millis(10))
+            .config;
+        let handler = Handler::<SlowSyncTools>::new(config, TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(SlowSyncTool::request_params(), ToolContext::default())
+            .await
+            .expect("sync tools can't be interrupted, so they are not timed out");
+
+        assert_eq!(result.content[0].as_text_content().unwrap().text, "done");
+    }
+
+    #[tokio::test]
+    async fn calls_with_the_same_session_id_share_the_session_state() {
+        use sessions::{SessionCounterTool, SessionTools};
+
+        let handler = Handler::<SessionTools>::new(ServerConfig::default(), TransportKind::Http);
+        let mut counts = Vec::new();
+
+        for session_id in [Some("first"), Some("first"), Some("second"), None] {
+            let context = handler.context(None, session_id.map(str::to_owned));
+            let result = handler
+                .call_tool(SessionCounterTool::request_params(), context)
+                .await
+                .unwrap();
+            counts.push(result.content[0].as_text_content().unwrap().text.clone());
+        }
+
+        assert_eq!(counts, ["1", "2", "1", "no session"]);
+    }
+
+    #[tokio::test]
+    async fn calls_with_the_same_idempotency_key_replay_the_cached_result() {
+        use idempotent::{CreateTicketTool, IdempotentTools};
+
+        let config = ServerBuilder::new()
+            .with_idempotency_cache(Duration::from_secs(60))
+            .config;
+        let handler = Handler::<IdempotentTools>::new(config, TransportKind::Stdio);
+        let params = |key: &str| {
+            let mut meta = serde_json::Map::new();
+            meta.insert("idempotencyKey".to_owned(), key.into());
+            let mut params = CreateTicketTool::request_params();
+            params.meta = Some(CallToolMeta {
+                progress_token: None,
+                extra: Some(meta),
+            });
+            params
+        };
+        let mut texts = Vec::new();
+
+        for params in [params("first"), params("first"), params("second")] {
+            let result = handler
+                .call_tool(params, ToolContext::default())
+                .await
+                .unwrap();
+            texts.push(result.content[0].as_text_content().unwrap().text.clone());
+        }
+
+        assert_eq!(texts[0], texts[1]);
+        assert_ne!(texts[0], texts[2]);
+        assert_eq!(
+            idempotent::CALLS.load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_to_hidden_idempotent_tools_are_cached() {
+        use idempotent::{IdempotentTools, RefundOrderTool};
+
+        let config = ServerBuilder::new()
+            .with_idempotency_cache(Duration::from_secs(60))
+            .config;
+        let handler = Handler::<IdempotentTools>::new(config, TransportKind::Stdio);
+        let mut meta = serde_json::Map::new();
+        meta.insert("idempotencyKey".to_owned(), "refund".into());
+        let mut params = RefundOrderTool::request_params();
+        params.meta = Some(CallToolMeta {
+            progress_token: None,
+            extra: Some(meta),
+        });
+        let mut texts = Vec::new();
+
+        for params in [params.clone(), params] {
+            let result = handler
+                .call_tool(params, ToolContext::default())
+                .await
+                .unwrap();
+            texts.push(result.content[0].as_text_content().unwrap().text.clone());
+        }
+
+        assert_eq!(texts, ["refund #1", "refund #1"]);
+        assert_eq!(
+            idempotent::REFUNDS.load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn idempotency_keys_are_scoped_to_the_session_and_the_arguments() {
+        use idempotent::{BookRoomTool, IdempotentTools};
+
+        let config = ServerBuilder::new()
+            .with_idempotency_cache(Duration::from_secs(60))
+            .config;
+        let handler = Handler::<IdempotentTools>::new(config, TransportKind::Http);
+        let call = |session_id: &str, room: &str| {
+            let mut meta = serde_json::Map::new();
+            meta.insert("idempotencyKey".to_owned(), "booking".into());
+            let arguments = serde_json::json!({ "room": room });
+            let mut params = CallToolRequestParams::new(BookRoomTool::tool_name())
+                .with_arguments(arguments.as_object().unwrap().clone());
+            params.meta = Some(CallToolMeta {
+                progress_token: None,
+                extra: Some(meta),
+            });
+            handler.call_tool(params, handler.context(None, Some(session_id.to_owned())))
+        };
+        let text =
+            |result: CallToolResult| result.content[0].as_text_content().unwrap().text.clone();
+
+        let first = text(call("first", "A").await.unwrap());
+        let second = text(call("second", "A").await.unwrap());
+        let replayed = text(call("first", "A").await.unwrap());
+        let error = call("first", "B").await.unwrap_err();
+
+        assert_ne!(first, second);
+        assert_eq!(first, replayed);
+        assert_eq!(
+            error.to_string(),
+            "the idempotency key `booking` was already used to call `book_room` with other arguments"
+        );
+        assert_eq!(
+            idempotent::BOOKINGS.load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn progress_token_is_available_from_the_context() {
+        use progress::{ProgressTokenTool, ProgressTools};
+
+        let handler = Handler::<ProgressTools>::new(ServerConfig::default(), TransportKind::Stdio);
+        let mut texts = Vec::new();
+
+        for progress_token in [
+            Some(ProgressToken::String("upload-7".to_owned())),
+            Some(ProgressToken::Integer(42)),
+            None,
+        ] {
+            let mut params = ProgressTokenTool::request_params();
+            params.meta = Some(CallToolMeta {
+                progress_token,
+                extra: None,
+            });
+            let result = handler
+                .call_tool(params, ToolContext::default())
+                .await
+                .unwrap();
+            texts.push(result.content[0].as_text_content().unwrap().text.clone());
+        }
+
+        assert_eq!(texts, ["upload-7", "42", "no progress token"]);
+    }
+
+    #[tokio::test]
+    async fn failed_warmup_is_not_fatal_by_default() {
+        let config = ServerBuilder::new().with_warmup("echo_tool").config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        assert!(handler.warm_up().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn failed_strict_warmup_is_fatal() {
+        let config = ServerBuilder::new()
+            .with_warmup("nonexistent_tool")
+            .with_warmup_strict(true)
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let error = handler.warm_up().await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            concat!(
+                "Server error: warmup of tool `nonexistent_tool` failed: Unknown tool: nonexistent_tool ",
+                "(available tools: panicking_tool, echo_tool, sampling_support, transport_tool, sleeping_tool)"
+            )
+        );
+    }
+
+    /// The tools of [`TestTools`], with an invalid input schema for `echo_tool`.
+    struct InvalidSchemaTools(TestTools);
+
+    impl ToolBox for InvalidSchemaTools {
+        fn get_tool(&self) -> CustomTool<'_> {
+            self.0.get_tool()
+        }
+
+        fn get_tools() -> Vec<Tool> {
+            let mut tools = TestTools::get_tools();
+
+            for tool in tools.iter_mut().filter(|tool| tool.name == "echo_tool") {
+                let message = serde_json::json!({ "type": 42 });
+                let properties = [("message".to_owned(), message.as_object().unwrap().clone())];
+                tool.input_schema = ToolInputSchema::new(
+                    vec!["message".to_owned()],
+                    Some(properties.into_iter().collect()),
+                    None,
+                );
+            }
+
+            tools
+        }
+    }
+
+    impl TryFrom<CallToolRequestParams> for InvalidSchemaTools {
+        type Error = CallToolError;
+
+        fn try_from(params: CallToolRequestParams) -> Result<Self, Self::Error> {
+            TestTools::try_from(params).map(Self)
+        }
+    }
+
+    static TOOL_LISTINGS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    /// The tools of [`TestTools`], counting how many times they are listed.
+    struct CountingTools(TestTools);
+
+    impl ToolBox for CountingTools {
+        fn get_tool(&self) -> CustomTool<'_> {
+            self.0.get_tool()
+        }
+
+        fn get_tools() -> Vec<Tool> {
+            TOOL_LISTINGS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            TestTools::get_tools()
+        }
+
+        fn get_hidden_tools() -> Vec<Tool> {
+            TOOL_LISTINGS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            TestTools::get_hidden_tools()
+        }
+
+        fn get_aliases() -> Vec<(String, String)> {
+            TOOL_LISTINGS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            TestTools::get_aliases()
+        }
+    }
+
+    impl TryFrom<CallToolRequestParams> for CountingTools {
+        type Error = CallToolError;
+
+        fn try_from(params: CallToolRequestParams) -> Result<Self, Self::Error> {
+            TestTools::try_from(params).map(Self)
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_calls_do_not_list_the_tools_again() {
+        let config = ServerBuilder::new()
+            .with_case_insensitive_tools(true)
+            .with_lenient_argument_coercion(true)
+            .with_idempotency_cache(Duration::from_secs(60))
+            .config;
+        let handler = Handler::<CountingTools>::new(config, TransportKind::Stdio);
+        let listings = TOOL_LISTINGS.load(std::sync::atomic::Ordering::SeqCst);
+
+        for name in ["echo_tool", "repeat_tool", "ECHO_TOOL"] {
+            handler
+                .call_tool(echo_params(name), ToolContext::default())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            TOOL_LISTINGS.load(std::sync::atomic::Ordering::SeqCst),
+            listings
+        );
+    }
+
+    #[test]
+    fn invalid_input_schema_prevents_startup_by_default() {
+        let error = check_tools::<InvalidSchemaTools>(&ServerConfig::default()).unwrap_err();
+
+        assert!(check_tools::<TestTools>(&ServerConfig::default()).is_ok());
+        assert!(
+            error
+                .to_string()
+                .starts_with("Server error: tool `echo_tool` has an invalid input schema: "),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn tools_with_invalid_input_schema_can_be_skipped() {
+        let config = ServerBuilder::new().with_skip_invalid_tools(true).config;
+        assert!(check_tools::<InvalidSchemaTools>(&config).is_ok());
+
+        let handler = Handler::<InvalidSchemaTools>::new(config, TransportKind::Stdio);
+        let names: Vec<_> = handler
+            .list_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        let error = handler
+            .call_tool(echo_params("echo_tool"), ToolContext::default())
+            .await
+            .expect_err("expected the invalid tool to be skipped")
+            .to_string();
+
+        assert!(!names.contains(&"echo_tool".to_owned()));
+        assert!(names.contains(&"panicking_tool".to_owned()));
+        assert_eq!(
+            error,
+            "Unknown tool: echo_tool (available tools: panicking_tool, sampling_support, transport_tool, sleeping_tool)"
+        );
+    }
+
+    #[test]
+    fn tool_names_with_spaces_prevent_startup_when_enforced() {
+        let config = ServerBuilder::new()
+            .with_enforce_tool_name_rules(true)
+            .config;
+
+        assert!(check_tools::<spaced::SpacedTools>(&ServerConfig::default()).is_ok());
+        assert!(check_tools::<TestTools>(&config).is_ok());
+        assert_eq!(
+            check_tools::<spaced::SpacedTools>(&config)
+                .unwrap_err()
+                .to_string(),
+            "Server error: tool name `get weather` does not match `^[a-zA-Z0-9_-]+$`"
+        );
+    }
+
+    #[test]
+    fn case_insensitive_tools_reject_names_differing_by_case() {
+        let config = ServerBuilder::new()
+            .with_case_insensitive_tools(true)
+            .config;
+
+        assert!(check_tools::<ambiguous::AmbiguousTools>(&ServerConfig::default()).is_ok());
+        assert_eq!(
+            check_tools::<ambiguous::AmbiguousTools>(&config)
+                .unwrap_err()
+                .to_string(),
+            "Server error: multiple tools are named `ECHO_TOOL` when ignoring case"
+        );
+    }
+
+    #[test]
+    fn listed_tools_follow_the_tool_sort() {
+        let config = ServerBuilder::new()
+            .with_tool_sort(ToolSort::Alphabetical)
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let names: Vec<_> = handler
+            .list_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "echo_tool",
+                "panicking_tool",
+                "sampling_support",
+                "sleeping_tool",
+                "transport_tool",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn aliases_are_listed_when_enabled() {
+        let config = ServerBuilder::new()
+            .with_tool_prefix("test_")
+            .with_list_aliases(true)
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let tools = handler.list_tools();
+        let alias = tools
+            .iter()
+            .find(|tool| tool.name == "test_repeat_tool")
+            .expect("expected the alias to be listed");
+        let result = handler
+            .call_tool(echo_params("test_repeat_tool"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            alias.description.as_deref(),
+            Some("A tool that echoes its message")
+        );
+        assert_eq!(result.content[0].as_text_content().unwrap().text, "hello");
+    }
+
+    #[test]
+    fn tool_prefix_is_applied_to_listed_tools() {
+        let config = ServerBuilder::new().with_tool_prefix("test_").config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let names: Vec<_> = handler
+            .list_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "test_panicking_tool",
+                "test_echo_tool",
+                "test_sampling_support",
+                "test_transport_tool",
+                "test_sleeping_tool"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_prefix_is_stripped_when_calling_a_tool() {
+        let config = ServerBuilder::new().with_tool_prefix("test_").config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(echo_params("test_echo_tool"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.content[0].as_text_content().unwrap().text, "hello");
+    }
+
+    #[tokio::test]
+    async fn invalid_arguments_are_not_reported_as_unknown_tools() {
+        let handler = Handler::<TestTools>::new(ServerConfig::default(), TransportKind::Stdio);
+
+        let error = handler
+            .call_tool(EchoTool::request_params(), ToolContext::default())
+            .await
+            .expect_err("expected the missing argument to be rejected");
+
+        assert!(!error.0.is::<UnknownTool>());
+        assert!(!error.to_string().contains("available tools"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn tool_prefix_rejects_unprefixed_tool_names() {
+        let config = ServerBuilder::new().with_tool_prefix("test_").config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let error = handler
+            .call_tool(echo_params("echo_tool"), ToolContext::default())
+            .await
+            .expect_err("expected the unprefixed name to be rejected");
+
+        assert_eq!(
+            error.to_string(),
+            "Unknown tool: echo_tool (available tools: test_panicking_tool, test_echo_tool, test_sampling_support, test_transport_tool, test_sleeping_tool)"
+        );
+    }
+
+    async fn reported_transport(handler: &Handler<TestTools>) -> String {
+        let result = handler
+            .call_tool(TransportTool::request_params(), handler.context(None, None))
+            .await
+            .unwrap();
+
+        result.content[0].as_text_content().unwrap().text.clone()
+    }
+
+    #[tokio::test]
+    async fn tools_can_read_the_transport_kind() {
+        let stdio = Handler::<TestTools>::new(ServerConfig::default(), TransportKind::Stdio);
+        let http = Handler::<TestTools>::new(ServerConfig::default(), TransportKind::Http);
+
+        assert_eq!(reported_transport(&stdio).await, "Stdio");
+        assert_eq!(reported_transport(&http).await, "Http");
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn in_memory_client_reports_the_in_memory_transport() {
+        let client = ServerBuilder::new()
+            .start_in_memory::<TestTools>()
+            .await
+            .unwrap();
+
+        let result = client
+            .call_tool(TransportTool::request_params())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "InMemory"
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn disabled_tools_are_not_listed_nor_callable() {
+        let builder = ServerBuilder::new().with_tools_disabled(true);
+        assert!(
+            builder
+                .clone()
+                .get_server_details::<TestTools>()
+                .capabilities
+                .tools
+                .is_none()
+        );
+
+        let client = builder.start_in_memory::<TestTools>().await.unwrap();
+        assert!(client.list_tools().is_empty());
+
+        let error = client
+            .call_tool(TransportTool::request_params())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "tools are disabled on this server, `transport_tool` was not called"
+        );
+    }
+
+    struct ColorCompletions;
+
+    impl CompletionProvider for ColorCompletions {
+        fn complete(
+            _reference: &CompleteRequestRef,
+            argument: &CompleteRequestArgument,
+        ) -> Vec<String> {
+            if argument.name != "color" {
+                return Vec::new();
+            }
+
+            ["green", "grey", "red"]
+                .into_iter()
+                .filter(|color| color.starts_with(&argument.value))
+                .map(str::to_owned)
+                .collect()
+        }
+    }
+
+    fn complete_params(argument: &str, value: &str) -> CompleteRequestParams {
+        CompleteRequestParams {
+            argument: CompleteRequestArgument {
+                name: argument.to_owned(),
+                value: value.to_owned(),
+            },
+            context: None,
+            meta: None,
+            ref_: CompleteRequestRef::PromptReference(PromptReference::new(
+                "paint".to_owned(),
+                None,
+            )),
+        }
+    }
+
+    #[test]
+    fn env_expansion_replaces_variables_in_title_and_instructions() {
+        let mut builder = ServerBuilder::new()
+            .with_title("Server (${MCP_UTILS_TEST_ENVIRONMENT})")
+            .with_instructions(
+                "Running in ${MCP_UTILS_TEST_ENVIRONMENT}, ${MCP_UTILS_TEST_REGION:-eu}",
+            );
+        builder.config.env_lookup = EnvLookup(Arc::new(|name| {
+            (name == "MCP_UTILS_TEST_ENVIRONMENT").then(|| "staging".to_owned())
+        }));
+
+        let expanded = builder
+            .clone()
+            .with_env_expansion(true)
+            .get_server_details::<TestTools>();
+        let verbatim = builder.get_server_details::<TestTools>();
+
+        assert_eq!(
+            expanded.server_info.title.as_deref(),
+            Some("Server (staging)")
+        );
+        assert_eq!(
+            expanded.instructions.as_deref(),
+            Some("Running in staging, eu")
+        );
+        assert_eq!(
+            verbatim.server_info.title.as_deref(),
+            Some("Server (${MCP_UTILS_TEST_ENVIRONMENT})")
+        );
+    }
+
+    #[test]
+    fn sanitize_name_removes_control_characters() {
+        let builder = ServerBuilder::new().with_name(" weather\u{1b}[31m\tserver\n");
+
+        let sanitized = builder
+            .clone()
+            .with_sanitize_name(true)
+            .get_server_details::<TestTools>();
+        let verbatim = builder.get_server_details::<TestTools>();
+
+        assert_eq!(sanitized.server_info.name, "weather[31mserver");
+        assert_eq!(verbatim.server_info.name, " weather\u{1b}[31m\tserver\n");
+    }
+
+    #[test]
+    fn completions_capability_is_advertised_when_enabled() {
+        let disabled = ServerBuilder::new().get_server_details::<TestTools>();
+        let enabled = ServerBuilder::new()
+            .with_completions::<ColorCompletions>()
+            .get_server_details::<TestTools>();
+
+        assert!(disabled.capabilities.completions.is_none());
+        assert!(enabled.capabilities.completions.is_some());
+    }
+
+    #[test]
+    fn completions_suggest_values_for_a_known_argument() {
+        let config = ServerBuilder::new()
+            .with_completions::<ColorCompletions>()
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let known = handler.complete(&complete_params("color", "gr")).unwrap();
+        let unknown = handler.complete(&complete_params("size", "gr")).unwrap();
+
+        assert_eq!(known.completion.values, ["green", "grey"]);
+        assert!(unknown.completion.values.is_empty());
+    }
+
+    #[test]
+    fn completions_are_not_found_when_disabled() {
+        let handler = Handler::<TestTools>::new(ServerConfig::default(), TransportKind::Stdio);
+
+        let error = handler
+            .complete(&complete_params("color", "gr"))
+            .expect_err("expected completions to be disabled");
+
+        assert_eq!(error.code, RpcError::method_not_found().code);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn in_memory_client_requests_completions() {
+        let client = ServerBuilder::new()
+            .with_completions::<ColorCompletions>()
+            .start_in_memory::<TestTools>()
+            .await
+            .unwrap();
+
+        let result = client.complete(complete_params("color", "r")).unwrap();
+
+        assert_eq!(result.completion.values, ["red"]);
+    }
+
+    #[tokio::test]
+    async fn tools_can_read_client_capabilities() {
+        let handler = Handler::<TestTools>::new(ServerConfig::default(), TransportKind::Stdio);
+        let capabilities = serde_json::from_value(serde_json::json!({ "sampling": {} })).unwrap();
+
+        let supported = handler
+            .call_tool(
+                SamplingSupportTool::request_params(),
+                handler.context(Some(capabilities), None),
+            )
+            .await
+            .unwrap();
+        let unsupported = handler
+            .call_tool(
+                SamplingSupportTool::request_params(),
+                handler.context(Some(Default::default()), None),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(supported.content[0].as_text_content().unwrap().text, "true");
+        assert_eq!(
+            unsupported.content[0].as_text_content().unwrap().text,
+            "false"
+        );
+    }
+
+    #[test]
+    fn tool_context_is_not_available_outside_of_tool_calls() {
+        assert!(ToolContext::current().is_none());
+    }
+
+    fn fallback_builder() -> ServerBuilder {
+        ServerBuilder::new().with_fallback_tool(|params: CallToolRequestParams| async move {
+            Ok(CallToolResult::text_content(vec![
+                format!("fallback called for `{}`", params.name).into(),
+            ]))
         })
     }
 
-    async fn handle_call_tool_request(
-        &self,
-        params: CallToolRequestParams,
-        runtime: Arc<dyn McpServer>,
-    ) -> Result<CallToolResult, CallToolError> {
-        let custom_tool = T::try_from(params).map_err(CallToolError::new)?;
+    #[tokio::test]
+    async fn fallback_tool_handles_unknown_tool_names() {
+        let handler = Handler::<TestTools>::new(fallback_builder().config, TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(echo_params("nonexistent_tool"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "fallback called for `nonexistent_tool`"
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_without_the_tool_prefix_go_to_the_fallback() {
+        let config = fallback_builder().with_tool_prefix("test_").config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(echo_params("echo_tool"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "fallback called for `echo_tool`"
+        );
+    }
+
+    #[tokio::test]
+    async fn fallback_tool_receives_unknown_names_with_the_tool_prefix() {
+        let config = fallback_builder().with_tool_prefix("test_").config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(echo_params("test_missing"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "fallback called for `test_missing`"
+        );
+    }
+
+    #[tokio::test]
+    async fn fallback_tool_is_not_used_for_invalid_arguments() {
+        let handler = Handler::<TestTools>::new(fallback_builder().config, TransportKind::Stdio);
+
+        let error = handler
+            .call_tool(EchoTool::request_params(), ToolContext::default())
+            .await
+            .expect_err("expected the missing argument to be rejected");
+
+        assert_eq!(error.to_string(), "missing field `message`");
+    }
+
+    #[tokio::test]
+    async fn rate_limited_tool_fails_once_the_bucket_is_empty() {
+        let config = ServerBuilder::new()
+            .with_tool_rate_limit("echo_tool", 3, Duration::from_secs(3600))
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        for _ in 0..3 {
+            handler
+                .call_tool(echo_params("echo_tool"), ToolContext::default())
+                .await
+                .unwrap();
+        }
+
+        let error = handler
+            .call_tool(echo_params("echo_tool"), ToolContext::default())
+            .await
+            .expect_err("expected the call to be rate limited");
+
+        assert!(
+            error
+                .to_string()
+                .starts_with("tool `echo_tool` is rate limited, retry after"),
+            "{error}"
+        );
+        handler
+            .call_tool(
+                SamplingSupportTool::request_params(),
+                ToolContext::default(),
+            )
+            .await
+            .expect("tools without a rate limit are not limited");
+    }
+
+    #[tokio::test]
+    async fn rate_limits_apply_to_calls_through_aliases() {
+        let config = ServerBuilder::new()
+            .with_tool_rate_limit("echo_tool", 2, Duration::from_secs(3600))
+            .with_case_insensitive_tools(true)
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        for name in ["echo_tool", "repeat_tool"] {
+            handler
+                .call_tool(echo_params(name), ToolContext::default())
+                .await
+                .unwrap();
+        }
+
+        for name in ["repeat_tool", "Repeat_Tool"] {
+            let error = handler
+                .call_tool(echo_params(name), ToolContext::default())
+                .await
+                .expect_err("expected the alias to share the bucket of its tool");
+
+            assert!(
+                error
+                    .to_string()
+                    .starts_with("tool `echo_tool` is rate limited, retry after"),
+                "{error}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn argument_defaults_fill_omitted_arguments() {
+        let config = ServerBuilder::new()
+            .with_argument_default("echo_tool", "message", "default message")
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let omitted = handler
+            .call_tool(EchoTool::request_params(), ToolContext::default())
+            .await
+            .unwrap();
+        let provided = handler
+            .call_tool(echo_message_params("hello"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            omitted.content[0].as_text_content().unwrap().text,
+            "default message"
+        );
+        assert_eq!(provided.content[0].as_text_content().unwrap().text, "hello");
+    }
+
+    #[tokio::test]
+    async fn argument_defaults_apply_to_calls_through_aliases() {
+        let config = ServerBuilder::new()
+            .with_argument_default("echo_tool", "message", "default message")
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+        let mut params = EchoTool::request_params();
+        params.name = "repeat_tool".to_owned();
+
+        let result = handler
+            .call_tool(params, ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "default message"
+        );
+    }
+
+    #[tokio::test]
+    async fn max_call_duration_stops_slow_tools() {
+        let config = ServerBuilder::new()
+            .with_max_call_duration(Duration::from_millis(20))
+            .config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let error = handler
+            .call_tool(SleepingTool::request_params(), ToolContext::default())
+            .await
+            .expect_err("expected the call to time out");
+        let result = tool_error_result(error).unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "tool `sleeping_tool` exceeded 20ms"
+        );
+        assert_eq!(result.meta.unwrap()["error_code"], -32001);
+    }
+
+    #[tokio::test]
+    async fn audit_log_writes_a_line_per_call() {
+        let path =
+            std::env::temp_dir().join(format!("mcp-utils-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let builder = ServerBuilder::new()
+            .with_audit_log(&path)
+            .with_audit_log_redaction(["message"]);
+        let handler = Handler::<TestTools>::new(builder.config.clone(), TransportKind::Stdio)
+            .with_audit_log(builder.open_audit_log().unwrap());
+
+        handler
+            .call_tool(echo_message_params("secret"), ToolContext::default())
+            .await
+            .unwrap();
+        let _ = handler
+            .call_tool(echo_params("unknown_tool"), ToolContext::default())
+            .await;
+        // waits for the queued lines to be written
+        drop(handler);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["tool"], "echo_tool");
+        assert_eq!(
+            lines[0]["arguments"],
+            serde_json::json!({ "message": "[redacted]" })
+        );
+        assert_eq!(lines[0]["success"], true);
+        assert_eq!(lines[1]["tool"], "unknown_tool");
+        assert_eq!(lines[1]["success"], false);
+        assert!(lines[1]["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn timing_meta_adds_call_duration() {
+        let config = ServerBuilder::new().with_timing_meta(true).config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(echo_params("echo_tool"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert!(result.meta.unwrap()["duration_ms"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn timing_meta_is_disabled_by_default() {
+        let handler = Handler::<TestTools>::new(ServerConfig::default(), TransportKind::Stdio);
+
+        let result = handler
+            .call_tool(echo_params("echo_tool"), ToolContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.meta, None);
+    }
+
+    #[test]
+    fn coded_tool_errors_carry_their_code_in_meta() {
+        let error = CallToolError::new(ToolError::from("quota exceeded").with_code(429));
+
+        let result = tool_error_result(error).unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "quota exceeded"
+        );
+        assert_eq!(result.meta.unwrap()["error_code"], 429);
+    }
+
+    #[test]
+    fn tool_errors_with_content_produce_error_results_with_content() {
+        let error = CallToolError::new(ToolError::from("the query is invalid").with_content(
+            ContentBlock::text_content("unknown column `nmae`".to_owned()),
+        ));
+
+        let result = tool_error_result(error).unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+        let texts: Vec<_> = result
+            .content
+            .iter()
+            .map(|content| content.as_text_content().unwrap().text.as_str())
+            .collect();
+        assert_eq!(texts, ["the query is invalid", "unknown column `nmae`"]);
+        assert_eq!(result.meta, None);
+    }
+
+    #[test]
+    fn tool_errors_without_code_or_content_are_left_unchanged() {
+        let error = CallToolError::new(ToolError::from("failure"));
+
+        assert!(tool_error_result(error).is_err());
+    }
+
+    #[test]
+    fn closed_stdin_is_detected_as_closed_pipe() {
+        let closed_stdin = io::Error::new(io::ErrorKind::UnexpectedEof, "stdin closed");
+        let broken_pipe = io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe");
+        let other = io::Error::new(io::ErrorKind::PermissionDenied, "permission denied");
+
+        assert!(is_pipe_closed(&McpSdkError::Transport(TransportError::Io(
+            closed_stdin
+        ))));
+        assert!(is_pipe_closed(&McpSdkError::Io(broken_pipe)));
+        assert!(!is_pipe_closed(&McpSdkError::Io(other)));
+        assert!(!is_pipe_closed(&McpSdkError::Internal {
+            description: "failure".to_owned()
+        }));
+    }
+
+    #[tokio::test]
+    async fn catch_panics_converts_panic_into_tool_error() {
+        let config = ServerBuilder::new().with_catch_panics(true).config;
+        let handler = Handler::<TestTools>::new(config, TransportKind::Stdio);
+
+        let error = handler
+            .call_tool(PanickingTool::request_params(), ToolContext::default())
+            .await
+            .expect_err("expected the panic to be converted into an error");
+
+        assert_eq!(
+            error.to_string(),
+            "tool panicked: something went terribly wrong"
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_init_aborts_startup() {
+        let builder =
+            ServerBuilder::new().with_init(|| async { Err::<(), _>("database unavailable") });
+
+        let error = builder
+            .start_stdio::<TestTools>()
+            .await
+            .expect_err("expected the initialization error");
+
+        assert_eq!(
+            error.to_string(),
+            "Server error: initialization failed: database unavailable"
+        );
+    }
+
+    #[tokio::test]
+    async fn too_long_name_aborts_startup_when_limited() {
+        let builder = ServerBuilder::new()
+            .with_name("a-server-with-a-very-long-name")
+            .with_field_limits(16, OversizedField::Reject);
+
+        let error = builder
+            .start_stdio::<TestTools>()
+            .await
+            .expect_err("expected the name to be rejected");
+
+        assert_eq!(
+            error.to_string(),
+            "Server error: server name is 30 characters long, which exceeds the limit of 16 characters"
+        );
+    }
+
+    #[test]
+    fn field_limits_truncate_name_and_title() {
+        let mut builder = ServerBuilder::new()
+            .with_name("a-server-with-a-very-long-name")
+            .with_title("A Server")
+            .with_field_limits(8, OversizedField::Truncate);
+
+        builder.apply_field_limits().unwrap();
+
+        assert_eq!(builder.name(), "a-server");
+        assert_eq!(builder.title(), "A Server");
+    }
+
+    #[test]
+    fn rename_returns_a_renamed_copy() {
+        let builder = ServerBuilder::new()
+            .with_name("original")
+            .with_default_port(3000);
+
+        let renamed = builder.rename("renamed");
+
+        assert_eq!(builder.name(), "original");
+        assert_eq!(renamed.name(), "renamed");
+        assert_eq!(renamed.default_port(), 3000);
+    }
+
+    #[test]
+    fn instructions_append_joins_fragments_with_newlines() {
+        let builder = ServerBuilder::new()
+            .with_instructions_append("Use the search tool first.")
+            .with_instructions_append("Answer in English.");
+
+        assert_eq!(
+            builder.instructions(),
+            "Use the search tool first.\nAnswer in English."
+        );
+    }
+
+    #[test]
+    fn http_options_use_sse_by_default() {
+        let options =
+            ServerBuilder::new().http_options("".to_owned(), 8080, TransportOptions::default());
+
+        assert_eq!(options.host, "127.0.0.1");
+        assert!(options.sse_support);
+        assert_eq!(options.enable_json_response, None);
+    }
+
+    #[test]
+    fn http_options_forward_the_sse_keepalive() {
+        let builder = ServerBuilder::new().with_sse_keepalive(Duration::from_secs(5));
+
+        let options = builder.http_options("".to_owned(), 8080, TransportOptions::default());
+
+        assert_eq!(options.ping_interval, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn http_transport_options_forward_the_sse_buffer_size() {
+        let default = ServerBuilder::new().http_transport_options();
+        let builder = ServerBuilder::new().with_sse_buffer_size(256);
+
+        assert_eq!(builder.config.sse_buffer_size, Some(256));
+        assert_eq!(builder.http_transport_options().channel_capacity, 256);
+        assert_eq!(
+            default.channel_capacity,
+            TransportOptions::default().channel_capacity
+        );
+    }
+
+    #[test]
+    fn http_options_are_customized_last() {
+        let builder = ServerBuilder::new()
+            .with_sse_keepalive(Duration::from_secs(5))
+            .with_actix_options(|options| {
+                options.ping_interval = Duration::from_secs(30);
+                options.max_request_body_size = Some(1024);
+            });
+
+        let options = builder.http_options("".to_owned(), 8080, TransportOptions::default());
+
+        assert_eq!(options.ping_interval, Duration::from_secs(30));
+        assert_eq!(options.max_request_body_size, Some(1024));
+        assert_eq!(options.port, 8080);
+    }
+
+    #[test]
+    fn http_options_keep_events_when_session_resumption_is_enabled() {
+        let default =
+            ServerBuilder::new().http_options("".to_owned(), 8080, TransportOptions::default());
+        let resumable = ServerBuilder::new()
+            .with_session_resumption(true)
+            .http_options("".to_owned(), 8080, TransportOptions::default());
+
+        assert!(default.event_store.is_none());
+        assert!(resumable.event_store.is_some());
+    }
+
+    #[test]
+    fn http_layers_forward_tcp_settings() {
+        let default = ServerBuilder::new().http_layers();
+        let tuned = ServerBuilder::new()
+            .with_tcp_backlog(4096)
+            .with_tcp_nodelay(true)
+            .http_layers();
+
+        assert_eq!(default.tcp_backlog, None);
+        assert_eq!(default.tcp_nodelay, None);
+        assert!(default.is_empty());
+        assert_eq!(tuned.tcp_backlog, Some(4096));
+        assert_eq!(tuned.tcp_nodelay, Some(true));
+        assert!(!tuned.is_empty());
+    }
+
+    #[test]
+    fn http_layers_forward_the_client_allowlist() {
+        let default = ServerBuilder::new().http_layers();
+        let layers = ServerBuilder::new()
+            .with_client_allowlist(["10.0.0.0/8".parse::<IpNet>().unwrap()])
+            .http_layers();
+
+        assert!(default.is_empty());
+        assert_eq!(
+            layers.client_allowlist,
+            Some(ClientAllowlist(vec!["10.0.0.0/8".parse().unwrap()]))
+        );
+        assert!(!layers.is_empty());
+    }
+
+    #[test]
+    fn http_layers_forward_the_shutdown_timeout() {
+        let layers = ServerBuilder::new()
+            .with_shutdown_timeout(Duration::from_secs(5))
+            .http_layers();
+
+        assert_eq!(layers.shutdown_timeout, Some(Duration::from_secs(5)));
+        assert!(!layers.is_empty());
+    }
+
+    #[test]
+    fn http_options_observe_messages_when_wire_trace_is_enabled() {
+        let default =
+            ServerBuilder::new().http_options("".to_owned(), 8080, TransportOptions::default());
+        let traced = ServerBuilder::new()
+            .with_wire_trace(io::sink())
+            .http_options("".to_owned(), 8080, TransportOptions::default());
+
+        assert!(default.message_observer.is_none());
+        assert!(traced.message_observer.is_some());
+    }
+
+    #[test]
+    fn http_options_forward_the_http_mode() {
+        let options = ServerBuilder::new()
+            .with_http_mode(HttpMode::StreamableHttp)
+            .http_options("localhost".to_owned(), 8080, TransportOptions::default());
+
+        assert_eq!(options.host, "localhost");
+        assert!(!options.sse_support);
+        assert_eq!(options.enable_json_response, Some(true));
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs `f` with a subscriber that captures the logs, and returns its result with the
+    /// captured logs.
+    fn capture_logs<R>(f: impl FnOnce() -> R) -> (R, String) {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+
+        let result = tracing::subscriber::with_default(subscriber, f);
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        (result, output)
+    }
+
+    #[test]
+    fn unbalanced_code_fences_in_instructions_log_a_warning() {
+        let (details, output) = capture_logs(|| {
+            ServerBuilder::new()
+                .with_instructions("Example:\n```json\n{}")
+                .with_validate_instructions(true)
+                .get_server_details::<TestTools>()
+        });
+
+        assert!(output.contains("WARN"), "{output}");
+        assert!(
+            output.contains("instructions contain malformed markdown"),
+            "{output}"
+        );
+        assert!(output.contains("code fence is never closed"), "{output}");
+        assert_eq!(
+            details.instructions.as_deref(),
+            Some("Example:\n```json\n{}")
+        );
+    }
+
+    #[test]
+    fn description_overrides_of_unknown_tools_log_a_warning() {
+        let (_, output) = capture_logs(|| {
+            ServerBuilder::new()
+                .with_tool_description_override("echo_tool", "Répète le message")
+                .with_tool_description_override("missing_tool", "Outil inconnu")
+                .get_server_details::<TestTools>()
+        });
+
+        assert!(output.contains("WARN"), "{output}");
+        assert!(
+            output.contains("ignoring the description override of an unknown tool"),
+            "{output}"
+        );
+        assert!(output.contains("tool=missing_tool"), "{output}");
+        assert!(!output.contains("tool=echo_tool"), "{output}");
+    }
+
+    #[test]
+    fn tools_are_listed_with_the_descriptions_of_the_negotiated_locale() {
+        let builder = ServerBuilder::new()
+            .with_tool_descriptions(
+                "fr",
+                HashMap::from([("echo_tool".to_owned(), "Répète le message".to_owned())]),
+            )
+            .with_tool_descriptions(
+                "de",
+                HashMap::from([(
+                    "echo_tool".to_owned(),
+                    "Wiederholt die Nachricht".to_owned(),
+                )]),
+            );
+        let description = |builder: &ServerBuilder, client_locale: Option<&str>| {
+            Handler::<TestTools>::new(builder.config.clone(), TransportKind::default())
+                .list_localized_tools(client_locale)
+                .into_iter()
+                .find(|tool| tool.name == "echo_tool")
+                .and_then(|tool| tool.description)
+                .unwrap()
+        };
+
+        assert_eq!(
+            description(&builder, None),
+            "A tool that echoes its message"
+        );
+
+        let builder = builder.with_locale("fr-CA");
+        assert_eq!(description(&builder, None), "Répète le message");
+
+        let params: InitializeRequestParams = serde_json::from_value(serde_json::json!({
+            "_meta": { "locale": "es, de-AT;q=0.9, fr;q=0.5" },
+            "capabilities": {},
+            "clientInfo": { "name": "test-client", "version": "1.0.0" },
+            "protocolVersion": LATEST_PROTOCOL_VERSION,
+        }))
+        .unwrap();
+        assert_eq!(
+            description(&builder, client_locale(&params)),
+            "Wiederholt die Nachricht"
+        );
+        assert_eq!(
+            description(&builder, Some("es")),
+            "Répète le message",
+            "the server locale applies when no preference of the client is available"
+        );
+    }
+
+    #[test]
+    fn log_client_initialize_logs_client_details() {
+        let params: InitializeRequestParams = serde_json::from_value(serde_json::json!({
+            "capabilities": { "roots": { "listChanged": true } },
+            "clientInfo": { "name": "test-client", "version": "2.0.0" },
+            "protocolVersion": LATEST_PROTOCOL_VERSION,
+        }))
+        .unwrap();
+
+        let ((), output) = capture_logs(|| log_client_initialize(&params));
 
-        custom_tool.get_tool().call().await
+        assert!(output.contains("INFO"));
+        assert!(output.contains("client initialized"));
+        assert!(output.contains("client_name=test-client"));
+        assert!(output.contains("client_version=2.0.0"));
+        assert!(output.contains(r#"capabilities={"roots":{"listChanged":true}}"#));
     }
 }
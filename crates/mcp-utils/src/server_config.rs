@@ -1,4 +1,64 @@
-use std::time::Duration;
+use std::{collections::HashMap, fmt, path::PathBuf, sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use ipnet::IpNet;
+use rust_mcp_actix::ActixServerOptions;
+use rust_mcp_sdk::schema::{CallToolRequestParams, CallToolResult, schema_utils::CallToolError};
+
+use crate::{
+    access_log::AccessLogFormat, completion::CompletionFn, field_limit::FieldLimits,
+    http_mode::HttpMode, rate_limit::RateLimit, result_limit::OversizedResult, tool_box::ToolSort,
+    wire_trace::WireTrace,
+};
+
+pub(crate) type InitError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Clone)]
+pub(crate) struct InitHook(
+    pub(crate) Arc<dyn Fn() -> BoxFuture<'static, Result<(), InitError>> + Send + Sync>,
+);
+
+impl fmt::Debug for InitHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("InitHook")
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ActixOptionsHook(pub(crate) Arc<dyn Fn(&mut ActixServerOptions) + Send + Sync>);
+
+impl fmt::Debug for ActixOptionsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ActixOptionsHook")
+    }
+}
+
+type FallbackFn = dyn Fn(CallToolRequestParams) -> BoxFuture<'static, Result<CallToolResult, CallToolError>>
+    + Send
+    + Sync;
+
+#[derive(Clone)]
+pub(crate) struct FallbackTool(pub(crate) Arc<FallbackFn>);
+
+impl fmt::Debug for FallbackTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FallbackTool")
+    }
+}
+
+/// Reads the environment variables referenced by the title and the instructions when
+/// environment expansion is enabled. Replaced in tests, since they can't safely set process
+/// environment variables.
+type EnvLookupFn = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct EnvLookup(pub(crate) Arc<EnvLookupFn>);
+
+impl fmt::Debug for EnvLookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EnvLookup")
+    }
+}
 
 #[derive(Debug, Clone)]
 pub(crate) struct ServerConfig {
@@ -8,6 +68,58 @@ pub(crate) struct ServerConfig {
     pub(crate) version: String,
     pub(crate) instructions: String,
     pub(crate) timeout: Duration,
+    pub(crate) catch_panics: bool,
+    pub(crate) default_host: String,
+    pub(crate) default_port: u16,
+    pub(crate) log_initialize: bool,
+    pub(crate) tool_prefix: String,
+    pub(crate) max_result_bytes: Option<usize>,
+    pub(crate) oversized_result: OversizedResult,
+    pub(crate) require_external_optin: bool,
+    pub(crate) init: Option<InitHook>,
+    pub(crate) compression: bool,
+    pub(crate) tcp_backlog: Option<u32>,
+    pub(crate) tcp_nodelay: Option<bool>,
+    pub(crate) shutdown_timeout: Option<Duration>,
+    pub(crate) fallback_tool: Option<FallbackTool>,
+    pub(crate) tool_rate_limits: HashMap<String, RateLimit>,
+    pub(crate) argument_defaults: HashMap<String, serde_json::Map<String, serde_json::Value>>,
+    pub(crate) tool_descriptions: HashMap<String, String>,
+    pub(crate) localized_tool_descriptions: HashMap<String, HashMap<String, String>>,
+    pub(crate) locale: Option<String>,
+    pub(crate) max_call_duration: Option<Duration>,
+    pub(crate) treat_pipe_close_as_clean: bool,
+    pub(crate) timing_meta: bool,
+    pub(crate) http_mode: HttpMode,
+    pub(crate) access_log: Option<AccessLogFormat>,
+    pub(crate) case_insensitive_tools: bool,
+    pub(crate) lenient_argument_coercion: bool,
+    pub(crate) tools_disabled: bool,
+    pub(crate) field_limits: Option<FieldLimits>,
+    pub(crate) sse_keepalive: Option<Duration>,
+    pub(crate) sse_buffer_size: Option<usize>,
+    pub(crate) actix_options: Option<ActixOptionsHook>,
+    pub(crate) completions: Option<CompletionFn>,
+    pub(crate) warmup_tool: Option<String>,
+    pub(crate) warmup_strict: bool,
+    pub(crate) tool_sort: ToolSort,
+    pub(crate) env_expansion: bool,
+    pub(crate) env_lookup: EnvLookup,
+    pub(crate) skip_invalid_tools: bool,
+    pub(crate) enforce_tool_name_rules: bool,
+    pub(crate) list_aliases: bool,
+    pub(crate) cors_origins: Vec<String>,
+    pub(crate) client_allowlist: Vec<IpNet>,
+    pub(crate) audit_log: Option<PathBuf>,
+    pub(crate) audit_log_redacted_fields: Vec<String>,
+    pub(crate) session_ttl: Duration,
+    pub(crate) idempotency_ttl: Option<Duration>,
+    pub(crate) session_resumption: bool,
+    pub(crate) landing_page: bool,
+    pub(crate) wire_trace: Option<WireTrace>,
+    pub(crate) validate_instructions: bool,
+    pub(crate) sanitize_name: bool,
+    pub(crate) structured_text_fallback: bool,
 }
 
 impl Default for ServerConfig {
@@ -19,6 +131,58 @@ impl Default for ServerConfig {
             version: env!("CARGO_PKG_VERSION").to_string(),
             instructions: "".to_string(),
             timeout: Duration::from_secs(60),
+            catch_panics: false,
+            default_host: "127.0.0.1".to_string(),
+            default_port: 8080,
+            log_initialize: false,
+            tool_prefix: "".to_string(),
+            max_result_bytes: None,
+            oversized_result: OversizedResult::Reject,
+            require_external_optin: false,
+            init: None,
+            compression: false,
+            tcp_backlog: None,
+            tcp_nodelay: None,
+            shutdown_timeout: None,
+            fallback_tool: None,
+            tool_rate_limits: HashMap::new(),
+            argument_defaults: HashMap::new(),
+            tool_descriptions: HashMap::new(),
+            localized_tool_descriptions: HashMap::new(),
+            locale: None,
+            max_call_duration: None,
+            treat_pipe_close_as_clean: true,
+            timing_meta: false,
+            http_mode: HttpMode::Sse,
+            access_log: None,
+            case_insensitive_tools: false,
+            lenient_argument_coercion: false,
+            tools_disabled: false,
+            field_limits: None,
+            sse_keepalive: None,
+            sse_buffer_size: None,
+            actix_options: None,
+            completions: None,
+            warmup_tool: None,
+            warmup_strict: false,
+            tool_sort: ToolSort::Declaration,
+            env_expansion: false,
+            env_lookup: EnvLookup(Arc::new(|name| std::env::var(name).ok())),
+            skip_invalid_tools: false,
+            enforce_tool_name_rules: false,
+            list_aliases: false,
+            cors_origins: Vec::new(),
+            client_allowlist: Vec::new(),
+            audit_log: None,
+            audit_log_redacted_fields: Vec::new(),
+            session_ttl: Duration::from_secs(30 * 60),
+            idempotency_ttl: None,
+            session_resumption: false,
+            landing_page: false,
+            wire_trace: None,
+            validate_instructions: false,
+            sanitize_name: false,
+            structured_text_fallback: true,
         }
     }
 }
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Values shared by the tool calls of an HTTP session, available with
+/// [`ToolContext::session`](crate::tool_prelude::ToolContext::session).
+///
+/// Clients that reconnect with the same session identifier find the values stored by their
+/// previous calls, until the session stays unused for longer than the duration set with
+/// [`ServerBuilder::with_session_ttl`](crate::server_prelude::ServerBuilder::with_session_ttl).
+///
+/// ```rust
+/// use mcp_utils::tool_prelude::*;
+///
+/// #[mcp_tool(name = "count_calls", description = "Counts the calls of the session")]
+/// #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// pub struct CountCalls {}
+///
+/// impl TextTool for CountCalls {
+///     type Output = String;
+///
+///     fn call(&self) -> Self::Output {
+///         let Some(session) = ToolContext::current().and_then(|context| context.session().cloned())
+///         else {
+///             return "no session".to_owned();
+///         };
+///
+///         let count = session.get("count").and_then(|count| count.as_u64()).unwrap_or(0) + 1;
+///         session.insert("count", count);
+///         count.to_string()
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SessionState {
+    values: Arc<Mutex<serde_json::Map<String, serde_json::Value>>>,
+}
+
+impl SessionState {
+    /// Returns a copy of the value stored under the key.
+    pub fn get(&self, key: &str) -> Option<serde_json::Value> {
+        self.lock().get(key).cloned()
+    }
+
+    /// Stores a value under the key, returning the value it replaces.
+    pub fn insert(
+        &self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Option<serde_json::Value> {
+        self.lock().insert(key.into(), value.into())
+    }
+
+    /// Removes the value stored under the key.
+    pub fn remove(&self, key: &str) -> Option<serde_json::Value> {
+        self.lock().remove(key)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, serde_json::Map<String, serde_json::Value>> {
+        self.values
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[derive(Debug)]
+struct SessionEntry {
+    state: SessionState,
+    last_used: Instant,
+}
+
+/// The state of each session, dropped once a session stays unused for longer than the TTL.
+#[derive(Debug)]
+pub(crate) struct SessionStates {
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, SessionEntry>>,
+}
+
+impl SessionStates {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the state of the session, created empty when the session is new or expired.
+    pub(crate) fn get(&self, session_id: &str) -> SessionState {
+        self.get_at(session_id, Instant::now())
+    }
+
+    fn get_at(&self, session_id: &str, now: Instant) -> SessionState {
+        let mut sessions = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        sessions.retain(|_, entry| now.saturating_duration_since(entry.last_used) <= self.ttl);
+
+        let entry = sessions
+            .entry(session_id.to_owned())
+            .or_insert_with(|| SessionEntry {
+                state: SessionState::default(),
+                last_used: now,
+            });
+        entry.last_used = now;
+
+        entry.state.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_with_the_same_id_share_their_state() {
+        let sessions = SessionStates::new(Duration::from_secs(60));
+
+        sessions.get("first").insert("answer", 42);
+
+        assert_eq!(sessions.get("first").get("answer"), Some(42.into()));
+        assert_eq!(sessions.get("second").get("answer"), None);
+    }
+
+    #[test]
+    fn unused_sessions_expire_after_the_ttl() {
+        let sessions = SessionStates::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        sessions.get_at("first", start).insert("answer", 42);
+
+        let state = sessions.get_at("first", start + Duration::from_secs(30));
+        assert_eq!(state.get("answer"), Some(42.into()));
+
+        let state = sessions.get_at("first", start + Duration::from_secs(120));
+        assert_eq!(state.get("answer"), None);
+    }
+}
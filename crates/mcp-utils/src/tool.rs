@@ -1,13 +1,27 @@
 use std::fmt;
 
 use async_trait::async_trait;
-use rust_mcp_sdk::schema::{CallToolResult, TextContent, schema_utils::CallToolError};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use rust_mcp_sdk::schema::{
+    Annotations, AudioContent, BlobResourceContents, CallToolResult, ContentBlock,
+    EmbeddedResource, EmbeddedResourceResource, Role, TextContent, TextResourceContents,
+    ToolOutputSchema, schema_utils::CallToolError,
+};
 use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::context::ToolContext;
 
 pub trait TextTool {
     type Output: IntoTextToolResult;
 
     fn call(&self) -> Self::Output;
+
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
+    }
 }
 
 #[async_trait]
@@ -15,10 +29,96 @@ pub trait AsyncTextTool {
     type Output: IntoTextToolResult;
 
     async fn call(&self) -> Self::Output;
+
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
+    }
+
+    /// Calls the tool once for every input and aggregates the outputs into a [`TextBatch`].
+    ///
+    /// Batching is opt-in: the default implementation simply awaits [`AsyncTextTool::call`]
+    /// on each input in order, and stops at the first [fatal](ToolError::fatal) error.
+    /// Override it when the tool can process all inputs at once (for example, with a single
+    /// database query).
+    async fn call_batch(inputs: Vec<Self>) -> TextBatch
+    where
+        Self: Sized + Send + Sync,
+        Self::Output: Send,
+    {
+        let mut batch = TextBatch::new();
+        for input in inputs.iter() {
+            if !batch.push(input.call().await) {
+                break;
+            }
+        }
+        batch
+    }
+}
+
+/// A text tool that produces its output lines through a channel, like a tool tailing logs.
+///
+/// The tool returns the receiving end of a channel, and every line is sent to the client as it
+/// arrives, in a `notifications/progress` message, when the client asks for progress with a
+/// progress token. The lines also become the text blocks of the tool result, in the order they
+/// were sent, which completes when the channel closes, that is when every sender is dropped.
+///
+/// The receiver is owned by the call: it is dropped when the call completes, or earlier when
+/// the call is interrupted (for example by
+/// [`ServerBuilder::with_max_call_duration`](crate::server_prelude::ServerBuilder::with_max_call_duration)).
+/// Producers should stop when sending fails, since the lines can't be delivered anymore.
+///
+/// Channel tools are declared with the `channel_text` kind of
+/// [`setup_tools!`](crate::server_prelude::setup_tools).
+///
+/// ```rust
+/// use async_trait::async_trait;
+/// use mcp_utils::tool_prelude::*;
+/// use tokio::sync::mpsc;
+///
+/// #[mcp_tool(name = "tail", description = "Returns the last lines of the log")]
+/// #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// pub struct TailTool {}
+///
+/// #[async_trait]
+/// impl ChannelTextTool for TailTool {
+///     async fn call(&self) -> Result<mpsc::Receiver<String>, ToolError> {
+///         let (sender, receiver) = mpsc::channel(16);
+///
+///         tokio::spawn(async move {
+///             for line in ["starting", "listening on port 8080"] {
+///                 if sender.send(line.to_owned()).await.is_err() {
+///                     break;
+///                 }
+///             }
+///         });
+///
+///         Ok(receiver)
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait ChannelTextTool {
+    async fn call(&self) -> Result<mpsc::Receiver<String>, ToolError>;
+
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
+    }
 }
 
 pub trait IntoTextToolResult {
     fn result(self) -> Result<String, ToolError>;
+
+    fn text_contents(self) -> Result<Vec<TextContent>, ToolError>
+    where
+        Self: Sized,
+    {
+        self.result()
+            .map(|text| vec![TextContent::new(text, None, None)])
+    }
 }
 
 impl IntoTextToolResult for String {
@@ -49,8 +149,168 @@ where
     }
 }
 
+/// The value is sent as pretty-printed JSON in a text block, without structured content.
+impl IntoTextToolResult for serde_json::Value {
+    fn result(self) -> Result<String, ToolError> {
+        serde_json::to_string_pretty(&self).map_err(ToolError::from)
+    }
+}
+
+/// Each string of the vector produces its own text block in the tool result.
+impl<T> IntoTextToolResult for Vec<T>
+where
+    T: Into<String>,
+{
+    fn result(self) -> Result<String, ToolError> {
+        Ok(self
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+
+    fn text_contents(self) -> Result<Vec<TextContent>, ToolError> {
+        Ok(self
+            .into_iter()
+            .map(|text| TextContent::new(text.into(), None, None))
+            .collect())
+    }
+}
+
+/// Binary data returned by a [`TextTool`] or an [`AsyncTextTool`] as a text block holding its
+/// base64 encoding, using the standard alphabet with padding (RFC 4648), without any prefix.
+///
+/// This is meant for generic binary data that the client passes along without interpreting it,
+/// like a gzipped archive. Typed media is better returned with its MIME type, so that clients
+/// can display it: use an [`AudioTool`] for audio and an [`EmbeddedResourceTool`] with a
+/// [`Resource::blob`] for other binary contents.
+///
+/// ```rust
+/// use mcp_utils::tool_prelude::*;
+///
+/// #[mcp_tool(name = "archive", description = "Returns the gzipped logs, base64-encoded")]
+/// #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// pub struct ArchiveTool {}
+///
+/// impl TextTool for ArchiveTool {
+///     type Output = Result<Base64, std::io::Error>;
+///
+///     fn call(&self) -> Self::Output {
+///         let gzipped: Vec<u8> = vec![0x1f, 0x8b, 0x08];
+///         Ok(Base64::new(gzipped))
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Base64(Vec<u8>);
+
+impl Base64 {
+    pub fn new(data: impl Into<Vec<u8>>) -> Self {
+        Self(data.into())
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Base64> for String {
+    fn from(value: Base64) -> Self {
+        BASE64.encode(value.0)
+    }
+}
+
+impl IntoTextToolResult for Base64 {
+    fn result(self) -> Result<String, ToolError> {
+        Ok(self.into())
+    }
+}
+
+/// The outputs of a batch of text tool calls.
+///
+/// Each successful output produces its own text block in the tool result, and each error
+/// produces a text block starting with `error: `, so one failing item does not fail the
+/// others. A [fatal](ToolError::fatal) error aborts the batch instead: no more outputs are
+/// added and the whole tool call fails with that error.
+#[derive(Debug, Default)]
+pub struct TextBatch {
+    outputs: Vec<Result<Vec<TextContent>, ToolError>>,
+    fatal_error: Option<ToolError>,
+}
+
+impl TextBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the output of one item to the batch. Returns `false` when the batch is aborted
+    /// by a fatal error, in which case the remaining items should not be processed and
+    /// further outputs are ignored.
+    pub fn push(&mut self, output: impl IntoTextToolResult) -> bool {
+        if self.is_aborted() {
+            return false;
+        }
+
+        match output.text_contents() {
+            Err(error) if error.is_fatal() => {
+                self.fatal_error = Some(error);
+                false
+            }
+            output => {
+                self.outputs.push(output);
+                true
+            }
+        }
+    }
+
+    /// Returns `true` when a fatal error was added to the batch.
+    pub fn is_aborted(&self) -> bool {
+        self.fatal_error.is_some()
+    }
+}
+
+impl IntoTextToolResult for TextBatch {
+    fn result(self) -> Result<String, ToolError> {
+        self.text_contents().map(|contents| {
+            contents
+                .into_iter()
+                .map(|content| content.text)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    fn text_contents(self) -> Result<Vec<TextContent>, ToolError> {
+        if let Some(error) = self.fatal_error {
+            return Err(error);
+        }
+
+        Ok(self
+            .outputs
+            .into_iter()
+            .flat_map(|output| {
+                output.unwrap_or_else(|error| {
+                    vec![TextContent::new(format!("error: {error}"), None, None)]
+                })
+            })
+            .collect())
+    }
+}
+
 pub trait IntoStructuredToolResult {
     fn result(self) -> Result<serde_json::Value, ToolError>;
+
+    fn annotated_result(self) -> Result<(serde_json::Value, Option<Annotations>), ToolError>
+    where
+        Self: Sized,
+    {
+        self.result().map(|value| (value, None))
+    }
+
+    /// Takes the `_meta` entries to attach to the tool result, see [`WithMeta`].
+    fn take_meta(&mut self) -> Option<serde_json::Map<String, serde_json::Value>> {
+        None
+    }
 }
 
 impl<T> IntoStructuredToolResult for T
@@ -58,14 +318,36 @@ where
     T: Serialize,
 {
     fn result(self) -> Result<serde_json::Value, ToolError> {
-        serde_json::to_value(self).map_err(|e| ToolError::from(e.to_string()))
+        serde_path_to_error::serialize(&self, serde_json::value::Serializer).map_err(|e| {
+            let location = match e.path().iter().next() {
+                Some(_) => format!(" at `{}`", e.path()),
+                None => String::new(),
+            };
+
+            ToolError::from(format!(
+                "unable to serialize the output of type `{}`{}: {}",
+                std::any::type_name::<T>(),
+                location,
+                e.inner()
+            ))
+        })
     }
 }
 
+/// A tool that returns structured JSON data.
+///
+/// The fields of the output are sent in alphabetical order, unless the `preserve-order`
+/// feature is enabled, which keeps the order in which they are declared.
 pub trait StructuredTool {
     type Output: IntoStructuredToolResult;
 
     fn call(&self) -> Self::Output;
+
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
+    }
 }
 
 #[async_trait]
@@ -73,199 +355,1697 @@ pub trait AsyncStructuredTool {
     type Output: IntoStructuredToolResult;
 
     async fn call(&self) -> Self::Output;
+
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
+    }
 }
 
-#[derive(Debug)]
-pub struct ToolError {
-    display: String,
+/// The output of a structured tool that describes itself with a JSON Schema.
+///
+/// Derive it with `#[derive(ToolOutput)]` on a struct that also derives `JsonSchema`, to use
+/// the schema of the struct as the output schema. The value sent to the client comes from the
+/// `Serialize` implementation.
+///
+/// ```rust
+/// use mcp_utils::tool_prelude::*;
+///
+/// #[derive(Debug, JsonSchema, Serialize, ToolOutput)]
+/// pub struct Forecast {
+///     /// The temperature in degrees Celsius
+///     temperature: f64,
+///     summary: Option<String>,
+/// }
+///
+/// let schema = Forecast::output_schema();
+///
+/// assert_eq!(schema.required, vec!["temperature".to_owned()]);
+/// ```
+///
+/// Structs that don't derive `JsonSchema` are rejected at compile time:
+///
+/// ```rust,compile_fail
+/// use mcp_utils::tool_prelude::*;
+///
+/// #[derive(Debug, Serialize, ToolOutput)]
+/// pub struct Forecast {
+///     temperature: f64,
+/// }
+/// ```
+pub trait ToolOutput {
+    fn output_schema() -> ToolOutputSchema;
 }
 
-impl fmt::Display for ToolError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.display)
-    }
+/// The JSON Schema of a type, implemented by `#[derive(ToolOutput)]` from the function
+/// generated by the `JsonSchema` derive.
+pub trait JsonSchema {
+    fn json_schema() -> serde_json::Map<String, serde_json::Value>;
 }
 
-impl From<String> for ToolError {
-    fn from(value: String) -> Self {
-        Self { display: value }
-    }
+/// Converts the JSON Schema generated by the `JsonSchema` derive into an output schema.
+pub fn output_schema(schema: serde_json::Map<String, serde_json::Value>) -> ToolOutputSchema {
+    let properties = match schema.get("properties") {
+        Some(serde_json::Value::Object(properties)) => Some(
+            properties
+                .iter()
+                .filter_map(|(name, property)| {
+                    property
+                        .as_object()
+                        .map(|property| (name.clone(), property.clone()))
+                })
+                .collect(),
+        ),
+        _ => None,
+    };
+    let required = schema
+        .get("required")
+        .and_then(serde_json::Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(|name| name.as_str().map(str::to_owned))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ToolOutputSchema::new(required, properties, None)
 }
 
-impl From<&str> for ToolError {
-    fn from(value: &str) -> Self {
-        Self {
-            display: value.to_owned(),
-        }
-    }
+/// Wraps a tool output to attach [`Annotations`] to the content sent to the client.
+///
+/// Annotations tell the client who the content is intended for and how important
+/// it is. For example, a tool can mark its output as intended for the assistant only:
+///
+/// ```rust
+/// use mcp_utils::tool_prelude::*;
+///
+/// # #[mcp_tool(name = "example_tool", description = "An example tool")]
+/// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// # pub struct ExampleTool {}
+/// impl TextTool for ExampleTool {
+///     type Output = Annotated<String>;
+///
+///     fn call(&self) -> Self::Output {
+///         Annotated::new("internal notes".to_owned()).with_audience([Role::Assistant])
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Annotated<T> {
+    value: T,
+    annotations: Annotations,
 }
 
-impl From<&String> for ToolError {
-    fn from(value: &String) -> Self {
+impl<T> Annotated<T> {
+    pub fn new(value: T) -> Self {
         Self {
-            display: value.clone(),
+            value,
+            annotations: Annotations::default(),
         }
     }
+
+    pub fn with_audience(mut self, audience: impl IntoIterator<Item = Role>) -> Self {
+        self.annotations.audience = audience.into_iter().collect();
+        self
+    }
+
+    /// Sets the importance of the content, from `0.0` (least important) to `1.0` (most important).
+    pub fn with_priority(mut self, priority: f64) -> Self {
+        self.annotations.priority = Some(priority);
+        self
+    }
+
+    /// Sets when the content was last modified, as an ISO 8601 formatted string.
+    pub fn with_last_modified(mut self, last_modified: impl Into<String>) -> Self {
+        self.annotations.last_modified = Some(last_modified.into());
+        self
+    }
 }
 
-impl std::error::Error for ToolError {}
+impl<T> IntoTextToolResult for Annotated<T>
+where
+    T: IntoTextToolResult,
+{
+    fn result(self) -> Result<String, ToolError> {
+        self.value.result()
+    }
 
-#[async_trait]
-trait CustomTextTool {
-    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+    fn text_contents(self) -> Result<Vec<TextContent>, ToolError> {
+        let annotations = self.annotations;
+
+        self.value.text_contents().map(|contents| {
+            contents
+                .into_iter()
+                .map(|mut content| {
+                    content.annotations = Some(annotations.clone());
+                    content
+                })
+                .collect()
+        })
+    }
 }
 
-#[async_trait]
-trait CustomStructuredTool {
-    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+impl<T> IntoStructuredToolResult for Annotated<T>
+where
+    T: IntoStructuredToolResult,
+{
+    fn result(self) -> Result<serde_json::Value, ToolError> {
+        self.value.result()
+    }
+
+    fn annotated_result(self) -> Result<(serde_json::Value, Option<Annotations>), ToolError> {
+        self.value
+            .result()
+            .map(|value| (value, Some(self.annotations)))
+    }
+
+    fn take_meta(&mut self) -> Option<serde_json::Map<String, serde_json::Value>> {
+        self.value.take_meta()
+    }
 }
 
-#[async_trait]
-trait AsyncCustomTextTool {
-    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+/// Wraps a structured tool output to attach `_meta` entries to the tool result.
+///
+/// Meta entries pass out-of-band hints to the client, outside of the content read by the model:
+///
+/// ```rust
+/// use mcp_utils::tool_prelude::*;
+///
+/// # #[mcp_tool(name = "example_tool", description = "An example tool")]
+/// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// # pub struct ExampleTool {}
+/// impl StructuredTool for ExampleTool {
+///     type Output = WithMeta<Vec<u32>>;
+///
+///     fn call(&self) -> Self::Output {
+///         WithMeta::new(vec![1, 2, 3]).with_entry("cache_hit", true)
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithMeta<T> {
+    value: T,
+    meta: serde_json::Map<String, serde_json::Value>,
 }
 
-#[async_trait]
-trait AsyncCustomStructuredTool {
-    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+impl<T> WithMeta<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            meta: serde_json::Map::new(),
+        }
+    }
+
+    pub fn with_entry(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.meta.insert(key.into(), value.into());
+        self
+    }
 }
 
-#[async_trait]
-impl<T, O> CustomTextTool for T
+impl<T> IntoStructuredToolResult for WithMeta<T>
 where
-    T: TextTool<Output = O> + Send + Sync,
-    O: IntoTextToolResult,
+    T: IntoStructuredToolResult,
 {
-    async fn call(&self) -> Result<CallToolResult, CallToolError> {
-        let result = TextTool::call(self).result().map_err(CallToolError::new)?;
-        Ok(CallToolResult::text_content(vec![TextContent::new(
-            result, None, None,
-        )]))
+    fn result(self) -> Result<serde_json::Value, ToolError> {
+        self.value.result()
+    }
+
+    fn annotated_result(self) -> Result<(serde_json::Value, Option<Annotations>), ToolError> {
+        self.value.annotated_result()
+    }
+
+    fn take_meta(&mut self) -> Option<serde_json::Map<String, serde_json::Value>> {
+        let mut meta = self.value.take_meta().unwrap_or_default();
+        meta.append(&mut self.meta);
+        Some(meta)
     }
 }
 
-#[async_trait]
-impl<T, O> AsyncCustomTextTool for T
-where
-    T: AsyncTextTool<Output = O> + Send + Sync,
-    O: IntoTextToolResult,
-{
-    async fn call(&self) -> Result<CallToolResult, CallToolError> {
-        let result = AsyncTextTool::call(self)
-            .await
-            .result()
-            .map_err(CallToolError::new)?;
-        Ok(CallToolResult::text_content(vec![TextContent::new(
-            result, None, None,
-        )]))
+pub trait AudioTool {
+    type Output: IntoAudioToolResult;
+
+    fn call(&self) -> Self::Output;
+
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
     }
 }
 
 #[async_trait]
-impl<T> CustomStructuredTool for T
-where
-    T: StructuredTool + Send + Sync,
-    T::Output: IntoStructuredToolResult,
-{
-    async fn call(&self) -> Result<CallToolResult, CallToolError> {
-        let value = StructuredTool::call(self)
-            .result()
-            .map_err(CallToolError::new)?;
+pub trait AsyncAudioTool {
+    type Output: IntoAudioToolResult;
 
-        build_tool_result(value)
+    async fn call(&self) -> Self::Output;
+
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
     }
 }
 
-#[async_trait]
-impl<T> AsyncCustomStructuredTool for T
+/// Raw audio data returned by an [`AudioTool`] or an [`AsyncAudioTool`].
+///
+/// The data is base64-encoded when it is sent to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Audio {
+    data: Vec<u8>,
+    mime_type: String,
+}
+
+impl Audio {
+    pub fn new(data: impl Into<Vec<u8>>, mime_type: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            mime_type: mime_type.into(),
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+}
+
+pub trait IntoAudioToolResult {
+    fn result(self) -> Result<Audio, ToolError>;
+}
+
+impl IntoAudioToolResult for Audio {
+    fn result(self) -> Result<Audio, ToolError> {
+        Ok(self)
+    }
+}
+
+impl<T, E> IntoAudioToolResult for Result<T, E>
 where
-    T: AsyncStructuredTool + Send + Sync,
-    T::Output: IntoStructuredToolResult,
+    T: Into<Audio>,
+    E: Into<ToolError>,
 {
-    async fn call(&self) -> Result<CallToolResult, CallToolError> {
-        let value = AsyncStructuredTool::call(self)
-            .await
-            .result()
-            .map_err(CallToolError::new)?;
+    fn result(self) -> Result<Audio, ToolError> {
+        self.map(|value| value.into()).map_err(|err| err.into())
+    }
+}
+
+pub trait EmbeddedResourceTool {
+    type Output: IntoResourceToolResult;
+
+    fn call(&self) -> Self::Output;
 
-        build_tool_result(value)
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
     }
 }
 
-fn build_tool_result(value: serde_json::Value) -> Result<CallToolResult, CallToolError> {
-    let text_representation = serde_json::to_string(&value).map_err(CallToolError::new)?;
+#[async_trait]
+pub trait AsyncEmbeddedResourceTool {
+    type Output: IntoResourceToolResult;
 
-    Ok(
-        CallToolResult::text_content(vec![TextContent::new(text_representation, None, None)])
-            .with_structured_content(match value {
-                serde_json::Value::Object(map) => map,
-                value => {
-                    let mut map = serde_json::Map::new();
-                    map.insert("result".to_string(), value);
-                    map
-                }
-            }),
-    )
+    async fn call(&self) -> Self::Output;
+
+    /// The error code attached to the errors returned by this tool that have no code, see
+    /// [`ToolError::with_code`].
+    fn error_code(&self) -> Option<i64> {
+        None
+    }
 }
 
-enum CustomToolInner<'a> {
-    Text(&'a (dyn CustomTextTool + Send + Sync)),
-    Structured(&'a (dyn CustomStructuredTool + Send + Sync)),
-    AsyncText(&'a (dyn AsyncCustomTextTool + Send + Sync)),
-    AsyncStructured(&'a (dyn AsyncCustomStructuredTool + Send + Sync)),
+/// A resource returned with its contents by an [`EmbeddedResourceTool`] or an
+/// [`AsyncEmbeddedResourceTool`].
+///
+/// Binary contents are base64-encoded when they are sent to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    uri: String,
+    mime_type: Option<String>,
+    contents: ResourceContents,
 }
 
-pub struct CustomTool<'a> {
-    inner: CustomToolInner<'a>,
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ResourceContents {
+    Text(String),
+    Blob(Vec<u8>),
 }
 
-impl<'a> CustomTool<'a> {
-    pub fn text<T, O>(tool: &'a T) -> Self
-    where
-        T: TextTool<Output = O> + Send + Sync,
-        O: IntoTextToolResult,
-    {
+impl Resource {
+    pub fn text(uri: impl Into<String>, text: impl Into<String>) -> Self {
         Self {
-            inner: CustomToolInner::Text(tool),
+            uri: uri.into(),
+            mime_type: None,
+            contents: ResourceContents::Text(text.into()),
         }
     }
 
-    pub fn structured<T>(tool: &'a T) -> Self
-    where
-        T: StructuredTool + Send + Sync,
-        T::Output: IntoStructuredToolResult,
-    {
+    pub fn blob(uri: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
         Self {
-            inner: CustomToolInner::Structured(tool),
+            uri: uri.into(),
+            mime_type: None,
+            contents: ResourceContents::Blob(data.into()),
         }
     }
 
-    pub fn async_text<T, O>(tool: &'a T) -> Self
-    where
-        T: AsyncTextTool<Output = O> + Send + Sync,
-        O: IntoTextToolResult,
-    {
-        Self {
-            inner: CustomToolInner::AsyncText(tool),
-        }
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
     }
 
-    pub fn async_structured<T>(tool: &'a T) -> Self
-    where
-        T: AsyncStructuredTool + Send + Sync,
-        T::Output: IntoStructuredToolResult,
-    {
-        Self {
-            inner: CustomToolInner::AsyncStructured(tool),
-        }
+    pub fn uri(&self) -> &str {
+        &self.uri
     }
 
-    pub async fn call(&self) -> Result<CallToolResult, CallToolError> {
-        match self.inner {
-            CustomToolInner::Text(tool) => tool.call().await,
-            CustomToolInner::Structured(tool) => tool.call().await,
-            CustomToolInner::AsyncText(tool) => tool.call().await,
-            CustomToolInner::AsyncStructured(tool) => tool.call().await,
-        }
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+}
+
+pub trait IntoResourceToolResult {
+    fn result(self) -> Result<Resource, ToolError>;
+}
+
+impl IntoResourceToolResult for Resource {
+    fn result(self) -> Result<Resource, ToolError> {
+        Ok(self)
+    }
+}
+
+impl<T, E> IntoResourceToolResult for Result<T, E>
+where
+    T: Into<Resource>,
+    E: Into<ToolError>,
+{
+    fn result(self) -> Result<Resource, ToolError> {
+        self.map(|value| value.into()).map_err(|err| err.into())
+    }
+}
+
+#[derive(Debug)]
+pub struct ToolError {
+    display: String,
+    code: Option<i64>,
+    fatal: bool,
+    content: Vec<ContentBlock>,
+}
+
+impl ToolError {
+    /// Attaches an error code to the error.
+    ///
+    /// MCP reports tool failures in the tool result (with `isError` set) rather than as
+    /// JSON-RPC errors, so the code is sent in the `_meta` field of the result under the
+    /// `error_code` key. The codes from -32768 to -32000 are reserved by JSON-RPC for
+    /// protocol errors and should not be used for domain errors.
+    pub fn with_code(mut self, code: i64) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn code(&self) -> Option<i64> {
+        self.code
+    }
+
+    /// Marks the error as fatal for a [`TextBatch`]: the batch stops processing the remaining
+    /// items and the whole tool call fails with this error. Errors that are not fatal are
+    /// reported for their own item only. Outside of batches, all errors fail the tool call.
+    pub fn fatal(mut self) -> Self {
+        self.fatal = true;
+        self
+    }
+
+    pub fn is_fatal(&self) -> bool {
+        self.fatal
+    }
+
+    /// Adds a content block to the error result sent to the client, after the text block
+    /// holding the error message. This is useful to give details about the failure, like
+    /// diagnostics or the partial output produced before the error.
+    ///
+    /// ```rust
+    /// use mcp_utils::tool_prelude::*;
+    ///
+    /// let error = ToolError::from("the query is invalid")
+    ///     .with_content(ContentBlock::text_content("unknown column `nmae`".to_owned()));
+    /// ```
+    pub fn with_content(mut self, content: impl Into<ContentBlock>) -> Self {
+        self.content.push(content.into());
+        self
+    }
+
+    pub fn content(&self) -> &[ContentBlock] {
+        &self.content
+    }
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display)
+    }
+}
+
+impl From<String> for ToolError {
+    fn from(value: String) -> Self {
+        Self {
+            display: value,
+            code: None,
+            fatal: false,
+            content: Vec::new(),
+        }
+    }
+}
+
+impl From<&str> for ToolError {
+    fn from(value: &str) -> Self {
+        Self {
+            display: value.to_owned(),
+            code: None,
+            fatal: false,
+            content: Vec::new(),
+        }
+    }
+}
+
+impl From<&String> for ToolError {
+    fn from(value: &String) -> Self {
+        Self {
+            display: value.clone(),
+            code: None,
+            fatal: false,
+            content: Vec::new(),
+        }
+    }
+}
+
+impl From<std::io::Error> for ToolError {
+    fn from(value: std::io::Error) -> Self {
+        Self {
+            display: value.to_string(),
+            code: None,
+            fatal: false,
+            content: Vec::new(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ToolError {
+    fn from(value: serde_json::Error) -> Self {
+        Self {
+            display: value.to_string(),
+            code: None,
+            fatal: false,
+            content: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl From<anyhow::Error> for ToolError {
+    fn from(value: anyhow::Error) -> Self {
+        // the alternate format includes the chain of context messages
+        Self {
+            display: format!("{value:#}"),
+            code: None,
+            fatal: false,
+            content: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for ToolError {
+    fn from(value: reqwest::Error) -> Self {
+        // the message of a request error rarely says why it failed, the cause is in its sources
+        let mut display = value.to_string();
+        let mut source = std::error::Error::source(&value);
+        while let Some(error) = source {
+            display.push_str(": ");
+            display.push_str(&error.to_string());
+            source = error.source();
+        }
+
+        Self {
+            display,
+            code: None,
+            fatal: false,
+            content: Vec::new(),
+        }
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+fn call_tool_error(error: ToolError, default_code: Option<i64>) -> CallToolError {
+    match default_code {
+        Some(code) if error.code.is_none() => CallToolError::new(error.with_code(code)),
+        _ => CallToolError::new(error),
+    }
+}
+
+#[async_trait]
+trait CustomTextTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait CustomStructuredTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait BlockingCustomStructuredTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait AsyncCustomTextTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait ChannelCustomTextTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait AsyncCustomStructuredTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait CustomAudioTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait AsyncCustomAudioTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait CustomEmbeddedResourceTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+trait AsyncCustomEmbeddedResourceTool {
+    async fn call(&self) -> Result<CallToolResult, CallToolError>;
+}
+
+#[async_trait]
+impl<T, O> CustomTextTool for T
+where
+    T: TextTool<Output = O> + Send + Sync,
+    O: IntoTextToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let contents = TextTool::call(self)
+            .text_contents()
+            .map_err(|err| call_tool_error(err, TextTool::error_code(self)))?;
+        Ok(CallToolResult::text_content(contents))
+    }
+}
+
+#[async_trait]
+impl<T, O> AsyncCustomTextTool for T
+where
+    T: AsyncTextTool<Output = O> + Send + Sync,
+    O: IntoTextToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let contents = AsyncTextTool::call(self)
+            .await
+            .text_contents()
+            .map_err(|err| call_tool_error(err, AsyncTextTool::error_code(self)))?;
+        Ok(CallToolResult::text_content(contents))
+    }
+}
+
+#[async_trait]
+impl<T> ChannelCustomTextTool for T
+where
+    T: ChannelTextTool + Send + Sync,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let mut receiver = ChannelTextTool::call(self)
+            .await
+            .map_err(|err| call_tool_error(err, ChannelTextTool::error_code(self)))?;
+        let context = ToolContext::current();
+
+        let mut lines = Vec::new();
+        while let Some(line) = receiver.recv().await {
+            if let Some(context) = &context {
+                let progress = (lines.len() + 1) as f64;
+                context
+                    .notify_progress(progress, None, Some(line.clone()))
+                    .await;
+            }
+            lines.push(line);
+        }
+
+        let contents = lines
+            .text_contents()
+            .map_err(|err| call_tool_error(err, ChannelTextTool::error_code(self)))?;
+        Ok(CallToolResult::text_content(contents))
+    }
+}
+
+#[async_trait]
+impl<T> CustomStructuredTool for T
+where
+    T: StructuredTool + Send + Sync,
+    T::Output: IntoStructuredToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let mut output = StructuredTool::call(self);
+        let meta = output.take_meta();
+        let (value, annotations) = output
+            .annotated_result()
+            .map_err(|err| call_tool_error(err, StructuredTool::error_code(self)))?;
+
+        build_tool_result(value, annotations, meta)
+    }
+}
+
+#[async_trait]
+impl<T> BlockingCustomStructuredTool for T
+where
+    T: StructuredTool + Clone + Send + Sync + 'static,
+    T::Output: IntoStructuredToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let tool = self.clone();
+        // the task-local context does not follow the call to the blocking thread
+        let context = ToolContext::current();
+        let result = tokio::task::spawn_blocking(move || {
+            let call = || {
+                let mut output = StructuredTool::call(&tool);
+                let meta = output.take_meta();
+                output
+                    .annotated_result()
+                    .map(|(value, annotations)| (value, annotations, meta))
+            };
+
+            match context {
+                Some(context) => context.sync_scope(call),
+                None => call(),
+            }
+        })
+        .await;
+
+        let (value, annotations, meta) = match result {
+            Ok(result) => result,
+            // resume the panic so that it is handled like the panics of other tools
+            Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+            Err(error) => Err(ToolError::from(error.to_string())),
+        }
+        .map_err(|err| call_tool_error(err, StructuredTool::error_code(self)))?;
+
+        build_tool_result(value, annotations, meta)
+    }
+}
+
+#[async_trait]
+impl<T> AsyncCustomStructuredTool for T
+where
+    T: AsyncStructuredTool + Send + Sync,
+    T::Output: IntoStructuredToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let mut output = AsyncStructuredTool::call(self).await;
+        let meta = output.take_meta();
+        let (value, annotations) = output
+            .annotated_result()
+            .map_err(|err| call_tool_error(err, AsyncStructuredTool::error_code(self)))?;
+
+        build_tool_result(value, annotations, meta)
+    }
+}
+
+#[async_trait]
+impl<T> CustomAudioTool for T
+where
+    T: AudioTool + Send + Sync,
+    T::Output: IntoAudioToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let audio = AudioTool::call(self)
+            .result()
+            .map_err(|err| call_tool_error(err, AudioTool::error_code(self)))?;
+
+        Ok(build_audio_result(audio))
+    }
+}
+
+#[async_trait]
+impl<T> AsyncCustomAudioTool for T
+where
+    T: AsyncAudioTool + Send + Sync,
+    T::Output: IntoAudioToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let audio = AsyncAudioTool::call(self)
+            .await
+            .result()
+            .map_err(|err| call_tool_error(err, AsyncAudioTool::error_code(self)))?;
+
+        Ok(build_audio_result(audio))
+    }
+}
+
+fn build_audio_result(audio: Audio) -> CallToolResult {
+    CallToolResult::audio_content(vec![AudioContent::new(
+        BASE64.encode(audio.data),
+        audio.mime_type,
+        None,
+        None,
+    )])
+}
+
+#[async_trait]
+impl<T> CustomEmbeddedResourceTool for T
+where
+    T: EmbeddedResourceTool + Send + Sync,
+    T::Output: IntoResourceToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let resource = EmbeddedResourceTool::call(self)
+            .result()
+            .map_err(|err| call_tool_error(err, EmbeddedResourceTool::error_code(self)))?;
+
+        Ok(build_resource_result(resource))
+    }
+}
+
+#[async_trait]
+impl<T> AsyncCustomEmbeddedResourceTool for T
+where
+    T: AsyncEmbeddedResourceTool + Send + Sync,
+    T::Output: IntoResourceToolResult,
+{
+    async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        let resource = AsyncEmbeddedResourceTool::call(self)
+            .await
+            .result()
+            .map_err(|err| call_tool_error(err, AsyncEmbeddedResourceTool::error_code(self)))?;
+
+        Ok(build_resource_result(resource))
+    }
+}
+
+fn build_resource_result(resource: Resource) -> CallToolResult {
+    let contents: EmbeddedResourceResource = match resource.contents {
+        ResourceContents::Text(text) => TextResourceContents {
+            meta: None,
+            mime_type: resource.mime_type,
+            text,
+            uri: resource.uri,
+        }
+        .into(),
+        ResourceContents::Blob(data) => BlobResourceContents {
+            blob: BASE64.encode(data),
+            meta: None,
+            mime_type: resource.mime_type,
+            uri: resource.uri,
+        }
+        .into(),
+    };
+
+    CallToolResult::embedded_resource(vec![EmbeddedResource::new(contents, None, None)])
+}
+
+fn build_tool_result(
+    value: serde_json::Value,
+    annotations: Option<Annotations>,
+    meta: Option<serde_json::Map<String, serde_json::Value>>,
+) -> Result<CallToolResult, CallToolError> {
+    let text_representation = serde_json::to_string_pretty(&value).map_err(CallToolError::new)?;
+
+    let mut result = CallToolResult::text_content(vec![TextContent::new(
+        text_representation,
+        annotations,
+        None,
+    )])
+    .with_structured_content(match value {
+        serde_json::Value::Object(map) => map,
+        value => {
+            let mut map = serde_json::Map::new();
+            map.insert("result".to_string(), value);
+            map
+        }
+    });
+    result.meta = meta;
+
+    Ok(result)
+}
+
+enum CustomToolInner<'a> {
+    Text(&'a (dyn CustomTextTool + Send + Sync)),
+    Structured(&'a (dyn CustomStructuredTool + Send + Sync)),
+    BlockingStructured(&'a (dyn BlockingCustomStructuredTool + Send + Sync)),
+    AsyncText(&'a (dyn AsyncCustomTextTool + Send + Sync)),
+    ChannelText(&'a (dyn ChannelCustomTextTool + Send + Sync)),
+    AsyncStructured(&'a (dyn AsyncCustomStructuredTool + Send + Sync)),
+    Audio(&'a (dyn CustomAudioTool + Send + Sync)),
+    AsyncAudio(&'a (dyn AsyncCustomAudioTool + Send + Sync)),
+    EmbeddedResource(&'a (dyn CustomEmbeddedResourceTool + Send + Sync)),
+    AsyncEmbeddedResource(&'a (dyn AsyncCustomEmbeddedResourceTool + Send + Sync)),
+}
+
+pub struct CustomTool<'a> {
+    inner: CustomToolInner<'a>,
+}
+
+impl<'a> CustomTool<'a> {
+    pub fn text<T, O>(tool: &'a T) -> Self
+    where
+        T: TextTool<Output = O> + Send + Sync,
+        O: IntoTextToolResult,
+    {
+        Self {
+            inner: CustomToolInner::Text(tool),
+        }
+    }
+
+    pub fn structured<T>(tool: &'a T) -> Self
+    where
+        T: StructuredTool + Send + Sync,
+        T::Output: IntoStructuredToolResult,
+    {
+        Self {
+            inner: CustomToolInner::Structured(tool),
+        }
+    }
+
+    /// Runs a [`StructuredTool`] on the blocking thread pool of the async runtime, with
+    /// [`tokio::task::spawn_blocking`](https://docs.rs/tokio/latest/tokio/task/fn.spawn_blocking.html).
+    ///
+    /// Synchronous tools otherwise run on the threads of the async runtime, where a long
+    /// computation delays every other request. Use this for CPU-bound tools or tools making
+    /// blocking calls that take more than a few milliseconds: for quick tools, the cost of
+    /// moving the call to another thread outweighs the benefits. The tool is cloned to be
+    /// moved to the blocking thread.
+    ///
+    /// A call abandoned because it exceeded the duration set with
+    /// [`ServerBuilder::with_max_call_duration`](crate::server_prelude::ServerBuilder::with_max_call_duration)
+    /// returns its error right away, but the blocking thread can't be stopped and keeps running
+    /// the tool until it returns.
+    pub fn blocking_structured<T>(tool: &'a T) -> Self
+    where
+        T: StructuredTool + Clone + Send + Sync + 'static,
+        T::Output: IntoStructuredToolResult,
+    {
+        Self {
+            inner: CustomToolInner::BlockingStructured(tool),
+        }
+    }
+
+    pub fn async_text<T, O>(tool: &'a T) -> Self
+    where
+        T: AsyncTextTool<Output = O> + Send + Sync,
+        O: IntoTextToolResult,
+    {
+        Self {
+            inner: CustomToolInner::AsyncText(tool),
+        }
+    }
+
+    pub fn channel_text<T>(tool: &'a T) -> Self
+    where
+        T: ChannelTextTool + Send + Sync,
+    {
+        Self {
+            inner: CustomToolInner::ChannelText(tool),
+        }
+    }
+
+    pub fn async_structured<T>(tool: &'a T) -> Self
+    where
+        T: AsyncStructuredTool + Send + Sync,
+        T::Output: IntoStructuredToolResult,
+    {
+        Self {
+            inner: CustomToolInner::AsyncStructured(tool),
+        }
+    }
+
+    pub fn audio<T>(tool: &'a T) -> Self
+    where
+        T: AudioTool + Send + Sync,
+        T::Output: IntoAudioToolResult,
+    {
+        Self {
+            inner: CustomToolInner::Audio(tool),
+        }
+    }
+
+    pub fn async_audio<T>(tool: &'a T) -> Self
+    where
+        T: AsyncAudioTool + Send + Sync,
+        T::Output: IntoAudioToolResult,
+    {
+        Self {
+            inner: CustomToolInner::AsyncAudio(tool),
+        }
+    }
+
+    pub fn embedded_resource<T>(tool: &'a T) -> Self
+    where
+        T: EmbeddedResourceTool + Send + Sync,
+        T::Output: IntoResourceToolResult,
+    {
+        Self {
+            inner: CustomToolInner::EmbeddedResource(tool),
+        }
+    }
+
+    pub fn async_embedded_resource<T>(tool: &'a T) -> Self
+    where
+        T: AsyncEmbeddedResourceTool + Send + Sync,
+        T::Output: IntoResourceToolResult,
+    {
+        Self {
+            inner: CustomToolInner::AsyncEmbeddedResource(tool),
+        }
+    }
+
+    /// Returns whether a call to the tool can be abandoned while it runs: synchronous tools run
+    /// to completion once called. Abandoning a blocking tool only stops waiting for it, since
+    /// its thread keeps running the tool until it returns.
+    pub(crate) fn is_interruptible(&self) -> bool {
+        !matches!(
+            self.inner,
+            CustomToolInner::Text(_)
+                | CustomToolInner::Structured(_)
+                | CustomToolInner::Audio(_)
+                | CustomToolInner::EmbeddedResource(_)
+        )
+    }
+
+    pub async fn call(&self) -> Result<CallToolResult, CallToolError> {
+        match self.inner {
+            CustomToolInner::Text(tool) => tool.call().await,
+            CustomToolInner::Structured(tool) => tool.call().await,
+            CustomToolInner::BlockingStructured(tool) => tool.call().await,
+            CustomToolInner::AsyncText(tool) => tool.call().await,
+            CustomToolInner::ChannelText(tool) => tool.call().await,
+            CustomToolInner::AsyncStructured(tool) => tool.call().await,
+            CustomToolInner::Audio(tool) => tool.call().await,
+            CustomToolInner::AsyncAudio(tool) => tool.call().await,
+            CustomToolInner::EmbeddedResource(tool) => tool.call().await,
+            CustomToolInner::AsyncEmbeddedResource(tool) => tool.call().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use rust_mcp_sdk::schema::ContentBlock;
+
+    struct Greet {
+        name: &'static str,
+    }
+
+    #[async_trait]
+    impl AsyncTextTool for Greet {
+        type Output = String;
+
+        async fn call(&self) -> Self::Output {
+            format!("Hello, {}!", self.name)
+        }
+    }
+
+    struct GreetMany {
+        names: Vec<&'static str>,
+    }
+
+    #[async_trait]
+    impl AsyncTextTool for GreetMany {
+        type Output = TextBatch;
+
+        async fn call(&self) -> Self::Output {
+            let inputs = self.names.iter().map(|&name| Greet { name }).collect();
+            Greet::call_batch(inputs).await
+        }
+    }
+
+    #[tokio::test]
+    async fn call_batch_produces_one_text_block_per_input() {
+        let tool = GreetMany {
+            names: vec!["Ada", "Grace", "Linus"],
+        };
+
+        let result = CustomTool::async_text(&tool).call().await.unwrap();
+
+        let texts: Vec<_> = result
+            .content
+            .iter()
+            .map(|content| match content {
+                ContentBlock::TextContent(text) => text.text.as_str(),
+                _ => panic!("expected text content"),
+            })
+            .collect();
+        assert_eq!(texts, ["Hello, Ada!", "Hello, Grace!", "Hello, Linus!"]);
+    }
+
+    struct Tail {
+        lines: Vec<&'static str>,
+    }
+
+    #[async_trait]
+    impl ChannelTextTool for Tail {
+        async fn call(&self) -> Result<mpsc::Receiver<String>, ToolError> {
+            let (sender, receiver) = mpsc::channel(1);
+            let lines = self.lines.clone();
+
+            tokio::spawn(async move {
+                for line in lines {
+                    if sender.send(line.to_owned()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            Ok(receiver)
+        }
+    }
+
+    #[tokio::test]
+    async fn channel_text_tool_emits_each_line_as_progress() {
+        use crate::context::{ProgressNotifier, TransportKind};
+        use futures::FutureExt;
+        use rust_mcp_sdk::schema::ProgressToken;
+
+        let tool = Tail {
+            lines: vec!["starting", "listening", "stopping"],
+        };
+        let emitted = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let notifier = {
+            let emitted = emitted.clone();
+            ProgressNotifier(Arc::new(move |params| {
+                emitted
+                    .lock()
+                    .unwrap()
+                    .push((params.progress, params.message.unwrap()));
+                async {}.boxed()
+            }))
+        };
+        let context = ToolContext::new(None, TransportKind::Http)
+            .with_progress_token(Some(ProgressToken::String("tail-1".to_owned())))
+            .with_progress_notifier(notifier);
+
+        let result = context
+            .scope(CustomTool::channel_text(&tool).call())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *emitted.lock().unwrap(),
+            [
+                (1.0, "starting".to_owned()),
+                (2.0, "listening".to_owned()),
+                (3.0, "stopping".to_owned()),
+            ]
+        );
+        let texts: Vec<_> = result
+            .content
+            .iter()
+            .map(|content| content.as_text_content().unwrap().text.as_str())
+            .collect();
+        assert_eq!(texts, ["starting", "listening", "stopping"]);
+    }
+
+    struct Divide {
+        dividend: i64,
+        divisor: i64,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AsyncTextTool for Divide {
+        type Output = Result<String, ToolError>;
+
+        async fn call(&self) -> Self::Output {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+
+            match self.divisor {
+                0 => Err(ToolError::from("division by zero")),
+                divisor if divisor < 0 => Err(ToolError::from("negative divisor").fatal()),
+                divisor => Ok((self.dividend / divisor).to_string()),
+            }
+        }
+    }
+
+    fn divide_batch(divisors: &[i64], calls: &Arc<AtomicUsize>) -> Vec<Divide> {
+        divisors
+            .iter()
+            .map(|&divisor| Divide {
+                dividend: 12,
+                divisor,
+                calls: calls.clone(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn call_batch_reports_errors_per_item() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let batch = Divide::call_batch(divide_batch(&[3, 0, 4], &calls)).await;
+
+        let texts: Vec<_> = batch
+            .text_contents()
+            .unwrap()
+            .into_iter()
+            .map(|content| content.text)
+            .collect();
+        assert_eq!(texts, ["4", "error: division by zero", "3"]);
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn call_batch_stops_at_fatal_error() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut batch = Divide::call_batch(divide_batch(&[3, 0, -1, 4], &calls)).await;
+
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        assert!(batch.is_aborted());
+        assert!(!batch.push("ignored"));
+        assert_eq!(
+            batch.text_contents().unwrap_err().to_string(),
+            "negative divisor"
+        );
+    }
+
+    #[derive(Clone)]
+    struct CountPrimes {
+        below: u64,
+    }
+
+    impl StructuredTool for CountPrimes {
+        type Output = serde_json::Value;
+
+        fn call(&self) -> Self::Output {
+            let count = (2..self.below)
+                .filter(|&n| (2..n).take_while(|d| d * d <= n).all(|d| n % d != 0))
+                .count();
+            serde_json::json!({ "count": count })
+        }
+    }
+
+    #[tokio::test]
+    async fn blocking_structured_tool_matches_direct_call() {
+        let tool = CountPrimes { below: 100_000 };
+
+        let blocking = CustomTool::blocking_structured(&tool).call().await.unwrap();
+        let direct = CustomTool::structured(&tool).call().await.unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&blocking).unwrap(),
+            serde_json::to_value(&direct).unwrap()
+        );
+        assert_eq!(
+            blocking.structured_content.unwrap()["count"],
+            serde_json::json!(9592)
+        );
+    }
+
+    #[derive(Clone)]
+    struct ReportTransport;
+
+    impl StructuredTool for ReportTransport {
+        type Output = serde_json::Value;
+
+        fn call(&self) -> Self::Output {
+            let transport = ToolContext::current().map(|context| context.transport_kind());
+            serde_json::json!({ "transport": format!("{:?}", transport) })
+        }
+    }
+
+    #[tokio::test]
+    async fn blocking_structured_tool_reads_the_context() {
+        use crate::context::TransportKind;
+
+        let result = ToolContext::new(None, TransportKind::Http)
+            .scope(CustomTool::blocking_structured(&ReportTransport).call())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.structured_content.unwrap()["transport"],
+            "Some(Http)"
+        );
+    }
+
+    struct Beep;
+
+    impl AudioTool for Beep {
+        type Output = Audio;
+
+        fn call(&self) -> Self::Output {
+            Audio::new(b"RIFF\x24\0\0\0WAVE".as_slice(), "audio/wav")
+        }
+    }
+
+    #[tokio::test]
+    async fn audio_tool_produces_base64_audio_content() {
+        let result = CustomTool::audio(&Beep).call().await.unwrap();
+
+        match &result.content[..] {
+            [ContentBlock::AudioContent(audio)] => {
+                assert_eq!(audio.mime_type, "audio/wav");
+                assert_eq!(audio.data, "UklGRiQAAABXQVZF");
+            }
+            _ => panic!("expected a single audio content block"),
+        }
+    }
+
+    struct Withdraw {
+        explicit_code: Option<i64>,
+    }
+
+    impl TextTool for Withdraw {
+        type Output = Result<String, ToolError>;
+
+        fn call(&self) -> Self::Output {
+            let error = ToolError::from("insufficient funds");
+
+            Err(match self.explicit_code {
+                Some(code) => error.with_code(code),
+                None => error,
+            })
+        }
+
+        fn error_code(&self) -> Option<i64> {
+            Some(1001)
+        }
+    }
+
+    fn error_code(error: &CallToolError) -> Option<i64> {
+        error
+            .0
+            .downcast_ref::<ToolError>()
+            .and_then(ToolError::code)
+    }
+
+    #[tokio::test]
+    async fn tool_error_code_is_used_for_errors_without_code() {
+        let tool = Withdraw {
+            explicit_code: None,
+        };
+
+        let error = CustomTool::text(&tool).call().await.unwrap_err();
+
+        assert_eq!(error.to_string(), "insufficient funds");
+        assert_eq!(error_code(&error), Some(1001));
+    }
+
+    #[tokio::test]
+    async fn explicit_error_code_takes_precedence() {
+        let tool = Withdraw {
+            explicit_code: Some(1002),
+        };
+
+        let error = CustomTool::text(&tool).call().await.unwrap_err();
+
+        assert_eq!(error_code(&error), Some(1002));
+    }
+
+    struct Inventory;
+
+    impl TextTool for Inventory {
+        type Output = serde_json::Value;
+
+        fn call(&self) -> Self::Output {
+            serde_json::json!({ "apples": 3 })
+        }
+    }
+
+    #[tokio::test]
+    async fn json_value_output_produces_pretty_text() {
+        let result = CustomTool::text(&Inventory).call().await.unwrap();
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "{\n  \"apples\": 3\n}"
+        );
+        assert_eq!(result.structured_content, None);
+    }
+
+    struct ReadConfig;
+
+    #[async_trait]
+    impl AsyncEmbeddedResourceTool for ReadConfig {
+        type Output = Result<Resource, ToolError>;
+
+        async fn call(&self) -> Self::Output {
+            Ok(Resource::text("file:///config.toml", "debug = true").with_mime_type("text/toml"))
+        }
+    }
+
+    struct ReadIcon;
+
+    impl EmbeddedResourceTool for ReadIcon {
+        type Output = Resource;
+
+        fn call(&self) -> Self::Output {
+            Resource::blob("file:///icon.bin", b"icon".as_slice())
+        }
+    }
+
+    #[tokio::test]
+    async fn embedded_resource_tool_produces_text_resource() {
+        let result = CustomTool::async_embedded_resource(&ReadConfig)
+            .call()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&result.content).unwrap(),
+            serde_json::json!([{
+                "type": "resource",
+                "resource": {
+                    "uri": "file:///config.toml",
+                    "mimeType": "text/toml",
+                    "text": "debug = true",
+                },
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn embedded_resource_tool_produces_base64_blob_resource() {
+        let result = CustomTool::embedded_resource(&ReadIcon)
+            .call()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&result.content).unwrap(),
+            serde_json::json!([{
+                "type": "resource",
+                "resource": {
+                    "uri": "file:///icon.bin",
+                    "blob": "aWNvbg==",
+                },
+            }])
+        );
+    }
+
+    struct Reminder;
+
+    impl TextTool for Reminder {
+        type Output = Annotated<&'static str>;
+
+        fn call(&self) -> Self::Output {
+            Annotated::new("remember to be nice")
+                .with_audience([Role::Assistant])
+                .with_priority(0.8)
+        }
+    }
+
+    #[tokio::test]
+    async fn annotated_text_output_keeps_annotations() {
+        let result = CustomTool::text(&Reminder).call().await.unwrap();
+
+        match &result.content[..] {
+            [ContentBlock::TextContent(text)] => {
+                let annotations = text.annotations.as_ref().unwrap();
+                assert_eq!(text.text, "remember to be nice");
+                assert_eq!(annotations.audience, [Role::Assistant]);
+                assert_eq!(annotations.priority, Some(0.8));
+            }
+            _ => panic!("expected a single text content block"),
+        }
+    }
+
+    struct Score;
+
+    impl StructuredTool for Score {
+        type Output = Annotated<u32>;
+
+        fn call(&self) -> Self::Output {
+            Annotated::new(42).with_audience([Role::User])
+        }
+    }
+
+    #[tokio::test]
+    async fn annotated_structured_output_keeps_annotations() {
+        let result = CustomTool::structured(&Score).call().await.unwrap();
+
+        match &result.content[..] {
+            [ContentBlock::TextContent(text)] => {
+                assert_eq!(text.text, "42");
+                assert_eq!(text.annotations.as_ref().unwrap().audience, [Role::User]);
+            }
+            _ => panic!("expected a single text content block"),
+        }
+        assert_eq!(result.structured_content.unwrap()["result"], 42);
+    }
+
+    struct Lookup;
+
+    impl StructuredTool for Lookup {
+        type Output = WithMeta<Annotated<u32>>;
+
+        fn call(&self) -> Self::Output {
+            WithMeta::new(Annotated::new(7).with_audience([Role::User]))
+                .with_entry("source", "cache")
+        }
+    }
+
+    #[derive(Debug, rust_mcp_sdk::macros::JsonSchema, Serialize, mcp_utils_derive::ToolOutput)]
+    struct Forecast {
+        temperature: f64,
+        summary: Option<String>,
+    }
+
+    #[test]
+    fn derived_tool_output_provides_the_value_and_the_schema() {
+        let forecast = Forecast {
+            temperature: 21.5,
+            summary: Some("sunny".to_owned()),
+        };
+
+        assert_eq!(
+            forecast.result().unwrap(),
+            serde_json::json!({ "temperature": 21.5, "summary": "sunny" })
+        );
+
+        let schema = serde_json::to_value(Forecast::output_schema()).unwrap();
+        assert_eq!(
+            schema,
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "temperature": { "type": "number" },
+                    "summary": { "type": "string", "nullable": true },
+                },
+                "required": ["temperature"],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn structured_output_with_meta_sets_result_meta() {
+        let result = CustomTool::structured(&Lookup).call().await.unwrap();
+
+        let round_trip: CallToolResult =
+            serde_json::from_value(serde_json::to_value(&result).unwrap()).unwrap();
+
+        assert_eq!(
+            round_trip.meta,
+            serde_json::json!({ "source": "cache" })
+                .as_object()
+                .cloned()
+        );
+        assert_eq!(round_trip.structured_content.unwrap()["result"], 7);
+        assert_eq!(
+            round_trip.content[0]
+                .as_text_content()
+                .unwrap()
+                .annotations
+                .as_ref()
+                .unwrap()
+                .audience,
+            [Role::User]
+        );
+    }
+
+    #[cfg(feature = "preserve-order")]
+    #[tokio::test]
+    async fn structured_output_preserves_field_order() {
+        #[derive(Serialize)]
+        struct Forecast {
+            zone: &'static str,
+            temperature: i32,
+            alert: bool,
+        }
+
+        struct GetForecast;
+
+        impl StructuredTool for GetForecast {
+            type Output = Forecast;
+
+            fn call(&self) -> Self::Output {
+                Forecast {
+                    zone: "north",
+                    temperature: 12,
+                    alert: false,
+                }
+            }
+        }
+
+        let result = CustomTool::structured(&GetForecast).call().await.unwrap();
+
+        let keys: Vec<_> = result
+            .structured_content
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, ["zone", "temperature", "alert"]);
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "{\n  \"zone\": \"north\",\n  \"temperature\": 12,\n  \"alert\": false\n}"
+        );
+    }
+
+    struct ListColors;
+
+    impl TextTool for ListColors {
+        type Output = Vec<String>;
+
+        fn call(&self) -> Self::Output {
+            vec!["red".to_owned(), "green".to_owned(), "blue".to_owned()]
+        }
+    }
+
+    #[tokio::test]
+    async fn vec_output_produces_one_text_block_per_item() {
+        let result = CustomTool::text(&ListColors).call().await.unwrap();
+
+        let texts: Vec<_> = result
+            .content
+            .iter()
+            .map(|content| content.as_text_content().unwrap().text.as_str())
+            .collect();
+        assert_eq!(texts, ["red", "green", "blue"]);
+    }
+
+    #[test]
+    fn test_base64_output_encodes_the_bytes() {
+        let output = Base64::new(b"\x1f\x8b\x08hello".as_slice());
+
+        assert_eq!(output.result().unwrap(), "H4sIaGVsbG8=");
+    }
+
+    #[test]
+    fn test_tool_error_from_io_error() {
+        let error = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
+
+        assert_eq!(ToolError::from(error).to_string(), "file not found");
+    }
+
+    #[test]
+    fn test_tool_error_from_serde_json_error() {
+        let error = serde_json::from_str::<i32>("oops").unwrap_err();
+        let expected = error.to_string();
+
+        assert_eq!(ToolError::from(error).to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialization_error_mentions_the_output_type() {
+        struct Unserializable;
+
+        impl Serialize for Unserializable {
+            fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom(
+                    "file handles can't be serialized",
+                ))
+            }
+        }
+
+        assert_eq!(
+            IntoStructuredToolResult::result(Unserializable)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "unable to serialize the output of type `{}`: file handles can't be serialized",
+                std::any::type_name::<Unserializable>()
+            )
+        );
+
+        #[derive(Serialize)]
+        struct Report {
+            entries: Vec<Entry>,
+        }
+
+        #[derive(Serialize)]
+        struct Entry {
+            file: Unserializable,
+        }
+
+        assert_eq!(
+            IntoStructuredToolResult::result(Report {
+                entries: vec![Entry {
+                    file: Unserializable
+                }],
+            })
+            .unwrap_err()
+            .to_string(),
+            format!(
+                "unable to serialize the output of type `{}` at `entries[0].file`: file handles can't be serialized",
+                std::any::type_name::<Report>()
+            )
+        );
+    }
+
+    #[test]
+    fn test_result_with_io_error_converts_into_tool_error() {
+        let result: Result<String, std::io::Error> = Err(std::io::Error::other("disk full"));
+
+        assert_eq!(result.result().unwrap_err().to_string(), "disk full");
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_tool_error_from_reqwest_error_includes_sources() {
+        let error = reqwest::Client::new().get("not a url").build().unwrap_err();
+
+        assert_eq!(
+            ToolError::from(error).to_string(),
+            "builder error: relative URL without a base"
+        );
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn test_tool_error_from_anyhow_error_includes_context() {
+        use anyhow::Context;
+
+        let error = Err::<(), _>(anyhow::anyhow!("connection refused"))
+            .context("unable to fetch data")
+            .unwrap_err();
+
+        assert_eq!(
+            ToolError::from(error).to_string(),
+            "unable to fetch data: connection refused"
+        );
     }
 }
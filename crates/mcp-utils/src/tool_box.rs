@@ -1,45 +1,169 @@
+use std::collections::{HashMap, HashSet};
+
+use rust_mcp_sdk::schema::{CallToolRequestParams, Tool, schema_utils::CallToolError};
+
 use crate::tool::CustomTool;
 
 #[macro_export]
 macro_rules! setup_tools {
-    ($visibility:vis $enum_name:ident, [$($tool_kind:ident ( $tool:ident ) ),* $(,)?]) => {
+    (@name $tool:ident [as $name:literal $($modifier:tt)*]) => {
+        ::std::string::String::from($name)
+    };
+    (@name $tool:ident [$($modifier:tt)*]) => {
+        $tool::tool_name()
+    };
+    (@aliases [$(as $name:literal)? aliases [$($alias:literal),* $(,)?] $($modifier:tt)*]) => {{
+        let aliases: &[&str] = &[$($alias),*];
+        aliases
+    }};
+    (@aliases [$($modifier:tt)*]) => {{
+        let aliases: &[&str] = &[];
+        aliases
+    }};
+    (@hidden [$(as $name:literal)? $(aliases [$($alias:literal),* $(,)?])? hidden]) => {
+        true
+    };
+    (@hidden [$(as $name:literal)? $(aliases [$($alias:literal),* $(,)?])?]) => {
+        false
+    };
+    ($visibility:vis $enum_name:ident, [$($tool_kind:ident ( $tool:ident $($modifier:tt)* ) $(if $cfg:meta)?),* $(,)?]) => {
+        #[derive(Debug)]
         $visibility struct $enum_name {
             inner: __tool_setup::InnerTools,
         }
 
+        impl $enum_name {
+            /// Returns the name under which the selected tool is exposed.
+            #[allow(dead_code)]
+            $visibility fn tool_name(&self) -> &'static str {
+                match self.inner {
+                    $(
+                        $(#[cfg($cfg)])?
+                        __tool_setup::InnerTools::$tool(_) => {
+                            static NAME: ::std::sync::OnceLock<::std::string::String> = ::std::sync::OnceLock::new();
+                            NAME.get_or_init(|| $crate::setup_tools!(@name $tool [$($modifier)*])).as_str()
+                        }
+                    )*
+                }
+            }
+        }
+
         mod __tool_setup {
+            #[allow(unused_imports)]
             use super::*;
 
-            rust_mcp_sdk::tool_box!(InnerTools, [$($tool),*]);
+            #[derive(Debug)]
+            #[allow(clippy::enum_variant_names)]
+            pub enum InnerTools {
+                $(
+                    $(#[cfg($cfg)])?
+                    $tool($tool),
+                )*
+            }
         }
 
         impl $crate::server_prelude::ToolBox for $enum_name {
-            fn get_tool(&self) -> $crate::tool_prelude::CustomTool {
-                match &self.inner {
+            fn get_tool(&self) -> $crate::tool_prelude::CustomTool<'_> {
+                match self.inner {
                     $(
-                        __tool_setup::InnerTools::$tool(tool_value) => $crate::tool_prelude::CustomTool::$tool_kind(tool_value),
+                        $(#[cfg($cfg)])?
+                        __tool_setup::InnerTools::$tool(ref tool_value) => $crate::tool_prelude::CustomTool::$tool_kind(tool_value),
                     )*
                 }
             }
 
             fn get_tools() -> Vec<rust_mcp_sdk::schema::Tool> {
-                __tool_setup::InnerTools::tools()
+                static TOOLS: ::std::sync::OnceLock<Vec<rust_mcp_sdk::schema::Tool>> = ::std::sync::OnceLock::new();
+
+                TOOLS.get_or_init(|| {
+                    #[allow(unused_mut)]
+                    let mut tools = Vec::new();
+                    $(
+                        $(#[cfg($cfg)])?
+                        if !$crate::setup_tools!(@hidden [$($modifier)*]) {
+                            tools.push(rust_mcp_sdk::schema::Tool {
+                                name: $crate::setup_tools!(@name $tool [$($modifier)*]),
+                                ..$tool::tool()
+                            });
+                        }
+                    )*
+                    tools
+                }).clone()
+            }
+
+            fn tool_count() -> usize {
+                const COUNT: usize = {
+                    #[allow(unused_mut)]
+                    let mut count = 0;
+                    $(
+                        $(#[cfg($cfg)])?
+                        if !$crate::setup_tools!(@hidden [$($modifier)*]) {
+                            count += 1;
+                        }
+                    )*
+                    count
+                };
+
+                COUNT
+            }
+
+            fn get_hidden_tools() -> Vec<rust_mcp_sdk::schema::Tool> {
+                static TOOLS: ::std::sync::OnceLock<Vec<rust_mcp_sdk::schema::Tool>> = ::std::sync::OnceLock::new();
+
+                TOOLS.get_or_init(|| {
+                    #[allow(unused_mut)]
+                    let mut tools = Vec::new();
+                    $(
+                        $(#[cfg($cfg)])?
+                        if $crate::setup_tools!(@hidden [$($modifier)*]) {
+                            tools.push(rust_mcp_sdk::schema::Tool {
+                                name: $crate::setup_tools!(@name $tool [$($modifier)*]),
+                                ..$tool::tool()
+                            });
+                        }
+                    )*
+                    tools
+                }).clone()
+            }
+
+            fn get_aliases() -> Vec<(String, String)> {
+                #[allow(unused_mut)]
+                let mut aliases = Vec::new();
+                $(
+                    $(#[cfg($cfg)])?
+                    for alias in $crate::setup_tools!(@aliases [$($modifier)*]) {
+                        aliases.push(((*alias).to_owned(), $crate::setup_tools!(@name $tool [$($modifier)*])));
+                    }
+                )*
+                aliases
             }
         }
 
         impl TryFrom<rust_mcp_sdk::schema::CallToolRequestParams> for $enum_name {
             type Error = rust_mcp_sdk::schema::schema_utils::CallToolError;
 
-            fn try_from(mut value: rust_mcp_sdk::schema::CallToolRequestParams) -> Result<Self, Self::Error> {
-                value.arguments.get_or_insert_default();
-                Ok(Self {
-                    inner: __tool_setup::InnerTools::try_from(value)?,
-                })
+            fn try_from(value: rust_mcp_sdk::schema::CallToolRequestParams) -> Result<Self, Self::Error> {
+                $(
+                    $(#[cfg($cfg)])?
+                    if value.name == $crate::setup_tools!(@name $tool [$($modifier)*])
+                        || $crate::setup_tools!(@aliases [$($modifier)*]).contains(&value.name.as_str())
+                    {
+                        let arguments = serde_json::Value::Object(value.arguments.unwrap_or_default());
+
+                        return serde_json::from_value(arguments)
+                            .map(|tool| Self {
+                                inner: __tool_setup::InnerTools::$tool(tool),
+                            })
+                            .map_err(rust_mcp_sdk::schema::schema_utils::CallToolError::new);
+                    }
+                )*
+
+                Err(rust_mcp_sdk::schema::schema_utils::CallToolError::unknown_tool(value.name))
             }
         }
     };
-    ($enum_name:ident, [$($tool_kind:ident ( $tool:ident ) ),* $(,)?]) => {
-        setup_tools!(pub(crate) $enum_name, [$($tool_kind ( $tool ) ),*]);
+    ($enum_name:ident, [$($tool_kind:ident ( $tool:ident $($modifier:tt)* ) $(if $cfg:meta)?),* $(,)?]) => {
+        setup_tools!(pub(crate) $enum_name, [$($tool_kind ( $tool $($modifier)* ) $(if $cfg)?),*]);
     };
 }
 pub use setup_tools;
@@ -47,5 +171,750 @@ pub use setup_tools;
 pub trait ToolBox {
     fn get_tool(&'_ self) -> CustomTool<'_>;
 
+    /// Returns the tools listed to clients. The tool boxes generated by [`setup_tools!`] build
+    /// the tools once, so that listing them again does not recompute their schemas, but each
+    /// call returns a copy of them. The server indexes the tools when it starts instead of
+    /// calling this function for each tool call.
     fn get_tools() -> Vec<rust_mcp_sdk::schema::Tool>;
+
+    /// Returns the number of tools listed by [`get_tools`](Self::get_tools). The tool boxes
+    /// generated by [`setup_tools!`] return a constant without building the tools.
+    fn tool_count() -> usize {
+        Self::get_tools().len()
+    }
+
+    /// Returns the aliases of the tools, as pairs of an alias and the name of the tool it
+    /// refers to. Calls to an alias are routed to the tool, but aliases are not listed by
+    /// [`get_tools`](Self::get_tools).
+    fn get_aliases() -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Returns the tools that are not listed by [`get_tools`](Self::get_tools), but that can
+    /// still be called by name. Tools are hidden with the `hidden` modifier of
+    /// [`setup_tools!`]:
+    ///
+    /// ```rust
+    /// use mcp_utils::server_prelude::*;
+    /// # use mcp_utils::tool_prelude::*;
+    /// # #[mcp_tool(name = "search", description = "Searches the documents")]
+    /// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    /// # pub struct SearchTool {}
+    /// # impl TextTool for SearchTool {
+    /// #     type Output = &'static str;
+    /// #     fn call(&self) -> Self::Output { "found" }
+    /// # }
+    /// # #[mcp_tool(name = "reindex", description = "Rebuilds the search index")]
+    /// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    /// # pub struct ReindexTool {}
+    /// # impl TextTool for ReindexTool {
+    /// #     type Output = &'static str;
+    /// #     fn call(&self) -> Self::Output { "done" }
+    /// # }
+    ///
+    /// setup_tools!(pub MyTools, [
+    ///     text(SearchTool),
+    ///     text(ReindexTool hidden),
+    /// ]);
+    ///
+    /// fn main() {
+    ///     assert_eq!(MyTools::get_tools().len(), 1);
+    ///     assert_eq!(MyTools::get_hidden_tools()[0].name, "reindex");
+    /// }
+    /// ```
+    ///
+    /// Hiding a tool only keeps it out of discovery: any client that knows or guesses its
+    /// name can call it. Hidden tools must not be used to restrict access to sensitive
+    /// operations, which need their own authorization checks.
+    fn get_hidden_tools() -> Vec<rust_mcp_sdk::schema::Tool> {
+        Vec::new()
+    }
+
+    /// Returns the tool with the given name, as listed by [`get_tools`](Self::get_tools).
+    ///
+    /// ```rust
+    /// use mcp_utils::server_prelude::*;
+    /// # use mcp_utils::tool_prelude::*;
+    /// # #[mcp_tool(name = "greet", description = "Greets someone")]
+    /// # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    /// # pub struct GreetTool {}
+    /// # impl TextTool for GreetTool {
+    /// #     type Output = &'static str;
+    /// #     fn call(&self) -> Self::Output { "hello" }
+    /// # }
+    ///
+    /// setup_tools!(pub MyTools, [text(GreetTool)]);
+    ///
+    /// fn main() {
+    ///     let tool = MyTools::find_tool("greet").unwrap();
+    ///     assert_eq!(tool.description.as_deref(), Some("Greets someone"));
+    ///
+    ///     assert!(MyTools::find_tool("unknown").is_none());
+    /// }
+    /// ```
+    fn find_tool(name: &str) -> Option<rust_mcp_sdk::schema::Tool> {
+        Self::get_tools().into_iter().find(|tool| tool.name == name)
+    }
+}
+
+/// The order in which tools are listed to clients and in the help output of the command-line
+/// interface, set with
+/// [`ServerBuilder::with_tool_sort`](crate::server_prelude::ServerBuilder::with_tool_sort).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToolSort {
+    /// Tools are listed in the order of the tool box.
+    #[default]
+    Declaration,
+    /// Tools are sorted by name.
+    Alphabetical,
+}
+
+impl ToolSort {
+    /// Sorts the tools in place.
+    pub fn sort(self, tools: &mut [Tool]) {
+        match self {
+            Self::Declaration => {}
+            Self::Alphabetical => tools.sort_by(|a, b| a.name.cmp(&b.name)),
+        }
+    }
+}
+
+/// A [`ToolBox`] that combines the tools of two other tool boxes.
+///
+/// Tools are listed in order: first the tools of `A`, then the tools of `B`. A tool call
+/// is dispatched to the tool box that lists the requested tool name. Tool boxes can be
+/// nested to combine more than two sets of tools.
+///
+/// Tool names must be unique across the combined tool boxes: starting a server with
+/// duplicated tool names fails with an error.
+///
+/// ```rust
+/// use mcp_utils::server_prelude::*;
+///
+/// mod first {
+///     # use mcp_utils::{server_prelude::*, tool_prelude::*};
+///     # #[mcp_tool(name = "first_tool", description = "A first tool")]
+///     # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+///     # pub struct FirstTool {}
+///     # impl TextTool for FirstTool {
+///     #     type Output = &'static str;
+///     #     fn call(&self) -> Self::Output { "first" }
+///     # }
+///     setup_tools!(pub FirstTools, [text(FirstTool)]);
+/// }
+///
+/// mod second {
+///     # use mcp_utils::{server_prelude::*, tool_prelude::*};
+///     # #[mcp_tool(name = "second_tool", description = "A second tool")]
+///     # #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+///     # pub struct SecondTool {}
+///     # impl TextTool for SecondTool {
+///     #     type Output = &'static str;
+///     #     fn call(&self) -> Self::Output { "second" }
+///     # }
+///     setup_tools!(pub SecondTools, [text(SecondTool)]);
+/// }
+///
+/// pub type AllTools = CompositeToolBox<first::FirstTools, second::SecondTools>;
+///
+/// assert_eq!(AllTools::get_tools().len(), 2);
+/// ```
+#[derive(Debug)]
+pub enum CompositeToolBox<A, B> {
+    First(A),
+    Second(B),
+}
+
+impl<A, B> ToolBox for CompositeToolBox<A, B>
+where
+    A: ToolBox,
+    B: ToolBox,
+{
+    fn get_tool(&'_ self) -> CustomTool<'_> {
+        match self {
+            Self::First(tool_box) => tool_box.get_tool(),
+            Self::Second(tool_box) => tool_box.get_tool(),
+        }
+    }
+
+    fn get_tools() -> Vec<rust_mcp_sdk::schema::Tool> {
+        let mut tools = A::get_tools();
+        tools.extend(B::get_tools());
+        tools
+    }
+
+    fn tool_count() -> usize {
+        A::tool_count() + B::tool_count()
+    }
+
+    fn get_hidden_tools() -> Vec<rust_mcp_sdk::schema::Tool> {
+        let mut tools = A::get_hidden_tools();
+        tools.extend(B::get_hidden_tools());
+        tools
+    }
+
+    fn get_aliases() -> Vec<(String, String)> {
+        let mut aliases = A::get_aliases();
+        aliases.extend(B::get_aliases());
+        aliases
+    }
+}
+
+impl<A, B> TryFrom<CallToolRequestParams> for CompositeToolBox<A, B>
+where
+    A: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError>,
+    B: ToolBox + TryFrom<CallToolRequestParams, Error = CallToolError>,
+{
+    type Error = CallToolError;
+
+    fn try_from(value: CallToolRequestParams) -> Result<Self, Self::Error> {
+        if has_tool::<A>(&value.name) {
+            A::try_from(value).map(Self::First)
+        } else if has_tool::<B>(&value.name) {
+            B::try_from(value).map(Self::Second)
+        } else {
+            Err(CallToolError::unknown_tool(value.name))
+        }
+    }
+}
+
+/// The tools of a tool box, listed or hidden, indexed by name with their aliases. It is built
+/// once per server, so that resolving the tool of each call does not copy the tools.
+#[derive(Debug)]
+pub(crate) struct ToolIndex {
+    tools: HashMap<String, Tool>,
+    aliases: HashMap<String, String>,
+    /// The tool names and aliases in lowercase, with the name of the tool they refer to.
+    lowercase_names: HashMap<String, String>,
+}
+
+impl ToolIndex {
+    pub(crate) fn new<T: ToolBox>() -> Self {
+        let tools: HashMap<String, Tool> = T::get_tools()
+            .into_iter()
+            .chain(T::get_hidden_tools())
+            .map(|tool| (tool.name.clone(), tool))
+            .collect();
+        let aliases: HashMap<String, String> = T::get_aliases().into_iter().collect();
+        let lowercase_names = tools
+            .keys()
+            .map(|name| (name.to_ascii_lowercase(), name.clone()))
+            .chain(
+                aliases
+                    .iter()
+                    .map(|(alias, name)| (alias.to_ascii_lowercase(), name.clone())),
+            )
+            .collect();
+
+        Self {
+            tools,
+            aliases,
+            lowercase_names,
+        }
+    }
+
+    /// Returns the name of the tool called with `name`: the tool an alias refers to, or the
+    /// name of the tool as declared when `ignore_case` is set. Other names are returned
+    /// unchanged.
+    pub(crate) fn canonical_name<'a>(&'a self, name: &'a str, ignore_case: bool) -> &'a str {
+        if let Some(tool_name) = self.aliases.get(name) {
+            return tool_name;
+        }
+
+        if ignore_case
+            && !self.tools.contains_key(name)
+            && let Some(tool_name) = self.lowercase_names.get(&name.to_ascii_lowercase())
+        {
+            return tool_name;
+        }
+
+        name
+    }
+
+    /// Returns the definition of the tool named `name`, listed or hidden, following aliases.
+    pub(crate) fn get(&self, name: &str) -> Option<&Tool> {
+        self.tools
+            .get(self.aliases.get(name).map_or(name, String::as_str))
+    }
+}
+
+/// Returns true when the tool box has a tool, a hidden tool or an alias with the given name.
+fn has_tool<T: ToolBox>(name: &str) -> bool {
+    T::find_tool(name).is_some()
+        || T::get_hidden_tools().iter().any(|tool| tool.name == name)
+        || T::get_aliases().iter().any(|(alias, _)| alias == name)
+}
+
+/// Returns the name of each tool whose input schema is not a valid JSON Schema, with the
+/// reason why it is invalid.
+pub(crate) fn find_invalid_tools<T: ToolBox>() -> Vec<(String, String)> {
+    T::get_tools()
+        .into_iter()
+        .chain(T::get_hidden_tools())
+        .filter_map(|tool| {
+            let result = serde_json::to_value(&tool.input_schema)
+                .map_err(|err| err.to_string())
+                .and_then(|schema| {
+                    jsonschema::validator_for(&schema)
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                });
+
+            result.err().map(|reason| (tool.name, reason))
+        })
+        .collect()
+}
+
+/// Returns the name of each listed tool, with the tool prefix, that does not match
+/// `^[a-zA-Z0-9_-]+$`, the names accepted by all clients.
+pub(crate) fn find_nonconforming_tool_names<T: ToolBox>(tool_prefix: &str) -> Vec<String> {
+    T::get_tools()
+        .into_iter()
+        .map(|tool| format!("{}{}", tool_prefix, tool.name))
+        .filter(|name| !is_conforming_tool_name(name))
+        .collect()
+}
+
+fn is_conforming_tool_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Returns the first name shared by several tools or aliases.
+pub(crate) fn find_duplicate_tool_name<T: ToolBox>(ignore_case: bool) -> Option<String> {
+    let mut names = HashSet::new();
+
+    T::get_tools()
+        .into_iter()
+        .chain(T::get_hidden_tools())
+        .map(|tool| tool.name)
+        .chain(T::get_aliases().into_iter().map(|(alias, _)| alias))
+        .find(|name| {
+            let key = if ignore_case {
+                name.to_ascii_lowercase()
+            } else {
+                name.clone()
+            };
+            !names.insert(key)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_mcp_sdk::schema::ContentBlock;
+
+    mod upper {
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "upper", description = "Converts text to uppercase")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct UpperTool {
+            pub text: String,
+        }
+
+        impl TextTool for UpperTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                self.text.to_uppercase()
+            }
+        }
+
+        setup_tools!(pub UpperTools, [text(UpperTool)]);
+    }
+
+    mod lower {
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "lower", description = "Converts text to lowercase")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct LowerTool {
+            pub text: String,
+        }
+
+        impl TextTool for LowerTool {
+            type Output = String;
+
+            fn call(&self) -> Self::Output {
+                self.text.to_lowercase()
+            }
+        }
+
+        setup_tools!(pub LowerTools, [text(LowerTool)]);
+    }
+
+    mod gated {
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "enabled", description = "A tool that is always compiled")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct EnabledTool {}
+
+        impl TextTool for EnabledTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "enabled"
+            }
+        }
+
+        #[mcp_tool(name = "disabled", description = "A tool that is never compiled")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct DisabledTool {}
+
+        impl TextTool for DisabledTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "disabled"
+            }
+        }
+
+        #[mcp_tool(name = "test_only", description = "A tool compiled only in tests")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct TestOnlyTool {}
+
+        impl TextTool for TestOnlyTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "test only"
+            }
+        }
+
+        setup_tools!(pub GatedTools, [
+            text(EnabledTool),
+            text(DisabledTool) if any(),
+            text(TestOnlyTool) if test,
+        ]);
+    }
+
+    mod renamed {
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "original", description = "A tool exposed under another name")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct OriginalTool {}
+
+        impl TextTool for OriginalTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "original"
+            }
+        }
+
+        setup_tools!(pub RenamedTools, [
+            text(OriginalTool as "custom_name"),
+        ]);
+    }
+
+    mod aliased {
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "new_name", description = "A tool that was renamed")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct RenamedTool {}
+
+        impl TextTool for RenamedTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "renamed"
+            }
+        }
+
+        setup_tools!(pub AliasedTools, [
+            text(RenamedTool aliases ["old_name", "older_name"]),
+        ]);
+    }
+
+    mod hidden {
+        use crate::tool_prelude::*;
+
+        #[mcp_tool(name = "public", description = "A tool listed to clients")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct PublicTool {}
+
+        impl TextTool for PublicTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "public"
+            }
+        }
+
+        #[mcp_tool(name = "internal", description = "A tool hidden from clients")]
+        #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+        pub struct InternalTool {}
+
+        impl TextTool for InternalTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "internal"
+            }
+        }
+
+        setup_tools!(pub HiddenTools, [
+            text(PublicTool),
+            text(InternalTool as "maintenance" aliases ["legacy_maintenance"] hidden),
+        ]);
+    }
+
+    mod cached {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use rust_mcp_sdk::schema::Tool;
+
+        use crate::tool_prelude::*;
+
+        pub static BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+        /// A tool written without `mcp_tool`, to count how many times its definition is built.
+        #[derive(Debug, Deserialize)]
+        pub struct CountedTool {}
+
+        impl CountedTool {
+            pub fn tool_name() -> String {
+                "counted".to_owned()
+            }
+
+            pub fn tool() -> Tool {
+                BUILDS.fetch_add(1, Ordering::SeqCst);
+
+                serde_json::from_value(serde_json::json!({
+                    "name": Self::tool_name(),
+                    "inputSchema": { "type": "object" },
+                }))
+                .unwrap()
+            }
+        }
+
+        impl TextTool for CountedTool {
+            type Output = &'static str;
+
+            fn call(&self) -> Self::Output {
+                "counted"
+            }
+        }
+
+        setup_tools!(pub CachedTools, [text(CountedTool)]);
+    }
+
+    use aliased::AliasedTools;
+    use gated::{DisabledTool, GatedTools};
+    use hidden::HiddenTools;
+    use lower::{LowerTool, LowerTools};
+    use renamed::{OriginalTool, RenamedTools};
+    use upper::{UpperTool, UpperTools};
+
+    type CaseTools = CompositeToolBox<UpperTools, LowerTools>;
+    type DuplicatedTools = CompositeToolBox<UpperTools, CaseTools>;
+
+    async fn call_text(params: CallToolRequestParams) -> String {
+        let tools = CaseTools::try_from(params).unwrap();
+        let result = tools.get_tool().call().await.unwrap();
+
+        match &result.content[0] {
+            ContentBlock::TextContent(text) => text.text.clone(),
+            _ => panic!("expected text content"),
+        }
+    }
+
+    fn text_arguments(text: &str) -> serde_json::Map<String, serde_json::Value> {
+        let mut arguments = serde_json::Map::new();
+        arguments.insert("text".to_owned(), text.into());
+        arguments
+    }
+
+    #[test]
+    fn composite_tool_box_lists_tools_of_both_boxes() {
+        let names: Vec<_> = CaseTools::get_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+
+        assert_eq!(names, ["upper", "lower"]);
+    }
+
+    #[tokio::test]
+    async fn composite_tool_box_calls_tool_from_each_box() {
+        let upper =
+            call_text(UpperTool::request_params().with_arguments(text_arguments("Hi"))).await;
+        let lower =
+            call_text(LowerTool::request_params().with_arguments(text_arguments("Hi"))).await;
+
+        assert_eq!(upper, "HI");
+        assert_eq!(lower, "hi");
+    }
+
+    #[test]
+    fn composite_tool_box_rejects_unknown_tool() {
+        let result = CaseTools::try_from(CallToolRequestParams::new("unknown".to_owned()));
+
+        assert_eq!(result.err().unwrap().to_string(), "Unknown tool: unknown");
+    }
+
+    #[test]
+    fn tool_sort_orders_tools_by_name() {
+        let names = |sort: ToolSort| {
+            let mut tools = CaseTools::get_tools();
+            sort.sort(&mut tools);
+            tools.into_iter().map(|tool| tool.name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(ToolSort::Declaration), ["upper", "lower"]);
+        assert_eq!(names(ToolSort::Alphabetical), ["lower", "upper"]);
+    }
+
+    #[test]
+    fn find_duplicate_tool_name_reports_duplicated_name() {
+        assert_eq!(find_duplicate_tool_name::<CaseTools>(false), None);
+        assert_eq!(
+            find_duplicate_tool_name::<DuplicatedTools>(false),
+            Some("upper".to_owned())
+        );
+        assert_eq!(
+            find_duplicate_tool_name::<CompositeToolBox<AliasedTools, AliasedTools>>(false),
+            Some("new_name".to_owned())
+        );
+    }
+
+    #[test]
+    fn setup_tools_excludes_tools_gated_out() {
+        let names: Vec<_> = GatedTools::get_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+
+        assert_eq!(names, ["enabled", "test_only"]);
+    }
+
+    #[test]
+    fn setup_tools_rejects_calls_to_tools_gated_out() {
+        let result = GatedTools::try_from(DisabledTool::request_params());
+
+        assert_eq!(result.err().unwrap().to_string(), "Unknown tool: disabled");
+    }
+
+    #[tokio::test]
+    async fn setup_tools_exposes_tools_under_overridden_name() {
+        let tools = RenamedTools::get_tools();
+        assert_eq!(tools[0].name, "custom_name");
+        assert_eq!(
+            tools[0].description.as_deref(),
+            Some("A tool exposed under another name")
+        );
+
+        let tool_box =
+            RenamedTools::try_from(CallToolRequestParams::new("custom_name".to_owned())).unwrap();
+        let result = tool_box.get_tool().call().await.unwrap();
+
+        assert_eq!(
+            result.content[0].as_text_content().unwrap().text,
+            "original"
+        );
+        assert_eq!(
+            RenamedTools::try_from(OriginalTool::request_params())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Unknown tool: original"
+        );
+    }
+
+    #[tokio::test]
+    async fn setup_tools_routes_aliases_to_the_tool() {
+        let names: Vec<_> = AliasedTools::get_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(names, ["new_name"]);
+        assert_eq!(
+            AliasedTools::get_aliases(),
+            [
+                ("old_name".to_owned(), "new_name".to_owned()),
+                ("older_name".to_owned(), "new_name".to_owned()),
+            ]
+        );
+
+        for name in ["new_name", "old_name", "older_name"] {
+            let tool_box = CompositeToolBox::<UpperTools, AliasedTools>::try_from(
+                CallToolRequestParams::new(name.to_owned()),
+            )
+            .unwrap();
+            let result = tool_box.get_tool().call().await.unwrap();
+
+            assert_eq!(result.content[0].as_text_content().unwrap().text, "renamed");
+        }
+    }
+
+    #[tokio::test]
+    async fn hidden_tools_are_not_listed_but_can_be_called() {
+        let names: Vec<_> = HiddenTools::get_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(names, ["public"]);
+        assert_eq!(HiddenTools::get_hidden_tools()[0].name, "maintenance");
+
+        for name in ["maintenance", "legacy_maintenance"] {
+            let tool_box = CompositeToolBox::<UpperTools, HiddenTools>::try_from(
+                CallToolRequestParams::new(name.to_owned()),
+            )
+            .unwrap();
+            let result = tool_box.get_tool().call().await.unwrap();
+
+            assert_eq!(
+                result.content[0].as_text_content().unwrap().text,
+                "internal"
+            );
+        }
+    }
+
+    #[test]
+    fn tool_count_matches_the_listed_tools() {
+        assert_eq!(CaseTools::tool_count(), CaseTools::get_tools().len());
+        assert_eq!(GatedTools::tool_count(), GatedTools::get_tools().len());
+        assert_eq!(HiddenTools::tool_count(), HiddenTools::get_tools().len());
+        assert_eq!(HiddenTools::tool_count(), 1);
+    }
+
+    #[test]
+    fn setup_tools_builds_the_tools_once() {
+        use cached::{BUILDS, CachedTools};
+
+        let first = CachedTools::get_tools();
+        let second = CachedTools::get_tools();
+
+        assert_eq!(first[0].name, "counted");
+        assert_eq!(second[0].name, "counted");
+        assert_eq!(BUILDS.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn conforming_tool_names_only_use_letters_digits_underscores_and_hyphens() {
+        assert!(is_conforming_tool_name("get_weather-v2"));
+        assert!(!is_conforming_tool_name("get weather"));
+        assert!(!is_conforming_tool_name("weather.get"));
+        assert!(!is_conforming_tool_name("météo"));
+        assert!(!is_conforming_tool_name(""));
+    }
+
+    #[test]
+    fn setup_tools_reports_the_name_of_the_selected_tool() {
+        let upper =
+            UpperTools::try_from(UpperTool::request_params().with_arguments(text_arguments("Hi")))
+                .unwrap();
+        let renamed =
+            RenamedTools::try_from(CallToolRequestParams::new("custom_name".to_owned())).unwrap();
+
+        assert_eq!(upper.tool_name(), "upper");
+        assert_eq!(renamed.tool_name(), "custom_name");
+        assert!(format!("{upper:?}").contains("UpperTool"));
+    }
 }
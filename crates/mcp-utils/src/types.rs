@@ -0,0 +1,132 @@
+use std::{fmt, ops::Deref, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+/// A duration argument written in the [`humantime`](https://docs.rs/humantime/latest/humantime/)
+/// format, like `30s`, `5m` or `1h30m`, the same format as the durations of the command-line
+/// interface.
+///
+/// ```rust
+/// use mcp_utils::tool_prelude::*;
+///
+/// #[mcp_tool(name = "remind", description = "Sets a reminder")]
+/// #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+/// pub struct RemindTool {
+///     /// How long to wait before the reminder
+///     delay: HumanDuration,
+/// }
+///
+/// impl TextTool for RemindTool {
+///     type Output = String;
+///
+///     fn call(&self) -> Self::Output {
+///         format!("reminder set in {} seconds", self.delay.as_secs())
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(humantime::Duration);
+
+impl HumanDuration {
+    /// Returns the JSON Schema of the argument, used by the `JsonSchema` derive of the tools.
+    pub fn json_schema() -> serde_json::Map<String, serde_json::Value> {
+        let mut schema = serde_json::Map::new();
+        schema.insert("type".to_owned(), "string".into());
+        schema.insert(
+            "description".to_owned(),
+            "A duration like `30s`, `5m` or `1h30m`".into(),
+        );
+        schema
+    }
+}
+
+impl Deref for HumanDuration {
+    type Target = Duration;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Duration> for HumanDuration {
+    fn from(duration: Duration) -> Self {
+        Self(duration.into())
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0.into()
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = humantime::DurationError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value.parse().map(Self)
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for HumanDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        value
+            .parse()
+            .map_err(|err| de::Error::custom(format!("invalid duration `{}`: {}", value, err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tool_prelude::*;
+
+    #[mcp_tool(name = "wait", description = "Waits for a while")]
+    #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+    pub struct WaitTool {
+        delay: HumanDuration,
+    }
+
+    #[test]
+    fn durations_are_deserialized_from_humantime_strings() {
+        let tool: WaitTool =
+            serde_json::from_value(serde_json::json!({ "delay": "1h30m" })).unwrap();
+
+        assert_eq!(*tool.delay, std::time::Duration::from_secs(90 * 60));
+        assert_eq!(
+            serde_json::to_value(&tool).unwrap(),
+            serde_json::json!({ "delay": "1h 30m" })
+        );
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        let error =
+            serde_json::from_value::<WaitTool>(serde_json::json!({ "delay": "soon" })).unwrap_err();
+
+        assert!(
+            error.to_string().starts_with("invalid duration `soon`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn duration_arguments_are_strings_in_the_input_schema() {
+        let schema = WaitTool::json_schema();
+
+        assert_eq!(schema["properties"]["delay"]["type"], "string");
+    }
+}
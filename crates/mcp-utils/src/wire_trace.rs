@@ -0,0 +1,148 @@
+use std::{
+    fmt,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use rust_mcp_sdk::{
+    McpObserver,
+    schema::{ClientMessage, ServerMessage},
+};
+use serde::Serialize;
+
+use crate::redaction::redact;
+
+/// The names of the fields whose value is never written to the trace, compared without case
+/// sensitivity.
+const SECRET_FIELDS: &[&str] = &[
+    "access_token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "client_secret",
+    "password",
+    "refresh_token",
+    "secret",
+    "token",
+];
+
+/// Writes the JSON-RPC messages received and sent by the server, one JSON line per message,
+/// set with [`ServerBuilder::with_wire_trace`](crate::server_prelude::ServerBuilder::with_wire_trace).
+#[derive(Clone)]
+pub(crate) struct WireTrace(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl WireTrace {
+    pub(crate) fn new(writer: impl Write + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(Box::new(writer))))
+    }
+
+    fn record(&self, direction: &str, message: &impl Serialize) {
+        let mut message = match serde_json::to_value(message) {
+            Ok(message) => message,
+            Err(err) => {
+                tracing::warn!("unable to trace a message: {}", err);
+                return;
+            }
+        };
+        redact(&mut message, &|field| {
+            SECRET_FIELDS
+                .iter()
+                .any(|secret| secret.eq_ignore_ascii_case(field))
+        });
+
+        let line = serde_json::json!({
+            "timestamp": humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            "direction": direction,
+            "message": message,
+        });
+
+        let mut writer = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(err) = writeln!(writer, "{}", line).and_then(|()| writer.flush()) {
+            tracing::warn!("unable to write to the wire trace: {}", err);
+        }
+    }
+}
+
+impl fmt::Debug for WireTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WireTrace")
+    }
+}
+
+impl McpObserver<ClientMessage, ServerMessage> for WireTrace {
+    fn on_receive(&self, message: &ClientMessage) {
+        self.record("received", message);
+    }
+
+    fn on_send(&self, message: &ServerMessage) {
+        self.record("sent", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct CapturedTrace(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for CapturedTrace {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tool_calls_are_traced_in_both_directions() {
+        let captured = CapturedTrace::default();
+        let trace = WireTrace::new(captured.clone());
+
+        let request: ClientMessage = serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "tools/call",
+            "params": {
+                "name": "login",
+                "arguments": { "user": "ada", "Password": "hunter2" },
+            },
+        }))
+        .unwrap();
+        let response: ServerMessage = serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "result": { "content": [{ "type": "text", "text": "welcome" }] },
+        }))
+        .unwrap();
+
+        trace.on_receive(&request);
+        trace.on_send(&response);
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["direction"], "received");
+        assert_eq!(lines[0]["message"]["method"], "tools/call");
+        assert_eq!(
+            lines[0]["message"]["params"]["arguments"],
+            serde_json::json!({ "user": "ada", "Password": "[redacted]" })
+        );
+        assert_eq!(lines[1]["direction"], "sent");
+        assert_eq!(lines[1]["message"]["id"], 7);
+        assert_eq!(
+            lines[1]["message"]["result"]["content"][0]["text"],
+            "welcome"
+        );
+    }
+}
@@ -20,3 +20,74 @@ fn main() -> Result<(), String> {
 
     mcp_cli_builder::run::<Tools>(server)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_mcp_sdk::schema::CallToolRequestParams;
+
+    #[test]
+    fn find_tool_looks_up_tools_by_name() {
+        let sum = Tools::find_tool("sum").unwrap();
+
+        assert_eq!(sum.name, "sum");
+        assert!(Tools::find_tool("product").is_none());
+    }
+
+    #[test]
+    fn description_overrides_apply_to_listed_tools() {
+        let tools = ServerBuilder::new()
+            .with_tool_description_override("sum", "Additionne une liste de nombres")
+            .list_tools::<Tools>();
+
+        let sum = tools.iter().find(|tool| tool.name == "sum").unwrap();
+        assert_eq!(
+            sum.description.as_deref(),
+            Some("Additionne une liste de nombres")
+        );
+    }
+
+    #[tokio::test]
+    async fn sum_tool_through_in_memory_server() {
+        let client = ServerBuilder::new()
+            .start_in_memory::<Tools>()
+            .await
+            .unwrap();
+
+        let params = CallToolRequestParams::new("sum").with_arguments(
+            serde_json::json!({ "values": [1.5, 2.5, 3.0] })
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+        let result = client.call_tool(params).await.unwrap();
+
+        assert_eq!(
+            result.structured_content,
+            serde_json::json!({ "sum": 7.0 }).as_object().cloned()
+        );
+    }
+
+    #[tokio::test]
+    async fn sum_tool_accepts_stringified_numbers_with_lenient_coercion() {
+        let client = ServerBuilder::new()
+            .with_lenient_argument_coercion(true)
+            .start_in_memory::<Tools>()
+            .await
+            .unwrap();
+
+        let params = CallToolRequestParams::new("sum").with_arguments(
+            serde_json::json!({ "values": ["1.5", "2.5", 3.0] })
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+        let result = client.call_tool(params).await.unwrap();
+
+        assert_eq!(result.is_error, None);
+        assert_eq!(
+            result.structured_content,
+            serde_json::json!({ "sum": 7.0 }).as_object().cloned()
+        );
+    }
+}
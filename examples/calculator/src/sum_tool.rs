@@ -30,6 +30,10 @@ impl StructuredTool for SumTool {
     type Output = SumResult;
 
     fn call(&self) -> Self::Output {
+        if let Some(context) = ToolContext::current() {
+            context.emit_stderr(format_args!("summing {} values", self.values.len()));
+        }
+
         let mut sum = 0.0_f64;
 
         for number in self.values.iter() {
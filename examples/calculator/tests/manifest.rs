@@ -0,0 +1,25 @@
+use std::process::Command;
+
+use serde_json::Value;
+
+#[test]
+fn manifest_lists_the_calculator_tools() {
+    let output = Command::new(env!("CARGO_BIN_EXE_calculator"))
+        .arg("manifest")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let manifest: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(manifest["name"], "calculator");
+    assert_eq!(manifest["title"], "Calculator MCP Server");
+
+    let sum = manifest["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tool| tool["name"] == "sum")
+        .expect("expected a `sum` tool entry");
+    assert!(sum["inputSchema"]["properties"]["values"].is_object());
+}
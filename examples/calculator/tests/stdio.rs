@@ -0,0 +1,80 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+#[test]
+fn stdout_only_carries_json_rpc_messages() {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_calculator"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = server.stdin.take().unwrap();
+    for message in [
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "capabilities": {},
+                "clientInfo": { "name": "test-client", "version": "1.0.0" },
+                "protocolVersion": rust_mcp_sdk::schema::LATEST_PROTOCOL_VERSION,
+            },
+        }),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": { "name": "sum", "arguments": { "values": [1, 2, 3] } },
+        }),
+    ] {
+        writeln!(stdin, "{}", message).unwrap();
+    }
+
+    let stdout = server.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if sender.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut call_result = None;
+    while call_result.is_none() {
+        let line = receiver
+            .recv_timeout(Duration::from_secs(30))
+            .expect("expected a response to the tool call");
+        let message: Value = serde_json::from_str(&line)
+            .unwrap_or_else(|err| panic!("stdout line is not JSON ({err}): {line}"));
+
+        assert_eq!(message["jsonrpc"], "2.0", "{line}");
+        if message["id"] == 2 {
+            call_result = Some(message["result"].clone());
+        }
+    }
+
+    drop(stdin);
+    server.kill().unwrap();
+    server.wait().unwrap();
+    let mut stderr = String::new();
+    server
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+
+    assert_eq!(call_result.unwrap()["structuredContent"]["sum"], 6.0);
+    assert!(stderr.contains("summing 3 values"), "{stderr}");
+}